@@ -0,0 +1,121 @@
+// src/backup.rs
+use crate::error::ApiError;
+use rusqlite::types::{Value as SqlValue, ValueRef};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+/// Every table holding restaurant data, parents before the tables referencing them.
+/// Restoring inserts in this order and wipes in the reverse one
+const BACKUP_TABLES: [&str; 11] = [
+    "tables",
+    "menus",
+    "customers",
+    "settings",
+    "orders",
+    "order_items",
+    "order_item_changes",
+    "audit_log",
+    "recent_adds",
+    "menu_schedule",
+    "menu_snapshots",
+];
+
+/// Triggers recording order item changes. They are off during a restore, the changes are part of the backup
+const ORDER_ITEM_TRIGGERS: [&str; 3] = ["order_items_added", "order_items_changed", "order_items_removed"];
+
+/// All rows of every table in BACKUP_TABLES, each row an object of column name to value
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Backup {
+    pub tables: BTreeMap<String, Vec<Map<String, Value>>>,
+}
+
+impl Backup {
+
+    /// Read every row of every table, in insertion order
+    pub fn create(conn: &rusqlite::Connection) -> rusqlite::Result<Backup> {
+        let mut tables = BTreeMap::new();
+        for table in BACKUP_TABLES {
+            let mut stmt = conn.prepare(&format!("SELECT * FROM {} ORDER BY rowid", table))?;
+            let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+            let rows = stmt.query_map([], |row| {
+                let mut object = Map::new();
+                for (index, column) in columns.iter().enumerate() {
+                    object.insert(column.clone(), to_json(row.get_ref(index)?));
+                }
+                Ok(object)
+            })?;
+            tables.insert(table.to_string(), rows.collect::<rusqlite::Result<_>>()?);
+        }
+        Ok(Backup { tables })
+    }
+
+    /// Replace all data with the backup's and return how many rows were loaded. Tables missing from the backup
+    /// end up empty. Call it inside a transaction so a failure leaves the current data in place
+    pub fn restore(&self, conn: &rusqlite::Connection) -> Result<usize, ApiError> {
+        if let Some(table) = self.tables.keys().find(|table| !BACKUP_TABLES.contains(&table.as_str())) {
+            return Err(ApiError::Validation(format!("unknown table {}", table)));
+        }
+        for trigger in ORDER_ITEM_TRIGGERS {
+            conn.execute(&format!("DROP TRIGGER IF EXISTS {}", trigger), [])?;
+        }
+        for table in BACKUP_TABLES.iter().rev() {
+            conn.execute(&format!("DELETE FROM {}", table), [])?;
+        }
+        let mut restored = 0;
+        for table in BACKUP_TABLES {
+            let columns = table_columns(conn, table)?;
+            for row in self.tables.get(table).into_iter().flatten() {
+                // Only names read back from the schema make it into the statement
+                if let Some(column) = row.keys().find(|column| !columns.contains(column)) {
+                    return Err(ApiError::Validation(format!("unknown column {}.{}", table, column)));
+                }
+                let names: Vec<&str> = row.keys().map(String::as_str).collect();
+                let placeholders: Vec<String> = (1..=names.len()).map(|index| format!("?{}", index)).collect();
+                let values = row.values().map(to_sql).collect::<Result<Vec<_>, _>>()?;
+                conn.execute(
+                    &format!("INSERT INTO {} ({}) VALUES ({})", table, names.join(", "), placeholders.join(", ")),
+                    rusqlite::params_from_iter(values),
+                )?;
+                restored += 1;
+            }
+        }
+        crate::db::create_triggers(conn)?;
+        Ok(restored)
+    }
+}
+
+fn table_columns(conn: &rusqlite::Connection, table: &str) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| row.get(1))?;
+    columns.collect()
+}
+
+fn to_json(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(number) => Value::from(number),
+        ValueRef::Real(number) => Value::from(number),
+        ValueRef::Text(text) => Value::from(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(bytes) => Value::from(bytes.to_vec()),
+    }
+}
+
+fn to_sql(value: &Value) -> Result<SqlValue, ApiError> {
+    match value {
+        Value::Null => Ok(SqlValue::Null),
+        Value::Bool(flag) => Ok(SqlValue::Integer(i64::from(*flag))),
+        Value::Number(number) => match number.as_i64() {
+            Some(integer) => Ok(SqlValue::Integer(integer)),
+            None => Ok(SqlValue::Real(number.as_f64().unwrap_or_default())),
+        },
+        Value::String(text) => Ok(SqlValue::Text(text.clone())),
+        // Blobs are written out as arrays of bytes
+        Value::Array(items) => items.iter()
+            .map(|item| item.as_u64().and_then(|byte| u8::try_from(byte).ok()))
+            .collect::<Option<Vec<u8>>>()
+            .map(SqlValue::Blob)
+            .ok_or_else(|| ApiError::Validation("backup arrays must hold bytes".to_string())),
+        Value::Object(_) => Err(ApiError::Validation("backup values cannot be objects".to_string())),
+    }
+}
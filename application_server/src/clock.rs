@@ -0,0 +1,41 @@
+// src/clock.rs
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where handlers read the current time from, so time dependent rules can be tested at a chosen moment
+pub trait Clock: Send + Sync {
+    /// Current time as seconds since the Unix epoch (UTC)
+    fn now(&self) -> i64;
+}
+
+/// The real time of the server
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs() as i64)
+            .unwrap_or(0)
+    }
+}
+
+/// A clock stopped at the given Unix time
+#[cfg(test)]
+pub struct FixedClock(pub i64);
+
+#[cfg(test)]
+impl Clock for FixedClock {
+    fn now(&self) -> i64 {
+        self.0
+    }
+}
+
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Minutes since midnight (UTC) of a Unix time
+pub fn minute_of_day(timestamp: i64) -> i64 {
+    timestamp.rem_euclid(86_400) / 60
+}
@@ -0,0 +1,63 @@
+// src/config.rs
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+const DEFAULT_DATABASE_URL: &str = "restaurent.db";
+const DEFAULT_BIND_ADDR: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+const DEFAULT_PORT: u16 = 3030;
+
+/// Where the server keeps its data and listens, read from DATABASE_URL, BIND_ADDR and PORT.
+/// Unset or empty variables fall back to restaurent.db, 127.0.0.1 and 3030
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    pub database_url: String,
+    pub bind_addr: IpAddr,
+    pub port: u16,
+}
+
+/// A variable that is set but cannot be used
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    pub var: &'static str,
+    pub value: String,
+    pub expected: &'static str,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} must be {}, got {:?}", self.var, self.expected, self.value)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+
+    pub fn from_env() -> Result<Config, ConfigError> {
+        Config::from_vars(|var| std::env::var(var).ok())
+    }
+
+    /// Build from any source of variables, `var` returns None for unset ones
+    pub(crate) fn from_vars<F>(var: F) -> Result<Config, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let var = |name: &str| var(name).map(|value| value.trim().to_string()).filter(|value| !value.is_empty());
+        let database_url = var("DATABASE_URL")
+            .map(|url| url.strip_prefix("sqlite://").map(String::from).unwrap_or(url))
+            .unwrap_or_else(|| DEFAULT_DATABASE_URL.to_string());
+        let bind_addr = match var("BIND_ADDR") {
+            Some(value) => value.parse().map_err(|_| ConfigError { var: "BIND_ADDR", value, expected: "an IP address" })?,
+            None => DEFAULT_BIND_ADDR,
+        };
+        let port = match var("PORT") {
+            Some(value) => value.parse().map_err(|_| ConfigError { var: "PORT", value, expected: "a port number from 0 to 65535" })?,
+            None => DEFAULT_PORT,
+        };
+        Ok(Config { database_url, bind_addr, port })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_addr, self.port)
+    }
+}
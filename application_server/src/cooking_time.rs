@@ -0,0 +1,37 @@
+// src/cooking_time.rs
+use rand::Rng;
+use std::sync::Arc;
+
+/// Cooking times new order items get, in minutes
+const MIN_COOKING_TIME: i64 = 5;
+const MAX_COOKING_TIME: i64 = 15;
+
+/// Where handlers get the cooking time of a new order item from, so tests can store known values
+pub trait CookingTimeSource: Send + Sync {
+    /// Cooking time in minutes for the next item
+    fn next(&self) -> i64;
+}
+
+/// A random time between MIN_COOKING_TIME and MAX_COOKING_TIME for every item
+pub struct RandomCookingTime;
+
+impl CookingTimeSource for RandomCookingTime {
+    fn next(&self) -> i64 {
+        rand::thread_rng().gen_range(MIN_COOKING_TIME..=MAX_COOKING_TIME)
+    }
+}
+
+/// The same cooking time for every item
+#[cfg(test)]
+pub struct FixedCookingTime(pub i64);
+
+#[cfg(test)]
+impl CookingTimeSource for FixedCookingTime {
+    fn next(&self) -> i64 {
+        self.0
+    }
+}
+
+pub fn random_cooking_time() -> Arc<dyn CookingTimeSource> {
+    Arc::new(RandomCookingTime)
+}
@@ -1,22 +1,261 @@
-use rusqlite::Connection;
-
-pub fn get_db_conn()->Connection{
-    let conn = Connection::open("restaurent.db").expect("Failed to open SQLite connection");
-    conn
-}
-pub fn initialize_db() {
-    println!("Initializing the database...");
-    let conn = Connection::open("restaurent.db").expect("Failed to open SQLite connection");
-    //Enable Foreignkey support
-    conn.execute("PRAGMA foreign_keys = ON;", []).expect("Failed to enable foreign key support");
-    println!("Creating Table table");
-    create_table_table_if_not_exists(&conn).expect("Failed to create Table table");
-    println!("Creating Menu table");
-    create_menu_table_if_not_exists(&conn).expect("Failed to create Table menus");
-    println!("Creating Order table");
-    create_order_table_if_not_exists(&conn).expect("Failed to create Table orders");
-    println!("Creating OrderItem table");
-    create_order_item_table_if_not_exists(&conn).expect("Failed to create Table order_items");
+use crate::slow_queries;
+use rusqlite::{Connection, ErrorCode, InterruptHandle};
+use std::ops::{Deref, DerefMut};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Database file used by the pool, set once by `initialize_db`
+static DB_PATH: OnceLock<String> = OnceLock::new();
+
+/// How long a connection waits on a lock held by another connection before giving up
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Extra attempts made by `with_retry` when the database is still busy after the busy timeout
+const RETRY_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Idle connections kept around for the next requests
+const POOL_MAX_IDLE: usize = 8;
+/// Connections older than this are closed instead of reused, so per connection state (temp tables, caches) does not pile up
+const POOL_MAX_LIFETIME: Duration = Duration::from_secs(30 * 60);
+
+pub fn get_db_conn()->PooledConnection{
+    static POOL: OnceLock<Arc<DbPool>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let path = DB_PATH.get().expect("initialize_db must run before connections are handed out");
+        Arc::new(DbPool::new(path, POOL_MAX_IDLE, POOL_MAX_LIFETIME))
+    })
+        .get()
+        .expect("Failed to open SQLite connection")
+}
+
+/// Debug query parameter that makes every database call of a request fail
+const FAIL_DB_PARAM: &str = "__fail=db";
+
+/// The connection for one request. When failure injection is allowed and the query string asks for it,
+/// this is an empty in-memory database instead, so each query errors and the handler takes its 500 path
+pub fn conn_for_request<F>(allow_fail_injection: bool, query: &str, open: F) -> PooledConnection
+where
+    F: FnOnce() -> PooledConnection,
+{
+    if allow_fail_injection && query.split('&').any(|pair| pair == FAIL_DB_PARAM) {
+        return Connection::open_in_memory()
+            .expect("Failed to open in-memory connection")
+            .into();
+    }
+    open()
+}
+
+/// Keeps the connections of finished requests for reuse. Checked out connections are validated first
+/// and replaced when broken or too old
+pub struct DbPool {
+    path: String,
+    max_idle: usize,
+    max_lifetime: Duration,
+    idle: Mutex<Vec<(Connection, Instant)>>,
+}
+
+impl DbPool {
+    pub fn new(path: &str, max_idle: usize, max_lifetime: Duration) -> DbPool {
+        DbPool {
+            path: path.to_string(),
+            max_idle,
+            max_lifetime,
+            idle: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Reuse an idle connection when one is still valid, otherwise open a new one
+    pub fn get(self: &Arc<Self>) -> rusqlite::Result<PooledConnection> {
+        loop {
+            let idle = self.idle.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).pop();
+            match idle {
+                Some((conn, opened_at)) if opened_at.elapsed() < self.max_lifetime && is_valid_conn(&conn) => {
+                    return Ok(PooledConnection { conn: Some(conn), opened_at, pool: Some(Arc::clone(self)) });
+                }
+                // Dropping a broken connection closes it, rolling back anything it left open
+                Some(_) => continue,
+                None => break,
+            }
+        }
+        let conn = open_db_conn(&self.path)?;
+        Ok(PooledConnection { conn: Some(conn), opened_at: Instant::now(), pool: Some(Arc::clone(self)) })
+    }
+
+    fn put_back(&self, conn: Connection, opened_at: Instant) {
+        if opened_at.elapsed() >= self.max_lifetime {
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if idle.len() < self.max_idle {
+            idle.push((conn, opened_at));
+        }
+    }
+}
+
+/// A connection still works and was not left inside a transaction, which would keep its locks
+fn is_valid_conn(conn: &Connection) -> bool {
+    conn.is_autocommit() && conn.query_row("SELECT 1", [], |row| row.get::<_, i64>(0)).is_ok()
+}
+
+/// A connection handed to a handler. It goes back to its pool when dropped
+pub struct PooledConnection {
+    conn: Option<Connection>,
+    opened_at: Instant,
+    pool: Option<Arc<DbPool>>,
+}
+
+/// A connection outside of any pool, closed when dropped
+impl From<Connection> for PooledConnection {
+    fn from(conn: Connection) -> PooledConnection {
+        PooledConnection { conn: Some(conn), opened_at: Instant::now(), pool: None }
+    }
+}
+
+impl PooledConnection {
+    /// A handle that cancels what is done on this connection from now on, until it goes back to its pool
+    pub fn cancel_handle(&self) -> CancelHandle {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&cancelled);
+        // A commit hook returning true turns the commit into a rollback
+        self.commit_hook(Some(move || flag.load(Ordering::SeqCst)));
+        CancelHandle { cancelled, interrupt: Arc::new(self.get_interrupt_handle()) }
+    }
+}
+
+/// Stops the database work of a request whose client was already answered. The statement running right now is
+/// interrupted and every later commit is rolled back instead, so none of the request's writes are kept
+#[derive(Clone)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+    interrupt: Arc<InterruptHandle>,
+}
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        self.interrupt.interrupt();
+    }
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().expect("connection already returned")
+    }
+}
+
+impl DerefMut for PooledConnection {
+    fn deref_mut(&mut self) -> &mut Connection {
+        self.conn.as_mut().expect("connection already returned")
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let (Some(conn), Some(pool)) = (self.conn.take(), self.pool.take()) {
+            // The next request must not inherit the cancellation of this one
+            conn.commit_hook(None::<fn() -> bool>);
+            pool.put_back(conn, self.opened_at);
+        }
+    }
+}
+
+/// Open a connection in WAL mode, so readers are not blocked by a writer, and make it wait on locks instead of failing.
+/// Foreign keys are enforced per connection, so every connection the pool hands out turns them on
+pub fn open_db_conn(path: &str) -> rusqlite::Result<Connection> {
+    let mut conn = Connection::open(path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.query_row("PRAGMA journal_mode = WAL", [], |row| row.get::<_, String>(0))?;
+    conn.execute("PRAGMA foreign_keys = ON", [])?;
+    enable_query_profiling(&mut conn);
+    Ok(conn)
+}
+
+/// Time every statement run on the connection, the slowest show up on /debug/slow-queries
+pub fn enable_query_profiling(conn: &mut Connection) {
+    conn.profile(Some(slow_queries::record));
+}
+
+/// Run a database operation, retrying a few times when SQLite still reports the database as busy or locked
+pub fn with_retry<T, F>(mut operation: F) -> rusqlite::Result<T>
+where
+    F: FnMut() -> rusqlite::Result<T>,
+{
+    let mut attempt = 0;
+    loop {
+        match operation() {
+            Err(rusqlite::Error::SqliteFailure(err, _))
+                if (err.code == ErrorCode::DatabaseBusy || err.code == ErrorCode::DatabaseLocked) && attempt < RETRY_ATTEMPTS =>
+            {
+                attempt += 1;
+                std::thread::sleep(RETRY_DELAY * attempt);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Extended result codes for UNIQUE, PRIMARY KEY and FOREIGN KEY constraint failures, not exported by the bundled ffi bindings
+const SQLITE_CONSTRAINT_UNIQUE: std::os::raw::c_int = 2067;
+const SQLITE_CONSTRAINT_PRIMARYKEY: std::os::raw::c_int = 1555;
+const SQLITE_CONSTRAINT_FOREIGNKEY: std::os::raw::c_int = 787;
+
+/// Whether an insert failed because the row already exists under a UNIQUE constraint or a (non rowid) PRIMARY KEY
+pub fn is_unique_violation(err: &rusqlite::Error) -> bool {
+    matches!(
+        err,
+        rusqlite::Error::SqliteFailure(err, _)
+            if err.extended_code == SQLITE_CONSTRAINT_UNIQUE || err.extended_code == SQLITE_CONSTRAINT_PRIMARYKEY
+    )
+}
+
+/// Whether a write failed because a row it points to does not exist
+pub fn is_foreign_key_violation(err: &rusqlite::Error) -> bool {
+    matches!(err, rusqlite::Error::SqliteFailure(err, _) if err.extended_code == SQLITE_CONSTRAINT_FOREIGNKEY)
+}
+
+/// Create or migrate the database at `path` and hand out connections to it from now on
+pub fn initialize_db(path: &str) {
+    tracing::info!("Initializing the database...");
+    DB_PATH.set(path.to_string()).expect("The database is already initialized");
+    let conn = open_db_conn(path).expect("Failed to open SQLite connection");
+    create_schema(&conn).expect("Failed to create the database schema");
+}
+
+/// Create all tables and bring older databases up to date.
+/// The test suite uses this as well, so the schema only lives in one place
+pub fn create_schema(conn: &Connection) -> rusqlite::Result<()> {
+    tracing::info!("Creating Table table");
+    create_table_table_if_not_exists(conn)?;
+    tracing::info!("Creating Menu table");
+    create_menu_table_if_not_exists(conn)?;
+    tracing::info!("Creating Order table");
+    create_order_table_if_not_exists(conn)?;
+    tracing::info!("Creating OrderItem table");
+    create_order_item_table_if_not_exists(conn)?;
+    tracing::info!("Creating Setting table");
+    create_setting_table_if_not_exists(conn)?;
+    tracing::info!("Creating Customer table");
+    create_customer_table_if_not_exists(conn)?;
+    tracing::info!("Creating AuditLog table");
+    create_audit_log_table_if_not_exists(conn)?;
+    tracing::info!("Creating RecentAdd table");
+    create_recent_add_table_if_not_exists(conn)?;
+    tracing::info!("Creating MenuSchedule table");
+    create_menu_schedule_table_if_not_exists(conn)?;
+    tracing::info!("Creating MenuSnapshot table");
+    create_menu_snapshot_table_if_not_exists(conn)?;
+    tracing::info!("Creating OrderItemChange table");
+    create_order_item_change_table_if_not_exists(conn)?;
+    tracing::info!("Migrating columns");
+    drop_orders_table_unique(conn)?;
+    migrate_columns(conn)?;
+    store_closed_order_prices(conn)?;
+    create_indexes(conn)?;
+    create_triggers(conn)?;
+    Ok(())
 }
 
 fn create_table_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
@@ -28,11 +267,166 @@ fn create_menu_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
     Ok(())
 }
 fn create_order_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
-    conn.execute("CREATE TABLE IF NOT EXISTS orders (id INTEGER PRIMARY KEY, table_id INTEGER NOT NULL, FOREIGN KEY (table_id) REFERENCES tables(id), UNIQUE (table_id))",[])?;
+    conn.execute("CREATE TABLE IF NOT EXISTS orders (id INTEGER PRIMARY KEY, table_id INTEGER NOT NULL, FOREIGN KEY (table_id) REFERENCES tables(id))",[])?;
     Ok(())
 }
 fn create_order_item_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
     conn.execute("CREATE TABLE IF NOT EXISTS order_items (id INTEGER PRIMARY KEY, order_id INTEGER NOT NULL, menu_id INTEGER NOT NULL, cooking_time INTEGER NOT NULL, quantity INTEGER NOT NULL default 1, FOREIGN KEY (order_id) REFERENCES orders(id), FOREIGN KEY (menu_id) REFERENCES menus(id))",[])?;
     Ok(())
 }
+fn create_setting_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("CREATE TABLE IF NOT EXISTS settings (key TEXT PRIMARY KEY, value TEXT NOT NULL)",[])?;
+    Ok(())
+}
+fn create_customer_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("CREATE TABLE IF NOT EXISTS customers (id INTEGER PRIMARY KEY, name TEXT NOT NULL, phone TEXT)",[])?;
+    Ok(())
+}
+fn create_audit_log_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("CREATE TABLE IF NOT EXISTS audit_log (id INTEGER PRIMARY KEY, order_id INTEGER NOT NULL, action TEXT NOT NULL, actor TEXT, note TEXT, created_at TEXT NOT NULL, FOREIGN KEY (order_id) REFERENCES orders(id))",[])?;
+    Ok(())
+}
+fn create_recent_add_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("CREATE TABLE IF NOT EXISTS recent_adds (table_id INTEGER NOT NULL, menu_id INTEGER NOT NULL, added_at TEXT NOT NULL, PRIMARY KEY (table_id, menu_id))",[])?;
+    Ok(())
+}
+fn create_menu_schedule_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("CREATE TABLE IF NOT EXISTS menu_schedule (date TEXT PRIMARY KEY, menu_id INTEGER NOT NULL, FOREIGN KEY (menu_id) REFERENCES menus(id))",[])?;
+    Ok(())
+}
+fn create_menu_snapshot_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("CREATE TABLE IF NOT EXISTS menu_snapshots (name TEXT NOT NULL, menu_id INTEGER NOT NULL, menu_name TEXT NOT NULL, price REAL NOT NULL, category TEXT, station TEXT, available INTEGER NOT NULL, tax_category TEXT, created_at TEXT NOT NULL, PRIMARY KEY (name, menu_id), FOREIGN KEY (menu_id) REFERENCES menus(id))",[])?;
+    Ok(())
+}
+/// History of item changes per order version. No foreign key on order_id: it outlives orders deleted with their last item
+fn create_order_item_change_table_if_not_exists(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("CREATE TABLE IF NOT EXISTS order_item_changes (id INTEGER PRIMARY KEY, order_id INTEGER NOT NULL, version INTEGER NOT NULL, menu_id INTEGER NOT NULL, change TEXT NOT NULL, quantity_before INTEGER, quantity_after INTEGER, item_status TEXT, created_at TEXT NOT NULL)",[])?;
+    Ok(())
+}
+
+/// Columns added after the first release. New columns go here instead of the CREATE TABLE
+/// statements so fresh and existing databases end up with the same schema
+fn migrate_columns(conn: &Connection) -> rusqlite::Result<()> {
+    add_column_if_not_exists(conn, "menus", "price", "REAL NOT NULL DEFAULT 0.0")?;
+    add_column_if_not_exists(conn, "orders", "created_at", "TEXT")?;
+    add_column_if_not_exists(conn, "menus", "category", "TEXT")?;
+    add_column_if_not_exists(conn, "orders", "status", "TEXT NOT NULL DEFAULT 'open'")?;
+    add_column_if_not_exists(conn, "orders", "void_reason", "TEXT")?;
+    add_column_if_not_exists(conn, "order_items", "item_status", "TEXT NOT NULL DEFAULT 'pending'")?;
+    add_column_if_not_exists(conn, "order_items", "fired_at", "TEXT")?;
+    add_column_if_not_exists(conn, "orders", "order_number", "TEXT")?;
+    add_column_if_not_exists(conn, "orders", "customer_id", "INTEGER REFERENCES customers(id)")?;
+    add_column_if_not_exists(conn, "orders", "paid_at", "TEXT")?;
+    add_column_if_not_exists(conn, "customers", "points", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_not_exists(conn, "orders", "discount_cents", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_not_exists(conn, "menus", "station", "TEXT")?;
+    add_column_if_not_exists(conn, "menus", "available", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_not_exists(conn, "order_items", "comped", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_not_exists(conn, "order_items", "comp_reason", "TEXT")?;
+    add_column_if_not_exists(conn, "order_items", "served_at", "TEXT")?;
+    add_column_if_not_exists(conn, "order_items", "ready_at", "TEXT")?;
+    add_column_if_not_exists(conn, "orders", "party_size", "INTEGER")?;
+    add_column_if_not_exists(conn, "menus", "tax_category", "TEXT")?;
+    add_column_if_not_exists(conn, "orders", "version", "INTEGER NOT NULL DEFAULT 0")?;
+    add_column_if_not_exists(conn, "order_items", "notes", "TEXT")?;
+    add_column_if_not_exists(conn, "menus", "cost_cents", "INTEGER")?;
+    add_column_if_not_exists(conn, "order_items", "created_at", "TEXT")?;
+    add_column_if_not_exists(conn, "menus", "active", "INTEGER NOT NULL DEFAULT 1")?;
+    add_column_if_not_exists(conn, "order_items", "unit_price_cents", "INTEGER")?;
+    Ok(())
+}
+
+/// Orders paid or voided before unit prices were stored on their items get today's menu prices, the closest
+/// there is to what was charged. From then on repricing a menu no longer changes them
+fn store_closed_order_prices(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE order_items SET unit_price_cents = (SELECT CAST(ROUND(price * 100) AS INTEGER) FROM menus WHERE id = order_items.menu_id)
+        WHERE unit_price_cents IS NULL AND order_id IN (SELECT id FROM orders WHERE status IN ('paid', 'void'))",
+        [],
+    )
+}
 
+/// Only one running order per table. Closed (paid or void) orders stay around as history.
+/// orders_running_table only excluded void orders and is replaced by orders_open_table.
+/// Names of active menus are unique, an archived menu frees its name. menus_name covered archived menus as well
+/// and is replaced by menus_active_name
+fn create_indexes(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute("DROP INDEX IF EXISTS orders_running_table",[])?;
+    conn.execute("CREATE UNIQUE INDEX IF NOT EXISTS orders_open_table ON orders (table_id) WHERE status NOT IN ('paid', 'void')",[])?;
+    conn.execute("DROP INDEX IF EXISTS menus_name",[])?;
+    rename_duplicate_menus(conn)?;
+    conn.execute("CREATE UNIQUE INDEX IF NOT EXISTS menus_active_name ON menus (name) WHERE active = 1",[])?;
+    Ok(())
+}
+
+/// Databases from before the menu name index can hold several active menus with the same name, the index could
+/// not be created on them. The oldest keeps the name, the others get their id appended: "Soup" and "Soup (7)".
+/// Renaming instead of merging keeps their order items apart, an order could hold both menus
+fn rename_duplicate_menus(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE menus SET name = name || ' (' || id || ')'
+        WHERE active = 1 AND EXISTS (SELECT 1 FROM menus AS oldest WHERE oldest.active = 1 AND oldest.name = menus.name AND oldest.id < menus.id)",
+        [],
+    )
+}
+
+/// Every insert, update and delete of an order item bumps the order's version and records the change under the new
+/// version, whichever code path made it. Updates that touch none of the tracked columns are not a change
+pub(crate) fn create_triggers(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TRIGGER IF NOT EXISTS order_items_added AFTER INSERT ON order_items
+        BEGIN
+            UPDATE orders SET version = version + 1 WHERE id = NEW.order_id;
+            INSERT INTO order_item_changes (order_id, version, menu_id, change, quantity_before, quantity_after, item_status, created_at)
+            SELECT NEW.order_id, version, NEW.menu_id, 'added', NULL, NEW.quantity, NEW.item_status, datetime('now') FROM orders WHERE id = NEW.order_id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS order_items_changed AFTER UPDATE ON order_items
+        WHEN OLD.quantity != NEW.quantity OR OLD.item_status != NEW.item_status OR OLD.comped != NEW.comped OR OLD.menu_id != NEW.menu_id
+        BEGIN
+            UPDATE orders SET version = version + 1 WHERE id = NEW.order_id;
+            INSERT INTO order_item_changes (order_id, version, menu_id, change, quantity_before, quantity_after, item_status, created_at)
+            SELECT NEW.order_id, version, NEW.menu_id, 'changed', OLD.quantity, NEW.quantity, NEW.item_status, datetime('now') FROM orders WHERE id = NEW.order_id;
+        END;
+        CREATE TRIGGER IF NOT EXISTS order_items_removed AFTER DELETE ON order_items
+        BEGIN
+            UPDATE orders SET version = version + 1 WHERE id = OLD.order_id;
+            INSERT INTO order_item_changes (order_id, version, menu_id, change, quantity_before, quantity_after, item_status, created_at)
+            SELECT OLD.order_id, version, OLD.menu_id, 'removed', OLD.quantity, NULL, OLD.item_status, datetime('now') FROM orders WHERE id = OLD.order_id;
+        END;",
+    )
+}
+
+/// Older databases declared UNIQUE (table_id) on orders, which does not allow keeping closed orders.
+/// SQLite cannot drop a constraint, so the table is rebuilt from its own definition without it
+fn drop_orders_table_unique(conn: &Connection) -> rusqlite::Result<()> {
+    let sql: String = conn.query_row("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = 'orders'", [], |row| row.get(0))?;
+    if !sql.contains(", UNIQUE (table_id)") {
+        return Ok(());
+    }
+    let rebuilt = sql
+        .replacen("CREATE TABLE orders", "CREATE TABLE orders_rebuild", 1)
+        .replace(", UNIQUE (table_id)", "");
+    conn.execute_batch(&format!(
+        "PRAGMA foreign_keys = OFF;
+        BEGIN;
+        {};
+        INSERT INTO orders_rebuild SELECT * FROM orders;
+        DROP TABLE orders;
+        ALTER TABLE orders_rebuild RENAME TO orders;
+        COMMIT;
+        PRAGMA foreign_keys = ON;",
+        rebuilt
+    ))
+}
+
+fn add_column_if_not_exists(conn: &Connection, table: &str, column: &str, definition: &str) -> rusqlite::Result<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let columns = stmt.query_map([], |row| row.get::<_, String>(1))?;
+    for existing in columns {
+        if existing? == column {
+            return Ok(());
+        }
+    }
+    conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    Ok(())
+}
@@ -0,0 +1,100 @@
+// src/error.rs
+use serde_json::{json, Map, Value};
+use warp::http::StatusCode;
+
+/// Failure of a handler, turned into a response by the rejection handler in routes.rs as
+/// {"error": {"code": .., "message": ..}} with a matching status. Handlers return it as `Err(ApiError::...into())`,
+/// warp converts any Reject into a Rejection
+#[derive(Debug)]
+pub enum ApiError {
+    NotFound(String),
+    Validation(String),
+    Forbidden(String),
+    Conflict(String),
+    Unavailable(String),
+    Timeout(String),
+    Database(rusqlite::Error),
+    /// Any other server side failure, logged like Database errors
+    Internal(String),
+    /// An error with extra fields the client can act on, next to code and message, see `with_details`
+    Detailed(Box<ApiError>, Map<String, Value>),
+}
+
+impl warp::reject::Reject for ApiError {}
+
+impl From<rusqlite::Error> for ApiError {
+    fn from(err: rusqlite::Error) -> Self {
+        ApiError::Database(err)
+    }
+}
+
+impl ApiError {
+    /// Add the fields of `details`, a JSON object, to the error: {"error": {"code", "message", "id": 3}}
+    pub fn with_details(self, details: Value) -> ApiError {
+        match details {
+            Value::Object(fields) => ApiError::Detailed(Box::new(self), fields),
+            _ => self,
+        }
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Validation(_) => StatusCode::BAD_REQUEST,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            ApiError::Database(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            ApiError::Detailed(err, _) => err.status(),
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Validation(_) => "validation",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::Unavailable(_) => "unavailable",
+            ApiError::Timeout(_) => "timeout",
+            ApiError::Database(_) => "database",
+            ApiError::Internal(_) => "internal",
+            ApiError::Detailed(err, _) => err.code(),
+        }
+    }
+
+    /// Database and internal details stay in the server log, the client only learns that something went wrong
+    pub fn message(&self) -> String {
+        match self {
+            ApiError::NotFound(message)
+            | ApiError::Validation(message)
+            | ApiError::Forbidden(message)
+            | ApiError::Conflict(message)
+            | ApiError::Unavailable(message)
+            | ApiError::Timeout(message) => message.clone(),
+            ApiError::Database(_) | ApiError::Internal(_) => "Something Wrong!".to_string(),
+            ApiError::Detailed(err, _) => err.message(),
+        }
+    }
+
+    /// What goes to the server log for errors the client gets no details about
+    pub fn log_detail(&self) -> Option<String> {
+        match self {
+            ApiError::Database(err) => Some(err.to_string()),
+            ApiError::Internal(detail) => Some(detail.clone()),
+            ApiError::Detailed(err, _) => err.log_detail(),
+            _ => None,
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut error = Map::new();
+        error.insert("code".to_string(), json!(self.code()));
+        error.insert("message".to_string(), json!(self.message()));
+        if let ApiError::Detailed(_, details) = self {
+            error.extend(details.clone());
+        }
+        json!({"error": error})
+    }
+}
@@ -0,0 +1,45 @@
+// src/events.rs
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tokio::sync::broadcast;
+
+/// How many events a slow subscriber can fall behind before it misses the oldest ones.
+/// EVENT_CAPACITY overrides it
+const EVENT_CAPACITY: usize = 100;
+
+/// An order change pushed to the /events subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderEvent {
+    pub event: String,
+    pub order_id: i64,
+    pub table_id: i64,
+    /// The menu the event is about, only on menu events such as item_86ed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub menu_id: Option<i64>,
+    /// Id of the request that caused the event, to correlate it with that request's logs
+    pub request_id: Option<String>,
+}
+
+/// The channel never holds more than this many events, a full channel overwrites its oldest event
+/// instead of blocking the sender
+fn event_capacity() -> usize {
+    std::env::var("EVENT_CAPACITY")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|capacity| *capacity > 0)
+        .unwrap_or(EVENT_CAPACITY)
+}
+
+fn sender() -> &'static broadcast::Sender<OrderEvent> {
+    static EVENTS: OnceLock<broadcast::Sender<OrderEvent>> = OnceLock::new();
+    EVENTS.get_or_init(|| broadcast::channel(event_capacity()).0)
+}
+
+/// Send an event to every subscriber without waiting. It is dropped when nobody is listening
+pub fn publish(event: OrderEvent) {
+    let _ = sender().send(event);
+}
+
+pub fn subscribe() -> broadcast::Receiver<OrderEvent> {
+    sender().subscribe()
+}
@@ -1,447 +1,1782 @@
-use crate::models::{OrderResponse, OrderItem, OrderRequestBody, Table, Menu, MenuResponse, TableResponse, OrderItemResponse};
-use rusqlite::Connection;
-use warp;
-use rand::Rng;
+use crate::db::{is_foreign_key_violation, is_unique_violation, with_retry, PooledConnection};
+use crate::backup::Backup;
+use crate::models::{OrderResponse, MenuScheduleRequest, MenuSnapshotRequest, PriceAdjustment, FloorOrderResponse, OrderItem, OrderRequestBody, OnExisting, OrderOperation, ModifyOrderRequest, OrderDiff, Table, Menu, MenuUpdate, OrderItemResponse, ItemStatusUpdate, QuantityUpdate, OrderStatus, OrderStatusUpdate, ITEM_STATUSES, ORDER_STATUSES, TablePerformance, PrepAccuracy, EodReport, CoversReport, TurnoverReport, WaitEstimate, TipSuggestions, Customer, AttachCustomerRequest, ImportRequest, Setting, SettingUpdate, VoidOrderRequest, RemoveItemQuery, PayOrderQuery, RedeemPointsRequest, Receipt, Bill, OrderTicket, AuditEntry, TimelineEntry, RequestContext, CompItemRequest, MENU_SORT_FIELDS, Page, PageQuery, is_valid_date, has_decimal_places_within, to_amount};
+use rusqlite::{Connection, TransactionBehavior};
 use rusqlite::params;
 use serde_json::json;
+use crate::events::{self, OrderEvent};
+use crate::error::ApiError;
+use crate::slow_queries;
+use crate::clock::{minute_of_day, Clock};
+use crate::cooking_time::CookingTimeSource;
+use futures_util::{stream, SinkExt, StreamExt};
+use warp::Reply;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
 
 
 // Table Handlers
 
-/// List All Tables
-pub async fn list_table_handler(conn: Connection)-> Result<impl warp::Reply, warp::Rejection>{
-    match Table::list(&conn) {
-        Ok(tables) => {
-            Ok(warp::reply::with_status(
-                warp::reply::json(&tables),
-                warp::http::StatusCode::OK
-            ))
-        }
-        Err(_err) => {
-            Ok(warp::reply::with_status(
-                warp::reply::json::<Vec<TableResponse>>(&vec![]),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR
-            ))
-        }
-    }
+/// List All Tables, a page at a time
+pub async fn list_table_handler(conn: PooledConnection, page: PageQuery)-> Result<impl warp::Reply, warp::Rejection>{
+    let (limit, offset) = page.resolve().map_err(ApiError::Validation)?;
+    let tables = Table::list(&conn, limit, offset).map_err(ApiError::from)?;
+    let total = Table::count(&conn).map_err(ApiError::from)?;
+    let count = tables.len();
+    Ok(with_large_list_hint(&conn, warp::reply::with_status(
+        warp::reply::json(&Page { items: tables, total, limit, offset }),
+        warp::http::StatusCode::OK
+    ), count))
+}
+/// List the Tables that never had an order, to find dead tables
+pub async fn unused_tables_handler(conn: PooledConnection)-> Result<impl warp::Reply, warp::Rejection>{
+    let tables = Table::list_unused(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&tables),
+        warp::http::StatusCode::OK
+    ))
+}
+/// List every Table with whether it is occupied, and if so its running order and how many items are on it
+pub async fn list_tables_with_status_handler(conn: PooledConnection)-> Result<impl warp::Reply, warp::Rejection>{
+    let tables = Table::list_with_status(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&tables),
+        warp::http::StatusCode::OK
+    ))
 }
 /// Create a new Table
-pub async fn create_table_handler(conn: Connection, data: Table) -> Result<impl warp::Reply, warp::Rejection> {
-    match Table::get_existing_table_id(&conn, &data) {
-    Ok(Some(table_id))=>{
-        Ok(warp::reply::with_status(
-            warp::reply::json(&json!({ "id": table_id })),
+pub async fn create_table_handler(conn: PooledConnection, data: Table) -> Result<impl warp::Reply, warp::Rejection> {
+    if data.code.is_empty() {
+        let table = Table::create_next(&conn).map_err(ApiError::from)?;
+        return Ok(with_location(warp::reply::with_status(
+            warp::reply::json(&json!({ "id": table.id })),
             warp::http::StatusCode::CREATED,
-        ))
+        ), format!("/tables/{}", table.id)));
     }
-    Ok(None)=>{
-        match Table::create(&conn, &data) {
-            Ok(table_id) => {
-                Ok(warp::reply::with_status(
-                    warp::reply::json(&json!({ "id": table_id })),
-                    warp::http::StatusCode::CREATED,
-                ))
+    data.validate().map_err(ApiError::Validation)?;
+    let table_id = match Table::get_existing_table_id(&conn, &data).map_err(ApiError::from)? {
+        Some(table_id) => table_id,
+        None => match Table::create(&conn, &data) {
+            Ok(table_id) => table_id,
+            // Another request created the same code since the check above, answer like the idempotent path
+            Err(err) if is_unique_violation(&err) => {
+                Table::get_existing_table_id(&conn, &data)
+                    .map_err(ApiError::from)?
+                    .ok_or_else(|| ApiError::from(err))?
             }
-            Err(_err) => {
-                // Respond with an error
-                Ok(warp::reply::with_status(
-                    warp::reply::json(&json!({"error":"Error creating table"})),
-                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                ))
+            Err(err) => return Err(ApiError::from(err).into()),
+        },
+    };
+    Ok(with_location(warp::reply::with_status(
+        warp::reply::json(&json!({ "id": table_id })),
+        warp::http::StatusCode::CREATED,
+    ), format!("/tables/{}", table_id)))
+}
+
+/// Create many Tables in one go, for onboarding a restaurant. Codes that already exist are skipped and
+/// reported, any other failure creates none of the tables
+pub async fn create_tables_bulk_handler(mut conn: PooledConnection, data: Vec<Table>) -> Result<impl warp::Reply, warp::Rejection> {
+    data.iter().try_for_each(Table::validate).map_err(ApiError::Validation)?;
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error rolls back the tables created so far
+    let summary = Table::create_bulk(&tx, &data).map_err(ApiError::from)?;
+    tx.commit().map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&summary),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+/// Create a table with the next auto-numbered code, e.g. T-04
+pub async fn create_next_table_handler(conn: PooledConnection) -> Result<impl warp::Reply, warp::Rejection> {
+    let table = Table::create_next(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&table),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+// Menu Handler
+
+/// List All Menus. Uses the requested sort, otherwise the configured default_menu_sort.
+/// Reads are retried while concurrent order writes hold a lock, so the list does not fail under load
+pub async fn list_menu_handler(conn: PooledConnection, sort: Option<String>, include_inactive: bool, page: PageQuery)-> Result<impl warp::Reply, warp::Rejection>{
+    let (limit, offset) = page.resolve().map_err(ApiError::Validation)?;
+    let sort = match sort {
+        Some(sort) => {
+            if !MENU_SORT_FIELDS.contains(&sort.as_str()) {
+                return Err(ApiError::Validation(format!("sort must be one of {}", MENU_SORT_FIELDS.join(", "))).into());
             }
+            sort
+        }
+        None => with_retry(|| Setting::default_menu_sort(&conn)).unwrap_or_else(|_| "id".to_string()),
+    };
+    let (menus, total) = with_retry(|| Ok((Menu::list(&conn, &sort, include_inactive, limit, offset)?, Menu::count(&conn, include_inactive)?)))
+        .map_err(ApiError::from)?;
+    let count = menus.len();
+    Ok(with_large_list_hint(&conn, warp::reply::with_status(
+        warp::reply::json(&Page { items: menus, total, limit, offset }),
+        warp::http::StatusCode::OK,
+    ), count))
+}
+/// Get a single Menu by its id
+pub async fn get_menu_handler(conn: PooledConnection, menu_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    let menu = Menu::get(&conn, menu_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Menu Found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&menu),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Menus with `query` in their name, an empty list when none match
+pub async fn search_menu_handler(conn: PooledConnection, query: String)-> Result<impl warp::Reply, warp::Rejection>{
+    let query = query.trim();
+    if query.is_empty() {
+        return Err(ApiError::Validation("q must not be empty".to_string()).into());
+    }
+    let menus = Menu::search(&conn, query).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&menus),
+        warp::http::StatusCode::OK,
+    ))
+}
+/// Menus of one category, e.g. all drinks. An empty list when the category has no menus
+pub async fn list_menus_by_category_handler(conn: PooledConnection, category: String)-> Result<impl warp::Reply, warp::Rejection>{
+    let menus = Menu::list_by_category(&conn, &category).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&menus),
+        warp::http::StatusCode::OK,
+    ))
+}
+/// The distinct categories of all menus
+pub async fn list_menu_categories_handler(conn: PooledConnection)-> Result<impl warp::Reply, warp::Rejection>{
+    let categories = Menu::categories(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&categories),
+        warp::http::StatusCode::OK,
+    ))
+}
+/// Fix the name and price of a Menu. The name must stay unique, the price is kept when left out
+pub async fn update_menu_handler(conn: PooledConnection, menu_id: i64, data: MenuUpdate)-> Result<impl warp::Reply, warp::Rejection>{
+    if data.name.trim().is_empty() {
+        return Err(ApiError::Validation("name must not be empty".to_string()).into());
+    }
+    if let Some(message) = invalid_price_message(&conn, data.price.map(|price| (data.name.as_str(), price))) {
+        return Err(ApiError::Validation(message).into());
+    }
+    match Menu::update(&conn, menu_id, &data) {
+        Ok(true) => {}
+        Ok(false) => return Err(ApiError::NotFound("No Menu Found".to_string()).into()),
+        Err(err) if is_unique_violation(&err) => {
+            return Err(ApiError::Conflict(format!("Another menu is already named {}", data.name)).into());
         }
+        Err(err) => return Err(ApiError::from(err).into()),
     }
-    Err(_err) => {
-        // Respond with an error
-        Ok(warp::reply::with_status(
-            warp::reply::json(&json!({"error":"Error creating table"})),
-            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-        ))
+    let menu = Menu::get(&conn, menu_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Menu Found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&menu),
+        warp::http::StatusCode::OK,
+    ))
+}
+/// Price, cost and margin of every Menu with a cost, managers only
+pub async fn menu_margins_handler(conn: PooledConnection, context: RequestContext)-> Result<impl warp::Reply, warp::Rejection>{
+    if !context.is_manager() {
+        return Err(ApiError::Forbidden("Only a manager can see margins".to_string()).into());
     }
+    let margins = Menu::margins(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&margins),
+        warp::http::StatusCode::OK,
+    ))
 }
-    
+/// Mark every Menu of a category as available or unavailable, e.g. when the grill goes down
+pub async fn set_category_availability_handler(conn: PooledConnection, category: String, available: bool)-> Result<impl warp::Reply, warp::Rejection>{
+    let updated = Menu::set_category_availability(&conn, &category, available).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"category": category, "available": available, "updated": updated})),
+        warp::http::StatusCode::OK,
+    ))
 }
 
-// Menu Handler
+/// 86 a Menu: make it unavailable right away and list the tables still waiting for it, so the floor can
+/// tell them. Dashboards get an item_86ed event for every waiting order
+pub async fn eighty_six_handler(conn: PooledConnection, menu_id: i64, context: RequestContext)-> Result<impl warp::Reply, warp::Rejection>{
+    if !Menu::set_availability(&conn, menu_id, false).map_err(ApiError::from)? {
+        return Err(ApiError::NotFound(format!("Menu {} not found", menu_id)).into());
+    }
+    let tables = Menu::awaiting_tables(&conn, menu_id).map_err(ApiError::from)?;
+    for table in &tables {
+        events::publish(OrderEvent {
+            event: "item_86ed".to_string(),
+            order_id: table.order_id,
+            table_id: table.table_id,
+            menu_id: Some(menu_id),
+            request_id: context.request_id.clone(),
+        });
+    }
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"menu_id": menu_id, "available": false, "tables": tables})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Archive a menu instead of deleting it. It can no longer be ordered and leaves the menu listings,
+/// past orders still show its name
+pub async fn archive_menu_handler(conn: PooledConnection, menu_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    if !Menu::archive(&conn, menu_id).map_err(ApiError::from)? {
+        return Err(ApiError::NotFound(format!("Menu {} not found", menu_id)).into());
+    }
+    let menu = Menu::get(&conn, menu_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(warp::reply::json(&menu), warp::http::StatusCode::OK))
+}
+
+/// Get the menu of the day for a date (YYYY-MM-DD), today when omitted
+pub async fn menu_of_the_day_handler(conn: PooledConnection, date: Option<String>, clock: Arc<dyn Clock>)-> Result<impl warp::Reply, warp::Rejection>{
+    let date = match date {
+        Some(date) => date,
+        None => conn.query_row("SELECT date(?1, 'unixepoch')", [clock.now()], |row| row.get(0)).map_err(ApiError::from)?,
+    };
+    check_date(&conn, &date)?;
+    let menu = Menu::of_the_day(&conn, &date)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No menu available".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"date": date, "menu": menu})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Schedule a menu as the menu of the day for a date (YYYY-MM-DD), replacing the daily rotation
+pub async fn schedule_menu_of_the_day_handler(conn: PooledConnection, date: String, data: MenuScheduleRequest)-> Result<impl warp::Reply, warp::Rejection>{
+    check_date(&conn, &date)?;
+    let menu = Menu::get(&conn, data.menu_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("Menu not found".to_string()))?;
+    Menu::schedule_of_the_day(&conn, &date, menu.id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"date": date, "menu": menu})),
+        warp::http::StatusCode::OK,
+    ))
+}
 
-/// List All Menus
-pub async fn list_menu_handler(conn: Connection)-> Result<impl warp::Reply, warp::Rejection>{
-    match Menu::list(&conn) {
+/// Save the current menus and prices under a name, to restore them later (e.g. seasonal menus)
+pub async fn create_menu_snapshot_handler(conn: PooledConnection, data: MenuSnapshotRequest)-> Result<impl warp::Reply, warp::Rejection>{
+    let name = data.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::Validation("Please provide a snapshot name".to_string()).into());
+    }
+    match Menu::snapshot(&conn, name) {
+        Ok(0) => Err(ApiError::Validation("No menus to snapshot".to_string()).into()),
         Ok(menus) => {
             Ok(warp::reply::with_status(
-                warp::reply::json(&menus),
+                warp::reply::json(&json!({"name": name, "menus": menus})),
+                warp::http::StatusCode::CREATED,
+            ))
+        }
+        Err(err) if is_unique_violation(&err) => Err(ApiError::Conflict(format!("Snapshot {} already exists", name)).into()),
+        Err(err) => Err(ApiError::Database(err).into()),
+    }
+}
+
+/// Put the menus back as they were in a snapshot, all of them or none
+pub async fn restore_menu_snapshot_handler(mut conn: PooledConnection, name: String)-> Result<impl warp::Reply, warp::Rejection>{
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error rolls back the menus already restored
+    let restored = Menu::restore_snapshot(&tx, &name)
+        .and_then(|restored| tx.commit().map(|_| restored))
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("Snapshot not found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"name": name, "restored": restored})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Merge two menus that are the same dish, moving the order items of `remove_id` onto `keep_id`
+pub async fn merge_menus_handler(mut conn: PooledConnection, keep_id: i64, remove_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    if keep_id == remove_id {
+        return Err(ApiError::Validation("Cannot merge a menu into itself".to_string()).into());
+    }
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error rolls back the order items already moved
+    match Menu::merge(&tx, keep_id, remove_id).and_then(|merged| tx.commit().map(|_| merged)).map_err(ApiError::from)? {
+        Some(merged) => {
+            Ok(warp::reply::with_status(
+                warp::reply::json(&json!({"id": keep_id, "removed": remove_id, "order_items": merged})),
                 warp::http::StatusCode::OK,
             ))
         }
+        None => Err(ApiError::NotFound("Menu not found".to_string()).into()),
+    }
+}
+
+/// Raise or lower menu prices by a percentage, optionally only for one category. Managers only
+pub async fn adjust_prices_handler(mut conn: PooledConnection, data: PriceAdjustment, context: RequestContext)-> Result<impl warp::Reply, warp::Rejection>{
+    if !context.is_manager() {
+        return Err(ApiError::Forbidden("Only a manager can adjust prices".to_string()).into());
+    }
+    if !data.percent.is_finite() || data.percent <= -100.0 || data.percent > 1000.0 {
+        return Err(ApiError::Validation("percent must be above -100 and at most 1000".to_string()).into());
+    }
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error leaves every price as it was
+    let updated = Menu::adjust_prices(&tx, data.percent, data.category.as_deref())
+        .and_then(|updated| tx.commit().map(|_| updated))
+        .map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"updated": updated})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Point the Location header of a CREATED reply at the new resource, other replies are left as they are
+fn with_location(reply: impl warp::Reply, location: String) -> warp::reply::Response {
+    let mut response = reply.into_response();
+    if response.status() == warp::http::StatusCode::CREATED {
+        if let Ok(location) = warp::http::HeaderValue::from_str(&location) {
+            response.headers_mut().insert(warp::http::header::LOCATION, location);
+        }
+    }
+    response
+}
+
+/// Flag a list reply with more than large_list_items items with X-Result-Truncated-Hint, asking the client
+/// to page through it. The reply itself is never cut short
+fn with_large_list_hint(conn: &rusqlite::Connection, reply: impl warp::Reply, item_count: usize) -> warp::reply::Response {
+    let mut response = reply.into_response();
+    let large = match Setting::large_list_items(conn) {
+        Ok(large_list_items) => item_count > large_list_items,
         Err(_err) => {
-            Ok(warp::reply::with_status(
-                warp::reply::json::<Vec<MenuResponse>>(&vec![]),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            )
-            )
+            tracing::error!("{}", _err);
+            false
+        }
+    };
+    if large {
+        response.headers_mut().insert("x-result-truncated-hint", warp::http::HeaderValue::from_static("true"));
+    }
+    response
+}
+
+/// Refuse a date that is not formatted as YYYY-MM-DD
+fn check_date(conn: &rusqlite::Connection, date: &str) -> Result<(), ApiError> {
+    if is_valid_date(conn, date)? {
+        Ok(())
+    } else {
+        Err(ApiError::Validation("Dates must be formatted as YYYY-MM-DD".to_string()))
+    }
+}
+
+/// Delete an order whose last item was taken off. Orders with audit history are kept, CONFLICT asks to void them instead
+fn delete_emptied_order(conn: &rusqlite::Connection, order_id: i64) -> Result<bool, ApiError> {
+    match OrderResponse::delete(conn, order_id) {
+        Err(rusqlite::Error::SqliteFailure(err, _)) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            Err(ApiError::Conflict("This order has audit history, void it instead".to_string()))
         }
+        result => result.map_err(ApiError::from),
     }
 }
+
+/// Error message when a menu price has more decimal places than price_decimal_places allows
+fn invalid_price_message<'a>(conn: &rusqlite::Connection, prices: impl IntoIterator<Item = (&'a str, f64)>) -> Option<String> {
+    let places = match Setting::price_decimal_places(conn) {
+        Ok(places) => places,
+        Err(_err) => {
+            tracing::error!("{}", _err);
+            2
+        }
+    };
+    prices.into_iter()
+        .find(|(_, price)| !has_decimal_places_within(*price, places))
+        .map(|(name, _)| format!("price of {} can have at most {} decimal places", name, places))
+}
+
 // Create a new Menu
-pub async fn create_menu_handler(conn: Connection, data: Menu) -> Result<impl warp::Reply, warp::Rejection> {
-    match Menu::get_existing_menu_id(&conn, &data) {
-        Ok(Some(menu_id))=>{
-            Ok(warp::reply::with_status(
+pub async fn create_menu_handler(conn: PooledConnection, data: Menu) -> Result<impl warp::Reply, warp::Rejection> {
+    if let Some(message) = invalid_price_message(&conn, [(data.name.as_str(), data.price)]) {
+        return Err(ApiError::Validation(message).into());
+    }
+    let menu_already_exists = |menu_id: Option<i64>| {
+        ApiError::Conflict("Menu already exists".to_string()).with_details(json!({"id": menu_id}))
+    };
+    if let Some(menu_id) = Menu::get_existing_menu_id(&conn, &data).map_err(ApiError::from)? {
+        return Err(menu_already_exists(Some(menu_id)).into());
+    }
+    match Menu::create(&conn, &data) {
+        Ok(menu_id) => {
+            Ok(with_location(warp::reply::with_status(
                 warp::reply::json(&json!({ "id": menu_id })),
                 warp::http::StatusCode::CREATED,
-            ))
-        }
-        Ok(None)=>{
-            match Menu::create(&conn, &data) {
-                Ok(menu_id) => {
-                    Ok(warp::reply::with_status(
-                        warp::reply::json(&json!({ "id": menu_id })),
-                        warp::http::StatusCode::CREATED,
-                    ))
-                }
-                Err(_err) => {
-                    // Respond with an error
-                    Ok(warp::reply::with_status(
-                        warp::reply::json(&json!({ "error": "Error creating Menu" })),
-                        warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                    ))
-                }
-            }
+            ), format!("/menus/{}", menu_id)))
         }
-        Err(_err) => {
-            // Respond with an error
-            Ok(warp::reply::with_status(
-                warp::reply::json(&json!({ "error": "Error creating Menu" })),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
+        // Another request created the same name since the check above
+        Err(err) if is_unique_violation(&err) => {
+            Err(menu_already_exists(Menu::get_existing_menu_id(&conn, &data).ok().flatten()).into())
         }
-}
+        Err(err) => Err(ApiError::from(err).into()),
+    }
 }
 
 
 
 // Order Handlers
 
-/// Create a new order
-pub async fn create_order_handler(conn: Connection, req_body: OrderRequestBody) -> Result<impl warp::Reply, warp::Rejection> {
+/// Repeated menus collapsed into (menu_id, count) in the order each menu first appears, summing their
+/// quantities, so a menu sent many times costs one write instead of one per repeat
+fn count_menu_ids(lines: impl IntoIterator<Item = (i64, i64)>) -> Vec<(i64, i64)> {
+    let mut positions: HashMap<i64, usize> = HashMap::new();
+    let mut counts: Vec<(i64, i64)> = Vec::new();
+    for (menu_id, quantity) in lines {
+        let position = *positions.entry(menu_id).or_insert_with(|| {
+            counts.push((menu_id, 0));
+            counts.len() - 1
+        });
+        // Saturating, so a sum too large for i64 is still refused by the quantity limit
+        counts[position].1 = counts[position].1.saturating_add(quantity);
+    }
+    counts
+}
+
+/// The quantity of an item holding `current` once `count` more are added, refused when it overflows or goes
+/// past the max_line_quantity setting `max`
+fn checked_line_quantity(menu_id: i64, current: i64, count: i64, max: i64) -> Result<i64, String> {
+    current.checked_add(count)
+        .filter(|&quantity| quantity <= max)
+        .ok_or_else(|| format!("Quantity of menu {} must not exceed {}", menu_id, max))
+}
+
+fn set_item_notes(conn: &rusqlite::Connection, order_id: i64, notes: &[(i64, String)]) -> rusqlite::Result<()> {
+    for (menu_id, notes) in notes {
+        OrderItem::set_notes(conn, order_id, *menu_id, notes)?;
+    }
+    Ok(())
+}
+
+/// Create a new order. A new running order is found at /orders/{table_id}, its Location
+pub async fn create_order_handler(mut conn: PooledConnection, req_body: OrderRequestBody, context: RequestContext, clock: Arc<dyn Clock>, cooking_times: Arc<dyn CookingTimeSource>) -> Result<impl warp::Reply, warp::Rejection> {
+    let location = format!("/orders/{}", req_body.table_id);
+    let reply = create_order(&mut conn, req_body, context, clock, cooking_times).await?;
+    Ok(with_location(reply, location))
+}
+
+/// Most orders POST /orders/batch creates in one call
+pub const MAX_BATCH_ORDERS: usize = 100;
+
+/// Create several orders for load tests and demos, one after another, each in its own transaction. A failing
+/// order does not stop the others, every order gets the status and body it would have got on its own
+pub async fn batch_create_orders_handler(mut conn: PooledConnection, orders: Vec<OrderRequestBody>, context: RequestContext, clock: Arc<dyn Clock>, cooking_times: Arc<dyn CookingTimeSource>) -> Result<impl warp::Reply, warp::Rejection> {
+    if orders.is_empty() || orders.len() > MAX_BATCH_ORDERS {
+        return Err(ApiError::Validation(format!("send between 1 and {} orders", MAX_BATCH_ORDERS)).into());
+    }
+    let mut results = Vec::with_capacity(orders.len());
+    for order in orders {
+        let table_id = order.table_id;
+        let response = match create_order(&mut conn, order, context.clone(), Arc::clone(&clock), Arc::clone(&cooking_times)).await {
+            Ok(reply) => reply.into_response(),
+            Err(rejection) => crate::routes::handle_rejection(rejection).await?.into_response(),
+        };
+        let status = response.status().as_u16();
+        let body = warp::hyper::body::to_bytes(response.into_body()).await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<serde_json::Value>(&bytes).ok())
+            .unwrap_or(serde_json::Value::Null);
+        results.push(json!({"table_id": table_id, "status": status, "body": body}));
+    }
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"results": results})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+async fn create_order(conn: &mut Connection, req_body: OrderRequestBody, context: RequestContext, clock: Arc<dyn Clock>, cooking_times: Arc<dyn CookingTimeSource>) -> Result<impl warp::Reply, warp::Rejection> {
     let table_id = req_body.table_id;
-    let menu_ids = req_body.menu_ids;
-    if menu_ids.len() == 0{
-        return Ok(warp::reply::with_status(
-            warp::reply::json(&json!({"error":"Please Add Items"})),
-            warp::http::StatusCode::BAD_REQUEST,
-        ));
+    // The old menu_ids form is one entry per portion, items carries the quantity and notes of each menu
+    let notes: Vec<(i64, String)> = req_body.items.iter().flatten()
+        .filter_map(|line| Some((line.menu_id, line.notes.as_deref()?.trim().to_string())))
+        .filter(|(_, notes)| !notes.is_empty())
+        .collect();
+    let lines = match req_body.items {
+        Some(items) => {
+            if items.iter().any(|line| line.quantity == 0) {
+                return Err(ApiError::Validation("Quantity must be at least 1".to_string()).into());
+            }
+            count_menu_ids(items.iter().map(|line| (line.menu_id, i64::from(line.quantity))))
+        }
+        None => count_menu_ids(req_body.menu_ids.iter().map(|&menu_id| (menu_id, 1))),
+    };
+    let menu_ids: Vec<i64> = lines.iter().map(|&(menu_id, _)| menu_id).collect();
+    let customer_id = req_body.customer_id;
+    let force = req_body.force;
+    let on_existing = req_body.on_existing;
+    let force_unavailable = req_body.force_unavailable;
+    let party_size = req_body.party_size;
+    if menu_ids.is_empty(){
+        return Err(ApiError::Validation("Please Add Items".to_string()).into());
+    }
+    let max_quantity = Setting::max_line_quantity(conn).map_err(ApiError::from)?;
+    for &(menu_id, count) in &lines {
+        checked_line_quantity(menu_id, 0, count, max_quantity).map_err(ApiError::Validation)?;
+    }
+    if Setting::past_last_orders(conn, minute_of_day(clock.now())).map_err(ApiError::from)? {
+        return Err(ApiError::Unavailable("last orders have passed".to_string()).into());
+    }
+    if party_size.is_some_and(|size| size < 1) {
+        return Err(ApiError::Validation("Party size must be at least 1".to_string()).into());
+    }
+    if force_unavailable && !context.is_manager() {
+        return Err(ApiError::Forbidden("Only a manager can order unavailable items".to_string()).into());
     }
+    if let Some(customer_id) = customer_id {
+        if Customer::get(conn, customer_id).map_err(ApiError::from)?.is_none() {
+            return Err(ApiError::NotFound("Customer not found".to_string()).into());
+        }
+    }
+    // Unavailable menus a manager pushed through, noted in the audit log once the order is saved
+    let forced_names = Menu::unavailable_names(conn, &menu_ids).map_err(ApiError::from)?;
+    if !forced_names.is_empty() && !force_unavailable {
+        return Err(ApiError::Conflict("Some items are unavailable".to_string())
+            .with_details(json!({"unavailable": forced_names}))
+            .into());
+    }
+    // Rejecting or replacing gives the same result when the request is sent twice, no need to catch double taps
+    if !force && on_existing == OnExisting::Append {
+        let duplicates = Setting::duplicate_add_window_seconds(conn).and_then(|window_seconds| {
+            if window_seconds > 0 {
+                OrderItem::recent_duplicate_names(conn, table_id, &menu_ids, window_seconds)
+            } else {
+                Ok(vec![])
+            }
+        }).map_err(ApiError::from)?;
+        if !duplicates.is_empty() {
+            return Err(ApiError::Conflict("These items were just added to this table, send force to add them again".to_string())
+                .with_details(json!({"duplicates": duplicates}))
+                .into());
+        }
+    }
+    // Taking the write lock up front lets a concurrent order wait on the busy timeout. A deferred transaction
+    // that reads first fails right away with "database is locked" when another write commits in between
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(ApiError::from)?;
+    // Dropping the transaction on an error return rolls back the order and every item written so far
+
     // Check if there is an existing order with status 0 (running order) for the given table_id
-    match OrderResponse::get_existing_order_id(&conn, table_id) {
-        Ok(Some(order_id)) => {
+    match OrderResponse::get_existing_order_id(&tx, table_id).map_err(ApiError::from)? {
+        Some(order_id) => {
+            // The table may have been deleted out from under its running order
+            if !Table::exists(&tx, table_id).map_err(ApiError::from)? {
+                return Err(ApiError::NotFound("table no longer exists".to_string()).into());
+            }
+            if on_existing == OnExisting::Reject {
+                return Err(ApiError::Conflict("This table already has a running order".to_string())
+                    .with_details(json!({"id": order_id}))
+                    .into());
+            }
+            // Ready and served lines are done in the kitchen, more portions on them would never be cooked and
+            // replacing the order would throw them away. Replace is refused when any line is locked
+            let statuses = Setting::locked_item_statuses(&tx).map_err(ApiError::from)?;
+            let locked = OrderItem::locked_items(&tx, order_id, &statuses).map_err(ApiError::from)?;
+            if let Some(item) = locked.into_iter().find(|item| on_existing == OnExisting::Replace || menu_ids.contains(&item.menu_id)) {
+                return Err(ApiError::Conflict(format!("{} is already {}, it can no longer be changed", item.menu_name, item.item_status)).into());
+            }
+            if on_existing == OnExisting::Replace {
+                OrderItem::clear_order(&tx, order_id).map_err(ApiError::from)?;
+            }
             // Order exists for the given table_id, update the order items
-            for menu_id in menu_ids {
-                // Generate a random cooking time
-                let cooking_time = rand::thread_rng().gen_range(5..=15);
-                match OrderItem::get_existing_order_item_id(&conn, order_id, menu_id) {
-                    Ok(Some(order_item_id)) => {
-                         // Order item does exist, update quantity
-                         match OrderItem::add_quantity_of_existing_order_item(&conn, order_item_id){
-                            Ok(_)=>{
-                                continue;
-                            },
-                            Err(_)=>{
-                                return Ok(warp::reply::with_status(
-                                    warp::reply::json(&json!({"error":"Error updating order Item"})),
-                                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                                ));
-                            }
-                         }
+            for &(menu_id, count) in &lines {
+                let cooking_time = cooking_times.next();
+                match OrderItem::get_existing_order_item_id(&tx, order_id, menu_id).map_err(ApiError::from)? {
+                    Some(order_item_id) => {
+                        // Order item does exist, update quantity
+                        let current = OrderItem::get_quantity(&tx, order_item_id).map_err(ApiError::from)?;
+                        checked_line_quantity(menu_id, current, count, max_quantity).map_err(ApiError::Validation)?;
+                        OrderItem::add_to_quantity(&tx, order_item_id, count).map_err(ApiError::from)?;
                     }
-                    Ok(None) => {
+                    None => {
                         // Order item does not exist, create a new order item
-                        match OrderItem::create(&conn, order_id, menu_id, cooking_time) {
-                            Ok(_) => {
-                                // Continue to the next menu_id
-                                continue;
-                            }
-                            Err(_err) => {
-                                // Return an error response
-                                eprintln!("{}",_err);
-                                return Ok(warp::reply::with_status(
-                                    warp::reply::json(&json!({"error":"Error creating order Item"})),
-                                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                                ));
-                            }
-                        }
-                    }
-                    Err(_err) => {
-                        // Return an error response
-                        return Ok(warp::reply::with_status(
-                            warp::reply::json(&json!({"error":"Error creating for existing order Item"})),
-                            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                        ));
+                        OrderItem::create_with_quantity(&tx, order_id, menu_id, cooking_time, count).map_err(ApiError::from)?;
                     }
                 }
             }
 
+            set_item_notes(&tx, order_id, &notes).map_err(ApiError::from)?;
+            if let Some(customer_id) = customer_id {
+                OrderResponse::set_customer(&tx, order_id, customer_id).map_err(ApiError::from)?;
+            }
+            if let Some(party_size) = party_size {
+                OrderResponse::set_party_size(&tx, order_id, party_size).map_err(ApiError::from)?;
+            }
+
+            if let Err(_err) = OrderItem::record_recent_adds(&tx, table_id, &menu_ids) {
+                tracing::error!("{}", _err);
+            }
+            record_forced_unavailable(&tx, order_id, &context, &forced_names);
+            tx.commit().map_err(ApiError::from)?;
+            publish_order_event("order_updated", order_id, table_id, &context);
+
             // If you reach this point, it means all order items were successfully handled
             Ok(warp::reply::with_status(
                 warp::reply::json(&json!({"success":"All order items updated successfully"})),
                 warp::http::StatusCode::OK,
             ))
         }
-        Ok(None) => {
+        None => {
             // No running order exists for the given table_id, create a new order and order items
-            match OrderResponse::create(&conn, table_id) {
-                Ok(last_inserted_id) => {
-                    for menu_id in menu_ids {
-                        // Generate a random cooking time
-                        let cooking_time = rand::thread_rng().gen_range(5..=15);
-                        match OrderItem::create(&conn, last_inserted_id, menu_id, cooking_time) {
-                            Ok(_) => {
-                                // Continue to the next menu_id
-                                continue;
-                            }
-                            Err(_err) => {
-                                // Return an error response
-                                eprintln!("{}",_err);
-                                return Ok(warp::reply::with_status(
-                                    warp::reply::json(&json!({"error":"Error creating order Item"})),
-                                    warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                                ));
-                            }
-                        }
-                    }
-
-                    Ok(warp::reply::with_status(
-                        warp::reply::json(&json!({"id":last_inserted_id, "success":"Order and All Order Item Created Successfully"})),
-                        warp::http::StatusCode::CREATED,
-                    ))
-                }
-                Err(_err) => {
-                    // Return an error response
-                    Ok(warp::reply::with_status(
-                        warp::reply::json(&json!({"error":format!("Error creating order {}", _err)})),
-                        warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                    ))
+            let last_inserted_id = match OrderResponse::create(&tx, table_id) {
+                Ok(last_inserted_id) => last_inserted_id,
+                Err(err) if is_foreign_key_violation(&err) => {
+                    return Err(ApiError::NotFound(format!("Table {} not found", table_id)).into());
                 }
+                Err(err) => return Err(ApiError::from(err).into()),
+            };
+            for &(menu_id, count) in &lines {
+                let cooking_time = cooking_times.next();
+                OrderItem::create_with_quantity(&tx, last_inserted_id, menu_id, cooking_time, count).map_err(ApiError::from)?;
             }
-        }
-        Err(_err) => {
-            // Return an error response
+            set_item_notes(&tx, last_inserted_id, &notes).map_err(ApiError::from)?;
+            if let Some(customer_id) = customer_id {
+                OrderResponse::set_customer(&tx, last_inserted_id, customer_id).map_err(ApiError::from)?;
+            }
+            if let Some(party_size) = party_size {
+                OrderResponse::set_party_size(&tx, last_inserted_id, party_size).map_err(ApiError::from)?;
+            }
+
+            if let Err(_err) = OrderItem::record_recent_adds(&tx, table_id, &menu_ids) {
+                tracing::error!("{}", _err);
+            }
+            record_forced_unavailable(&tx, last_inserted_id, &context, &forced_names);
+            tx.commit().map_err(ApiError::from)?;
+            publish_order_event("order_created", last_inserted_id, table_id, &context);
+
             Ok(warp::reply::with_status(
-                warp::reply::json(&json!({"error":"Error checking for existing order"})),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
+                warp::reply::json(&json!({"id":last_inserted_id, "success":"Order and All Order Item Created Successfully"})),
+                warp::http::StatusCode::CREATED,
             ))
         }
     }
 }
 
-/// List All Orders
-pub async fn list_order_handler(conn: Connection)-> Result<impl warp::Reply, warp::Rejection>{
-    match OrderResponse::list(&conn) {
-        Ok(menus) => {
-            Ok(warp::reply::with_status(
-                warp::reply::json(&menus),
-                warp::http::StatusCode::OK,
-            ))
-        }
-        Err(_err) => {
-            Ok(
-                warp::reply::with_status(
-                warp::reply::json::<Vec<OrderResponse>>(&vec![]),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
-        }
+/// Audit unavailable menus a manager forced onto an order
+fn record_forced_unavailable(conn: &Connection, order_id: i64, context: &RequestContext, names: &[String]) {
+    if names.is_empty() {
+        return;
+    }
+    if let Err(_err) = AuditEntry::record(conn, order_id, "force_unavailable", context.actor.as_deref(), Some(&names.join(", "))) {
+        tracing::error!("{}", _err);
     }
 }
 
-/// Delete Specific Order Item from Order By Table
-pub async fn delete_order_item_handler(conn: Connection, table_id: i64, menu_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
+fn publish_order_event(event: &str, order_id: i64, table_id: i64, context: &RequestContext) {
+    events::publish(OrderEvent {
+        event: event.to_string(),
+        order_id,
+        table_id,
+        menu_id: None,
+        request_id: context.request_id.clone(),
+    });
+}
 
-    // Decrease the item quantity if greater than 1
-    let result = conn.execute(
-        "UPDATE order_items 
-        SET cooking_time = cooking_time - (cooking_time/quantity), quantity = quantity - 1
-        WHERE order_items.order_id IN (
-            SELECT orders.id
-            FROM orders
-            JOIN tables ON orders.table_id = tables.id
-            WHERE tables.id = ?1
-        ) AND order_items.menu_id = ?2 AND order_items.quantity > 1",
-        params![table_id, menu_id],
-    );
-
-    match result {
-        Ok(updated) => {
-            if updated > 0 {
-                // If quantity was greater than 1, update and return success
-                Ok(warp::reply::with_status(
-                    warp::reply::json(&json!({"success": "Menu quantity updated successfully"})),
-                    warp::http::StatusCode::OK,
-                ))
-            } else {
-                // Quantity is 1, delete the order item
-                let delete_result = conn.execute(
-                    "DELETE FROM order_items 
-                    WHERE order_items.order_id IN (
-                        SELECT orders.id
-                        FROM orders
-                        JOIN tables ON orders.table_id = tables.id
-                        WHERE tables.id = ?1
-                    ) AND order_items.menu_id = ?2",
-                    params![table_id, menu_id],
-                );
-
-                match delete_result {
-                    Ok(_) => {
-                        let order_id_result = OrderResponse::get_existing_order_id(&conn, table_id);
-
-                        match order_id_result {
-                            Ok(Some(order_id)) => {
-                                let has_items = OrderResponse::has_items(&conn, order_id);
-
-                                match has_items {
-                                    Ok(false) => {
-                                        // If there are no more items, delete the order as well
-                                        let _ = conn.execute("DELETE from orders WHERE id = ?", params![order_id]);
-
-                                        Ok(warp::reply::with_status(
-                                            warp::reply::json(&json!({"success": "Menu deleted successfully and order deleted"})),
-                                            warp::http::StatusCode::OK,
-                                        ))
-                                    }
-                                    Ok(true)=>{
-                                        Ok(warp::reply::with_status(
-                                            warp::reply::json(&json!({"success": "Menu deleted successfully"})),
-                                            warp::http::StatusCode::OK,
-                                        )) 
-                                    }
-                                    Err(_err) => {
-                                        Ok(warp::reply::with_status(
-                                            warp::reply::json(&json!({"error": "Menu deleted failed"})),
-                                            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                                        ))
-                                    }
-                                }
-                            }
-                            _ => Ok(warp::reply::with_status(
-                                warp::reply::json(&json!({"error": "Failed to retrieve order ID"})),
-                                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                            )),
-                        }
-                    }
-                    Err(_) => {
-                        Ok(warp::reply::with_status(
-                            warp::reply::json(&json!({"error": "Menu delete failed"})),
-                            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-                        ))
-                    }
-                }
-            }
-        }
-        Err(_err) => {
-            eprintln!("Failed to update quantity: {:?}", _err);
-            Ok(warp::reply::with_status(
-                warp::reply::json(&json!({"error": "Failed to update quantity"})),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-            ))
-        }
+fn publish_item_event(event: &str, order_id: i64, table_id: i64, menu_id: i64, context: &RequestContext) {
+    events::publish(OrderEvent {
+        event: event.to_string(),
+        order_id,
+        table_id,
+        menu_id: Some(menu_id),
+        request_id: context.request_id.clone(),
+    });
+}
+
+/// Why a list of order operations was not applied
+enum OperationError {
+    /// The operation at this index is not valid, BAD REQUEST
+    Invalid(usize, String),
+    /// The operation at this index changes a ready or served item, CONFLICT
+    Locked(usize, String),
+    Database(rusqlite::Error),
+}
+
+impl From<rusqlite::Error> for OperationError {
+    fn from(err: rusqlite::Error) -> OperationError {
+        OperationError::Database(err)
     }
 }
 
-/// List All Orders for a specific table
-pub async fn list_order_items_for_table_handler(conn: Connection, table_id:i64)-> Result<impl warp::Reply, warp::Rejection>{
-    match OrderItem::list_order_items(&conn, table_id) {
-        Ok(items) => {
-            Ok(warp::reply::with_status(
-                warp::reply::json(&items),
-                warp::http::StatusCode::OK
-            ))
+/// Apply the operations in order, stopping at the first one that fails
+fn apply_order_operations(conn: &Connection, table_id: i64, order_id: i64, operations: &[OrderOperation], locked: &[&str], cooking_times: &dyn CookingTimeSource) -> Result<(), OperationError> {
+    let max_quantity = Setting::max_line_quantity(conn)?;
+    for (index, operation) in operations.iter().enumerate() {
+        let (menu_id, quantity) = match operation {
+            OrderOperation::Add { menu_id } => (*menu_id, None),
+            OrderOperation::Remove { menu_id } => (*menu_id, None),
+            OrderOperation::SetQuantity { menu_id, quantity } => (*menu_id, Some(*quantity)),
+        };
+        if quantity.is_some_and(|quantity| quantity < 1) {
+            return Err(OperationError::Invalid(index, "quantity must be at least 1, use remove to take an item off".to_string()));
         }
-        Err(_err) => {
-            eprintln!("{}", _err);
-            Ok(warp::reply::with_status(
-                warp::reply::json::<Vec<OrderItemResponse>>(&vec![]),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR
-            ))
+        if let Some(Err(message)) = quantity.map(|quantity| checked_line_quantity(menu_id, 0, quantity, max_quantity)) {
+            return Err(OperationError::Invalid(index, message));
+        }
+        let item = OrderItem::get_item(conn, table_id, menu_id)?;
+        // More portions of a menu need it to be orderable, as for a new order, whether or not it is on the order yet
+        let adds_portions = match (operation, &item) {
+            (OrderOperation::Remove { .. }, _) => false,
+            (OrderOperation::Add { .. }, _) | (OrderOperation::SetQuantity { .. }, None) => true,
+            (OrderOperation::SetQuantity { quantity, .. }, Some(item)) => *quantity > item.quantity,
+        };
+        if adds_portions {
+            match Menu::get(conn, menu_id)? {
+                Some(menu) if menu.available && menu.active => {}
+                Some(menu) => return Err(OperationError::Invalid(index, format!("{} is unavailable", menu.name))),
+                None => return Err(OperationError::Invalid(index, format!("Menu {} not found", menu_id))),
+            }
+        }
+        if let (OrderOperation::Remove { .. } | OrderOperation::SetQuantity { .. }, Some(item)) = (operation, &item) {
+            if locked.contains(&item.item_status.as_str()) {
+                return Err(OperationError::Locked(index, format!("{} is already {}, it can no longer be changed", item.menu_name, item.item_status)));
+            }
+        }
+        match (operation, item) {
+            (OrderOperation::Remove { .. }, Some(item)) => {
+                OrderItem::remove_one(conn, item.id)?;
+            }
+            (OrderOperation::Remove { .. }, None) => {
+                return Err(OperationError::Invalid(index, format!("Menu {} is not on the order", menu_id)));
+            }
+            (OrderOperation::Add { .. }, Some(item)) => {
+                checked_line_quantity(menu_id, item.quantity, 1, max_quantity).map_err(|message| OperationError::Invalid(index, message))?;
+                OrderItem::add_quantity_of_existing_order_item(conn, item.id)?;
+            }
+            (OrderOperation::SetQuantity { quantity, .. }, Some(item)) => {
+                OrderItem::set_quantity(conn, item.id, *quantity)?;
+            }
+            (_, None) => {
+                let order_item_id = OrderItem::create(conn, order_id, menu_id, cooking_times.next())?;
+                if let Some(quantity) = quantity {
+                    OrderItem::set_quantity(conn, order_item_id, quantity)?;
+                }
+            }
         }
     }
+    // Like removing the last item one by one, an order left without items is deleted
+    if !OrderResponse::has_items(conn, order_id)? {
+        conn.execute("DELETE FROM orders WHERE id = ?1", params![order_id])?;
+    }
+    Ok(())
 }
 
-/// Retrieve a specific item from a specific table
-pub async fn get_order_item_for_table_handler(conn: Connection, table_id:i64, menu_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
-    match OrderItem::get_item(&conn, table_id, menu_id) {
-        Ok(Some(item)) => {
-            Ok(warp::reply::with_status(
-                warp::reply::json(&item),
-                warp::http::StatusCode::OK
-            ))
-        }
-        Ok(None) => {
-            Ok(warp::reply::with_status(
-                warp::reply::json(&json!({"error": "No Item Found"})),
-                warp::http::StatusCode::NOT_FOUND,
-            ))
+/// Apply several adds, removes and quantity changes to the running order of a table in one transaction.
+/// Nothing is changed when any of them fails
+pub async fn modify_order_handler(mut conn: PooledConnection, table_id: i64, data: ModifyOrderRequest, context: RequestContext, cooking_times: Arc<dyn CookingTimeSource>) -> Result<impl warp::Reply, warp::Rejection> {
+    if data.operations.is_empty() {
+        return Err(ApiError::Validation("Please Add Operations".to_string()).into());
+    }
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let locked = Setting::locked_item_statuses(&conn).map_err(ApiError::from)?;
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error rolls back the operations already applied
+    let applied = apply_order_operations(&tx, table_id, order_id, &data.operations, &locked, cooking_times.as_ref())
+        .and_then(|_| tx.commit().map_err(OperationError::from));
+    match applied {
+        Ok(()) => {}
+        Err(OperationError::Invalid(index, error)) => {
+            return Err(ApiError::Validation(error).with_details(json!({"operation": index})).into());
         }
-        Err(_err) => {
-            eprintln!("{}", _err);
-            Ok(warp::reply::with_status(
-                warp::reply::json(&json!({"error": "Something Wrong!"})),
-                warp::http::StatusCode::INTERNAL_SERVER_ERROR
-            ))
+        Err(OperationError::Locked(index, error)) => {
+            return Err(ApiError::Conflict(error).with_details(json!({"operation": index})).into());
         }
+        Err(OperationError::Database(err)) => return Err(ApiError::from(err).into()),
     }
+    publish_order_event("order_updated", order_id, table_id, &context);
+    let items = OrderItem::list_order_items(&conn, table_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"id": order_id, "items": items})),
+        warp::http::StatusCode::OK,
+    ))
 }
 
+/// Set the quantity of a menu on the running order of a table, scaling its cooking time along. 0 takes the item
+/// off, and the order with it when that was its last item. Ready and served items are locked as for modify
+pub async fn set_order_item_quantity_handler(mut conn: PooledConnection, table_id: i64, menu_id: i64, data: QuantityUpdate, context: RequestContext) -> Result<impl warp::Reply, warp::Rejection> {
+    if data.quantity < 0 {
+        return Err(ApiError::Validation("quantity must not be negative".to_string()).into());
+    }
+    let max_quantity = Setting::max_line_quantity(&conn).map_err(ApiError::from)?;
+    checked_line_quantity(menu_id, 0, data.quantity, max_quantity).map_err(ApiError::Validation)?;
+    // Immediate, so the item cannot be readied or removed between the lock check and the change
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(ApiError::from)?;
+    let item = OrderItem::get_item(&tx, table_id, menu_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound(format!("Menu {} is not on the running order", menu_id)))?;
+    if Setting::locked_item_statuses(&tx).map_err(ApiError::from)?.contains(&item.item_status.as_str()) {
+        return Err(ApiError::Conflict(format!("{} is already {}, it can no longer be changed", item.menu_name, item.item_status)).into());
+    }
+    // Dropping the transaction on error puts the item back when its order cannot be deleted
+    let order_deleted = if data.quantity == 0 {
+        OrderItem::delete(&tx, item.id).map_err(ApiError::from)?;
+        !OrderResponse::has_items(&tx, item.order_id).map_err(ApiError::from)?
+            && delete_emptied_order(&tx, item.order_id)?
+    } else {
+        OrderItem::set_quantity(&tx, item.id, data.quantity).map_err(ApiError::from)?;
+        false
+    };
+    tx.commit().map_err(ApiError::from)?;
+    publish_item_event("item_quantity_set", item.order_id, table_id, menu_id, &context);
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"order_id": item.order_id, "menu_id": menu_id, "quantity": data.quantity, "order_deleted": order_deleted})),
+        warp::http::StatusCode::OK,
+    ))
+}
 
-/// Unit Tests
-#[cfg(test)]
-mod tests {
-    use warp::{Reply, hyper::Body};
-    use super::*;
+/// Reseat the running order of one table at another. NOT FOUND when the source has no running order or the
+/// destination does not exist, CONFLICT when the destination already has a running order
+pub async fn move_order_handler(mut conn: PooledConnection, from_table_id: i64, to_table_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
+    if from_table_id == to_table_id {
+        return Err(ApiError::Validation("An order cannot be moved to the table it is on".to_string()).into());
+    }
+    // Immediate, so no order can be opened on the destination between the check and the move
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(ApiError::from)?;
+    let order_id = OrderResponse::get_existing_order_id(&tx, from_table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound(format!("Table {} has no running order", from_table_id)))?;
+    if !Table::exists(&tx, to_table_id).map_err(ApiError::from)? {
+        return Err(ApiError::NotFound(format!("Table {} not found", to_table_id)).into());
+    }
+    if OrderResponse::get_existing_order_id(&tx, to_table_id).map_err(ApiError::from)?.is_some() {
+        return Err(ApiError::Conflict(format!("Table {} already has a running order", to_table_id)).into());
+    }
+    OrderResponse::move_to_table(&tx, order_id, to_table_id).map_err(ApiError::from)?;
+    tx.commit().map_err(ApiError::from)?;
+    let order = OrderResponse::get(&conn, order_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(warp::reply::json(&order), warp::http::StatusCode::OK))
+}
 
+/// Total of a table's running order, quantity * price of its items with comped items free.
+/// Tax and service charge are left out, the bill and receipt add them
+pub async fn get_order_total_handler(conn: PooledConnection, table_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let total = OrderResponse::total(&conn, order_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"total": total})),
+        warp::http::StatusCode::OK,
+    ))
+}
 
-    // Set up the test database
-    fn setup_test_db() -> Connection {
-        println!("Initializing the test database...");
-        let conn = Connection::open_in_memory().expect("Failed to create test database");
-        conn.execute("PRAGMA foreign_keys = ON;", []).expect("Failed to enable foreign key support");
-        conn.execute("CREATE TABLE IF NOT EXISTS tables (id INTEGER PRIMARY KEY,code TEXT NOT NULL UNIQUE)",[]).expect("Table table creation failed");
-        conn.execute("CREATE TABLE IF NOT EXISTS menus (id INTEGER PRIMARY KEY, name TEXT NOT NULL)",[]).expect("Menu table creation failed");
-        conn.execute("CREATE TABLE IF NOT EXISTS orders (id INTEGER PRIMARY KEY, table_id INTEGER NOT NULL, FOREIGN KEY (table_id) REFERENCES tables(id), UNIQUE (table_id))",[]).expect("Order table creation failed");
-        conn.execute("CREATE TABLE IF NOT EXISTS order_items (id INTEGER PRIMARY KEY, order_id INTEGER NOT NULL, menu_id INTEGER NOT NULL, cooking_time INTEGER NOT NULL,  quantity INTEGER NOT NULL default 1, FOREIGN KEY (order_id) REFERENCES orders(id), FOREIGN KEY (menu_id) REFERENCES menus(id))",[]).expect("OrderItems table creation failed");
-        conn
+/// What changed in the items of a table's running order since the version the client holds
+pub async fn order_diff_handler(conn: PooledConnection, table_id: i64, since_version: i64) -> Result<impl warp::Reply, warp::Rejection> {
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let current = OrderDiff::current_version(&conn, order_id).map_err(ApiError::from)?;
+    if since_version < 0 || since_version > current {
+        return Err(ApiError::Validation(format!("since must be between 0 and the order version {}", current)).into());
     }
+    let diff = OrderDiff::since(&conn, order_id, current, since_version).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&diff),
+        warp::http::StatusCode::OK,
+    ))
+}
 
-    // Inserting static table and menu data
-    fn setup_static_data(conn: &Connection){
-        let values_to_insert = vec!["T-01", "T-02", "T-03"];
+/// List All Orders
+pub async fn list_order_handler(conn: PooledConnection, page: PageQuery)-> Result<impl warp::Reply, warp::Rejection>{
+    let (limit, offset) = page.resolve().map_err(ApiError::Validation)?;
+    let orders = OrderResponse::list(&conn, limit, offset).map_err(ApiError::from)?;
+    let total = OrderResponse::count(&conn).map_err(ApiError::from)?;
+    let count = orders.len();
+    Ok(with_large_list_hint(&conn, warp::reply::with_status(
+        warp::reply::json(&Page { items: orders, total, limit, offset }),
+        warp::http::StatusCode::OK,
+    ), count))
+}
 
-        for value in values_to_insert {
-            conn.execute("INSERT INTO tables (code) VALUES (?1)", &[value]).expect("Insertion Failed");
-        }
-        let values_to_insert = vec!["M-01", "M-02", "M-03", "M-04", "M-05"];
+/// List open Orders for the floor dashboard with an age based urgency (green, amber, red)
+pub async fn floor_status_handler(conn: PooledConnection, clock: Arc<dyn Clock>)-> Result<impl warp::Reply, warp::Rejection>{
+    let orders = FloorOrderResponse::list(&conn, clock.now()).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&orders),
+        warp::http::StatusCode::OK
+    ))
+}
 
-        for value in values_to_insert {
-            conn.execute("INSERT INTO menus (name) VALUES (?1)", &[value]).expect("Insertion Failed");
+/// Get an order by its daily order number, including paid and voided orders.
+/// `date` (YYYY-MM-DD) picks the day the number belongs to, today when omitted
+pub async fn get_order_by_number_handler(conn: PooledConnection, order_number: String, date: Option<String>, clock: Arc<dyn Clock>)-> Result<impl warp::Reply, warp::Rejection>{
+    let date = match date {
+        Some(date) => date,
+        None => conn.query_row("SELECT date(?1, 'unixepoch')", [clock.now()], |row| row.get(0)).map_err(ApiError::from)?,
+    };
+    check_date(&conn, &date)?;
+    let order = OrderResponse::get_by_number(&conn, &order_number, &date)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Order Found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&order),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Attach a customer to the running order of a table
+pub async fn attach_customer_handler(conn: PooledConnection, table_id: i64, data: AttachCustomerRequest) -> Result<impl warp::Reply, warp::Rejection> {
+    let customer = Customer::get(&conn, data.customer_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("Customer not found".to_string()))?;
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    OrderResponse::set_customer(&conn, order_id, customer.id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"id": order_id, "customer": customer})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Suggested tips for the running order of a table, at the configured tip_percentages.
+/// There are none when service is included in the bill
+pub async fn tip_suggestions_handler(conn: PooledConnection, table_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let percentages = if Setting::service_included(&conn).map_err(ApiError::from)? {
+        vec![]
+    } else {
+        Setting::tip_percentages(&conn).map_err(ApiError::from)?
+    };
+    let charge_cents = OrderResponse::charge_cents(&conn, order_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&TipSuggestions::for_total(order_id, charge_cents, &percentages)),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Delete Specific Order Item from Order By Table
+/// Items that are ready or served are locked (see the lock_items_from setting) unless a manager forces it
+pub async fn delete_order_item_handler(mut conn: PooledConnection, table_id: i64, menu_id: i64, query: RemoveItemQuery, context: RequestContext) -> Result<impl warp::Reply, warp::Rejection> {
+    if query.force && !context.is_manager() {
+        return Err(ApiError::Forbidden("Only a manager can change ready or served items".to_string()).into());
+    }
+    // The audit entry, the item and an emptied order are written together, or not at all
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(ApiError::from)?;
+    let statuses = Setting::locked_item_statuses(&tx).map_err(ApiError::from)?;
+    let locked = OrderItem::get_item(&tx, table_id, menu_id)
+        .map_err(ApiError::from)?
+        .filter(|item| statuses.contains(&item.item_status.as_str()));
+    if let Some(item) = locked {
+        if !query.force {
+            return Err(ApiError::Conflict(format!("{} is already {}, it can no longer be changed", item.menu_name, item.item_status)).into());
         }
+        let note = format!("{}: {}", item.menu_name, item.item_status);
+        AuditEntry::record(&tx, item.order_id, "remove_locked_item", context.actor.as_deref(), Some(&note)).map_err(ApiError::from)?;
+    }
+
+    // The running order is looked up once, every change below is made to that order
+    let order_id = OrderResponse::get_existing_order_id(&tx, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("Item not on this table".to_string()))?;
+
+    // Decrease the item quantity if greater than 1
+    let updated = tx.execute(
+        "UPDATE order_items 
+        SET cooking_time = cooking_time - (cooking_time/quantity), quantity = quantity - 1
+        WHERE order_id = ?1 AND menu_id = ?2 AND quantity > 1",
+        params![order_id, menu_id],
+    ).map_err(ApiError::from)?;
 
+    if updated > 0 {
+        tx.commit().map_err(ApiError::from)?;
+        publish_item_event("item_removed", order_id, table_id, menu_id, &context);
+        // If quantity was greater than 1, update and return success
+        return Ok(warp::reply::with_status(
+            warp::reply::json(&json!({"success": "Menu quantity updated successfully"})),
+            warp::http::StatusCode::OK,
+        ));
+    }
+    // Quantity is 1, delete the order item
+    let deleted = tx.execute(
+        "DELETE FROM order_items WHERE order_id = ?1 AND menu_id = ?2",
+        params![order_id, menu_id],
+    ).map_err(ApiError::from)?;
+    // Neither the update nor the delete touched a row, the menu is not on the running order
+    if deleted == 0 {
+        return Err(ApiError::NotFound("Item not on this table".to_string()).into());
     }
+    let success = if OrderResponse::has_items(&tx, order_id).map_err(ApiError::from)? {
+        "Menu deleted successfully"
+    } else {
+        // If there are no more items, delete the order as well. Dropping the transaction keeps the item
+        // when the order has audit history
+        delete_emptied_order(&tx, order_id)?;
+        "Menu deleted successfully and order deleted"
+    };
+    tx.commit().map_err(ApiError::from)?;
+    publish_item_event("item_removed", order_id, table_id, menu_id, &context);
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"success": success})),
+        warp::http::StatusCode::OK,
+    ))
+}
 
-    // Convert warp Response to serde Json Value
-    async fn convert_response_to_json(resp:  warp::http::Response<Body>)->serde_json::Value {
-        let body_bytes = warp::hyper::body::to_bytes(resp.into_body()).await.unwrap();
-        let body_vec = body_bytes.to_vec();
-        let body_string = String::from_utf8_lossy(&body_vec);
-        let json_value: serde_json::Value = serde_json::from_str(&body_string).unwrap();
-        return json_value;
+/// Cancel the running order of a table, deleting it with all its items in one transaction.
+/// Orders with audit entries (comps, forced items) are kept, they have to be voided instead
+pub async fn delete_order_handler(mut conn: PooledConnection, table_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error puts the items back when the order itself cannot be deleted
+    match OrderResponse::delete(&tx, order_id).and_then(|_| tx.commit()) {
+        Ok(()) => {}
+        Err(rusqlite::Error::SqliteFailure(err, _)) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            return Err(ApiError::Conflict("This order has audit history, void it instead".to_string()).into());
+        }
+        Err(err) => return Err(ApiError::from(err).into()),
     }
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"id": order_id, "success": "Order cancelled"})),
+        warp::http::StatusCode::OK,
+    ))
+}
 
-    // Test Case: 01 Menu Creation
-    #[tokio::test]
-    async fn test_create_menu_handler() {
+/// Void the running order of a table. Unlike removing its items, the order is kept (status void) for history
+/// and excluded from revenue. The table is free for a new order afterwards
+pub async fn void_order_handler(conn: PooledConnection, table_id: i64, data: VoidOrderRequest) -> Result<impl warp::Reply, warp::Rejection> {
+    let reason = data.reason.trim();
+    if reason.is_empty() {
+        return Err(ApiError::Validation("A reason is required to void an order".to_string()).into());
+    }
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    OrderResponse::void(&conn, order_id, reason).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"id": order_id, "success": "Order voided"})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Pay the running Order for a specific table. The order is kept for history with status=paid
+/// and its customer, if any, is credited loyalty points
+/// An order with no charged items is only closed when a comp reason is given, which is audited
+pub async fn pay_order_handler(mut conn: PooledConnection, table_id: i64, query: PayOrderQuery, context: RequestContext) -> Result<impl warp::Reply, warp::Rejection> {
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let comp_reason = query.comp_reason.as_deref().map(str::trim).filter(|reason| !reason.is_empty());
+    let comp_note = match OrderResponse::chargeable_item_count(&conn, order_id).map_err(ApiError::from)? {
+        0 => match comp_reason {
+            Some(reason) => Some(reason),
+            None => return Err(ApiError::Validation("cannot pay an empty order".to_string()).into()),
+        },
+        _ => None,
+    };
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error rolls back both the payment and the points
+    let payment = OrderResponse::pay(&tx, order_id)
+        .and_then(|payment| match comp_note {
+            Some(note) => AuditEntry::record(&tx, order_id, "comp_order", context.actor.as_deref(), Some(note)).map(|_| payment),
+            None => Ok(payment),
+        })
+        .and_then(|payment| tx.commit().map(|_| payment))
+        .map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({
+            "id": order_id,
+            "total": to_amount(payment.total_cents),
+            "points_earned": payment.points_earned,
+            "success": "Order paid"
+        })),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Text ticket of an order for the kitchen printers, see OrderTicket::to_text
+pub async fn order_ticket_handler(conn: PooledConnection, order_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
+    let ticket = OrderTicket::for_order(&conn, order_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Order Found".to_string()))?;
+    // A String reply is sent as text/plain
+    Ok(warp::reply::with_status(
+        ticket.to_text(),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Move the running order of a table forward through open, preparing, served and paid.
+/// Going back or skipping to void is rejected with 400, moving to paid pays the order like /pay does
+pub async fn update_order_status_handler(mut conn: PooledConnection, table_id: i64, data: OrderStatusUpdate) -> Result<impl warp::Reply, warp::Rejection> {
+    let new_status: OrderStatus = data.status.parse().map_err(ApiError::Validation)?;
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let current = OrderResponse::status(&conn, order_id).map_err(ApiError::from)?;
+    if !current.can_move_to(new_status) {
+        return Err(ApiError::Validation(format!("cannot move an order from {} to {}", current.as_str(), new_status.as_str())).into());
+    }
+    if new_status == OrderStatus::Paid {
+        if OrderResponse::chargeable_item_count(&conn, order_id).map_err(ApiError::from)? == 0 {
+            return Err(ApiError::Validation("cannot pay an empty order".to_string()).into());
+        }
+        let tx = conn.transaction().map_err(ApiError::from)?;
+        // Dropping the transaction on error rolls back both the payment and the points
+        OrderResponse::pay(&tx, order_id).and_then(|_| tx.commit()).map_err(ApiError::from)?;
+    } else {
+        OrderResponse::set_status(&conn, order_id, new_status).map_err(ApiError::from)?;
+    }
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"id": order_id, "status": new_status.as_str()})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Status, cooking time, elapsed and remaining minutes of every item on the running Order of a table
+pub async fn order_prep_breakdown_handler(conn: PooledConnection, table_id: i64, clock: Arc<dyn Clock>)-> Result<impl warp::Reply, warp::Rejection>{
+    if OrderResponse::get_existing_order_id(&conn, table_id).map_err(ApiError::from)?.is_none() {
+        return Err(ApiError::NotFound("No running order for this table".to_string()).into());
+    }
+    let items = OrderItem::prep_breakdown(&conn, table_id, clock.now()).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&items),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Minutes until the running Order of a table is cooked, see OrderItem::remaining_time
+pub async fn get_table_eta_handler(conn: PooledConnection, table_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    if OrderResponse::get_existing_order_id(&conn, table_id).map_err(ApiError::from)?.is_none() {
+        return Err(ApiError::NotFound("No running order for this table".to_string()).into());
+    }
+    let eta_minutes = OrderItem::remaining_time(&conn, table_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"eta_minutes": eta_minutes})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Wall clock time the running Order of a table should be cooked, the remaining station time scaled during a rush
+pub async fn order_eta_handler(conn: PooledConnection, table_id: i64, clock: Arc<dyn Clock>)-> Result<impl warp::Reply, warp::Rejection>{
+    let order_id = match OrderResponse::get_existing_order_id(&conn, table_id).map_err(ApiError::from)? {
+        Some(order_id) => order_id,
+        None => return Err(ApiError::NotFound("No running order for this table".to_string()).into()),
+    };
+    let now = clock.now();
+    let minutes = OrderItem::remaining_station_time(&conn, table_id, now).map_err(ApiError::from)?;
+    let remaining_minutes = (minutes as f64 * Setting::rush_multiplier(&conn).map_err(ApiError::from)?).round() as i64;
+    let estimated_ready_at: String = conn
+        .query_row("SELECT strftime('%Y-%m-%dT%H:%M:%SZ', ?1, 'unixepoch')", [now + remaining_minutes * 60], |row| row.get(0))
+        .map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"order_id": order_id, "remaining_minutes": remaining_minutes, "estimated_ready_at": estimated_ready_at})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Estimated wait for the running Order of a table and the current kitchen load, scaled during a rush
+pub async fn estimated_wait_handler(conn: PooledConnection, table_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    let order_id = OrderResponse::get_existing_order_id(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let estimate = WaitEstimate::for_order(&conn, order_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&estimate),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Redeem a Customer's loyalty points as a discount on the running Order of a table.
+/// The customer attached to the order must have enough points and the discount cannot exceed what is left to pay
+pub async fn redeem_points_handler(mut conn: PooledConnection, table_id: i64, data: RedeemPointsRequest) -> Result<impl warp::Reply, warp::Rejection> {
+    if data.points <= 0 {
+        return Err(ApiError::Validation("Points to redeem must be greater than zero".to_string()).into());
+    }
+    // Immediate takes the write lock before the balances are read, so a concurrent redemption waits for this one
+    // and then sees the points it spent
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate).map_err(ApiError::from)?;
+    let order_id = OrderResponse::get_existing_order_id(&tx, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let order = OrderResponse::get(&tx, order_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    let discount_cents = data.points
+        .checked_mul(Setting::points_redemption_cents(&tx).map_err(ApiError::from)?)
+        .ok_or_else(|| ApiError::Validation("Too many points to redeem".to_string()))?;
+    let due_cents = OrderResponse::due_cents(&tx, order_id).map_err(ApiError::from)?;
+    let customer = order.customer
+        .ok_or_else(|| ApiError::Validation("No customer attached to this order".to_string()))?;
+    if customer.points < data.points {
+        return Err(ApiError::Validation(format!("Customer only has {} points", customer.points)).into());
+    }
+    if discount_cents > due_cents {
+        return Err(ApiError::Validation("Discount cannot exceed the order total".to_string()).into());
+    }
+    OrderResponse::redeem_points(&tx, order_id, customer.id, data.points, discount_cents)
+        .and_then(|_| tx.commit())
+        .map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({
+            "id": order_id,
+            "discount": to_amount(discount_cents),
+            "due": to_amount(due_cents - discount_cents),
+            "points_remaining": customer.points - data.points
+        })),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Itemized bill of the running Order of a table, with tax rounded per line to the cent
+pub async fn generate_bill_handler(conn: PooledConnection, table_id: i64) -> Result<impl warp::Reply, warp::Rejection> {
+    let bill = Bill::for_table(&conn, table_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No running order for this table".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&bill),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Reprint the receipt of an Order. Every reprint is recorded in the audit log with the actor asking for it
+pub async fn reprint_receipt_handler(mut conn: PooledConnection, order_id: i64, context: RequestContext) -> Result<impl warp::Reply, warp::Rejection> {
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    let mut receipt = Receipt::for_order(&tx, order_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Order Found".to_string()))?;
+    AuditEntry::record(&tx, order_id, "reprint", context.actor.as_deref(), None)
+        .and_then(|_| tx.commit())
+        .map_err(ApiError::from)?;
+    receipt.reprint_count += 1;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&receipt),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// List the audit log of an Order, oldest entry first
+pub async fn order_audit_handler(conn: PooledConnection, order_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    let entries = AuditEntry::list_for_order(&conn, order_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&entries),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Timeline of an Order, whatever its status: creation, item changes, audited actions and payment, oldest first
+pub async fn order_timeline_handler(conn: PooledConnection, order_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    let timeline = TimelineEntry::for_order(&conn, order_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("Order not found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&timeline),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// List All Orders for a specific table
+pub async fn list_order_items_for_table_handler(conn: PooledConnection, table_id:i64)-> Result<impl warp::Reply, warp::Rejection>{
+    let items = OrderItem::list_order_items(&conn, table_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&items),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Retrieve a specific item from a specific table
+pub async fn get_order_item_for_table_handler(conn: PooledConnection, table_id:i64, menu_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    let item = OrderItem::get_item(&conn, table_id, menu_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Item Found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&item),
+        warp::http::StatusCode::OK
+    ))
+}
+
+
+// Import Handler
+
+/// Create or update tables and menus from one payload, all in a single transaction.
+/// Nothing is kept if any part of the import fails
+pub async fn import_handler(mut conn: PooledConnection, data: ImportRequest)-> Result<impl warp::Reply, warp::Rejection>{
+    let prices = data.menus.iter().map(|menu| (menu.name.as_str(), menu.price));
+    data.validate()
+        .and_then(|_| invalid_price_message(&conn, prices).map_or(Ok(()), Err))
+        .map_err(ApiError::Validation)?;
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error rolls everything back
+    let summary = data.import(&tx)
+        .and_then(|summary| tx.commit().map(|_| summary))
+        .map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&summary),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// Kitchen Handlers
+
+/// Stream order events as server sent events, each named after its event and carrying the json payload
+pub async fn events_handler() -> Result<impl warp::Reply, warp::Rejection> {
+    let receiver = events::subscribe();
+    let event_stream = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse_event = warp::sse::Event::default()
+                        .event(event.event.clone())
+                        .json_data(&event)
+                        .unwrap_or_default();
+                    return Some((Ok::<_, Infallible>(sse_event), receiver));
+                }
+                // A subscriber that fell behind skips the missed events
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(event_stream)))
+}
+
+/// Stream order events over a websocket for kitchen displays, each a json text message. The open items of
+/// all running orders come first as a "snapshot" message
+pub async fn kitchen_ws_handler(conn: PooledConnection, ws: warp::ws::Ws) -> Result<impl warp::Reply, warp::Rejection> {
+    // Subscribed before reading the snapshot, so an item added in between is in the snapshot or in an event
+    let receiver = events::subscribe();
+    let items = OrderItem::list_open_items(&conn).map_err(ApiError::from)?;
+    Ok(ws.on_upgrade(move |socket| kitchen_socket(socket, items, receiver)))
+}
+
+async fn kitchen_socket(socket: warp::ws::WebSocket, items: Vec<OrderItemResponse>, mut receiver: tokio::sync::broadcast::Receiver<OrderEvent>) {
+    let (mut sender, mut incoming) = socket.split();
+    let snapshot = json!({"event": "snapshot", "items": items});
+    if sender.send(warp::ws::Message::text(snapshot.to_string())).await.is_err() {
+        return;
+    }
+    loop {
+        tokio::select! {
+            event = receiver.recv() => match event {
+                Ok(event) => {
+                    let message = serde_json::to_string(&event).unwrap_or_default();
+                    if sender.send(warp::ws::Message::text(message)).await.is_err() {
+                        return;
+                    }
+                }
+                // A display that fell behind skips the missed events
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(tokio::sync::broadcast::error::RecvError::Closed) => return,
+            },
+            // Displays only listen, anything they send is ignored until they close the socket
+            message = incoming.next() => match message {
+                Some(Ok(message)) if !message.is_close() => continue,
+                _ => return,
+            },
+        }
+    }
+}
+
+/// List items currently cooking ("on the pass") with the minutes elapsed since they were fired
+pub async fn on_the_pass_handler(conn: PooledConnection)-> Result<impl warp::Reply, warp::Rejection>{
+    let items = OrderItem::list_cooking(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&items),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// List the ready items waiting to be served ("expo hold"), grouped by table
+pub async fn ready_to_serve_handler(conn: PooledConnection, clock: Arc<dyn Clock>)-> Result<impl warp::Reply, warp::Rejection>{
+    let tables = OrderItem::list_ready_by_table(&conn, clock.now()).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&tables),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Portions served per minute over the trailing `window_minutes`, rounded to two decimals
+pub async fn throughput_handler(conn: PooledConnection, window_minutes: i64, clock: Arc<dyn Clock>)-> Result<impl warp::Reply, warp::Rejection>{
+    if !(1..=1440).contains(&window_minutes) {
+        return Err(ApiError::Validation("window_minutes must be between 1 and 1440".to_string()).into());
+    }
+    let served = OrderItem::served_in_window(&conn, clock.now(), window_minutes).map_err(ApiError::from)?;
+    let per_minute = (served as f64 / window_minutes as f64 * 100.0).round() / 100.0;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"window_minutes": window_minutes, "served": served, "per_minute": per_minute})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// List the items a kitchen station still has to prepare (pending or cooking)
+pub async fn station_queue_handler(conn: PooledConnection, station: String)-> Result<impl warp::Reply, warp::Rejection>{
+    let items = OrderItem::list_station_queue(&conn, &station).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&items),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// List the items of running orders that take between min and max minutes to cook
+pub async fn items_by_time_handler(conn: PooledConnection, min: i64, max: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    if min > max {
+        return Err(ApiError::Validation("min must not be greater than max".to_string()).into());
+    }
+    let items = OrderItem::list_by_cooking_time(&conn, min, max).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&items),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Move an item of a table's running order to another kitchen status (pending, cooking, ready, served)
+pub async fn update_item_status_handler(conn: PooledConnection, table_id: i64, menu_id: i64, data: ItemStatusUpdate)-> Result<impl warp::Reply, warp::Rejection>{
+    if !ITEM_STATUSES.contains(&data.status.as_str()) {
+        return Err(ApiError::Validation(format!("status must be one of {}", ITEM_STATUSES.join(", "))).into());
+    }
+    let item = OrderItem::set_status(&conn, table_id, menu_id, &data.status)
+        .and_then(|updated| if updated { OrderItem::get_item(&conn, table_id, menu_id) } else { Ok(None) })
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Item Found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&item),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Serve all ready items of a table's running order at once
+pub async fn serve_table_handler(conn: PooledConnection, table_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    if OrderResponse::get_existing_order_id(&conn, table_id).map_err(ApiError::from)?.is_none() {
+        return Err(ApiError::NotFound("No running order for this table".to_string()).into());
+    }
+    let served = OrderItem::serve_ready(&conn, table_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"table_id": table_id, "served": served})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Comp an item of a table's running order. It stays on the order for the kitchen but is left out of totals.
+/// Only managers can comp, a reason is required and the comp is recorded in the audit log
+pub async fn comp_item_handler(mut conn: PooledConnection, table_id: i64, menu_id: i64, data: CompItemRequest, context: RequestContext)-> Result<impl warp::Reply, warp::Rejection>{
+    if !context.is_manager() {
+        return Err(ApiError::Forbidden("Only a manager can comp items".to_string()).into());
+    }
+    let reason = data.reason.trim();
+    if reason.is_empty() {
+        return Err(ApiError::Validation("A reason is required to comp an item".to_string()).into());
+    }
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    let item = OrderItem::get_item(&tx, table_id, menu_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Item Found".to_string()))?;
+    let note = format!("{}: {}", item.menu_name, reason);
+    let item = OrderItem::comp(&tx, table_id, menu_id, reason)
+        .and_then(|_| AuditEntry::record(&tx, item.order_id, "comp", context.actor.as_deref(), Some(&note)))
+        .and_then(|_| OrderItem::get_item(&tx, table_id, menu_id))
+        .and_then(|item| tx.commit().map(|_| item))
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("No Item Found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&item),
+        warp::http::StatusCode::OK
+    ))
+}
+
+// Customer Handlers
+
+/// Create a new Customer
+pub async fn create_customer_handler(conn: PooledConnection, data: Customer) -> Result<impl warp::Reply, warp::Rejection> {
+    if data.name.trim().is_empty() {
+        return Err(ApiError::Validation("Customer name cannot be empty".to_string()).into());
+    }
+    let customer_id = Customer::create(&conn, &data).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({ "id": customer_id })),
+        warp::http::StatusCode::CREATED,
+    ))
+}
+
+/// List the paid Orders of a Customer, newest first
+pub async fn customer_history_handler(conn: PooledConnection, customer_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    if Customer::get(&conn, customer_id).map_err(ApiError::from)?.is_none() {
+        return Err(ApiError::NotFound("Customer not found".to_string()).into());
+    }
+    let orders = Customer::history(&conn, customer_id).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&orders),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Get the loyalty points balance of a Customer
+pub async fn customer_points_handler(conn: PooledConnection, customer_id: i64)-> Result<impl warp::Reply, warp::Rejection>{
+    let customer = Customer::get(&conn, customer_id)
+        .map_err(ApiError::from)?
+        .ok_or_else(|| ApiError::NotFound("Customer not found".to_string()))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"customer_id": customer.id, "points": customer.points})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// Enum Handlers
+
+/// The status values the API accepts, from the same lists used to validate them
+pub async fn enums_handler()-> Result<impl warp::Reply, warp::Rejection>{
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"order_status": ORDER_STATUSES, "item_status": ITEM_STATUSES})),
+        warp::http::StatusCode::OK
+    ))
+}
+
+// Backup Handlers
+
+/// Every row of every table as one JSON document, managers only
+pub async fn backup_handler(conn: PooledConnection, context: RequestContext)-> Result<impl warp::Reply, warp::Rejection>{
+    if !context.is_manager() {
+        return Err(ApiError::Forbidden("Only a manager can back up the data".to_string()).into());
+    }
+    let backup = Backup::create(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&backup),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+/// Wipe all data and load a document from backup_handler instead, managers only.
+/// Either the whole backup is loaded or the current data stays
+pub async fn restore_handler(mut conn: PooledConnection, backup: Backup, context: RequestContext)-> Result<impl warp::Reply, warp::Rejection>{
+    if !context.is_manager() {
+        return Err(ApiError::Forbidden("Only a manager can restore a backup".to_string()).into());
+    }
+    let tx = conn.transaction().map_err(ApiError::from)?;
+    // Dropping the transaction on error rolls back the wipe as well
+    let rows = backup.restore(&tx)?;
+    tx.commit().map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"rows": rows, "success": "Backup restored"})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// Health Handlers
+
+/// Ready when the database answers a query against the schema, SERVICE UNAVAILABLE otherwise
+pub async fn health_handler(conn: PooledConnection)-> Result<impl warp::Reply, warp::Rejection>{
+    match conn.query_row("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'orders'", [], |row| row.get::<_, i64>(0)) {
+        Ok(_) => {
+            Ok(warp::reply::with_status(
+                warp::reply::json(&json!({"status": "ok"})),
+                warp::http::StatusCode::OK,
+            ))
+        }
+        Err(_err) => {
+            tracing::error!("{}", _err);
+            Ok(warp::reply::with_status(
+                warp::reply::json(&json!({"status": "db_unreachable"})),
+                warp::http::StatusCode::SERVICE_UNAVAILABLE,
+            ))
+        }
+    }
+}
+
+/// Alive as long as the server answers, the database is not checked
+pub async fn live_handler()-> Result<impl warp::Reply, warp::Rejection>{
+    Ok(warp::reply::with_status(
+        warp::reply::json(&json!({"status": "ok"})),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// Debug Handlers
+
+/// The slowest SQL statements seen since startup, slowest first
+pub async fn slow_queries_handler()-> Result<impl warp::Reply, warp::Rejection>{
+    Ok(warp::reply::with_status(
+        warp::reply::json(&slow_queries::list()),
+        warp::http::StatusCode::OK
+    ))
+}
+
+// Setting Handlers
+
+/// List all settings that differ from their defaults
+pub async fn list_settings_handler(conn: PooledConnection)-> Result<impl warp::Reply, warp::Rejection>{
+    let settings = Setting::list(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&settings),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Change a single setting, managers only. Unknown keys and invalid values are rejected with BAD REQUEST
+pub async fn update_setting_handler(conn: PooledConnection, key: String, data: SettingUpdate, context: RequestContext)-> Result<impl warp::Reply, warp::Rejection>{
+    if !context.is_manager() {
+        return Err(ApiError::Forbidden("Only a manager can change settings".to_string()).into());
+    }
+    let value = match data.value {
+        serde_json::Value::String(value) => value,
+        other => other.to_string(),
+    };
+    Setting::validate(&key, &value).map_err(ApiError::Validation)?;
+    Setting::set(&conn, &key, &value).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&Setting { key, value }),
+        warp::http::StatusCode::OK,
+    ))
+}
+
+// Report Handlers
+
+/// End of day report for a date (YYYY-MM-DD): revenue, order count, items sold, top menus and items per station
+pub async fn eod_report_handler(conn: PooledConnection, date: String)-> Result<impl warp::Reply, warp::Rejection>{
+    check_date(&conn, &date)?;
+    let report = EodReport::for_date(&conn, &date).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&report),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Estimated covers (guests served) and average spend per cover of a day
+pub async fn covers_handler(conn: PooledConnection, date: String)-> Result<impl warp::Reply, warp::Rejection>{
+    check_date(&conn, &date)?;
+    let report = CoversReport::for_date(&conn, &date).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&report),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Average and median table turnover (order creation to payment) of a day
+pub async fn turnover_handler(conn: PooledConnection, date: String)-> Result<impl warp::Reply, warp::Rejection>{
+    check_date(&conn, &date)?;
+    let report = TurnoverReport::for_date(&conn, &date).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&report),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Average estimated against actual preparation minutes per menu, to tune cooking time estimates
+pub async fn prep_accuracy_handler(conn: PooledConnection)-> Result<impl warp::Reply, warp::Rejection>{
+    let report = PrepAccuracy::list(&conn).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&report),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Orders served and revenue per table between two dates (inclusive)
+pub async fn table_performance_handler(conn: PooledConnection, from: String, to: String)-> Result<impl warp::Reply, warp::Rejection>{
+    check_date(&conn, &from)?;
+    check_date(&conn, &to)?;
+    if from > to {
+        return Err(ApiError::Validation("from must not be after to".to_string()).into());
+    }
+    let report = TablePerformance::list(&conn, &from, &to).map_err(ApiError::from)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&report),
+        warp::http::StatusCode::OK
+    ))
+}
+
+/// Unit Tests
+#[cfg(test)]
+mod tests {
+    use warp::{Filter, Reply, hyper::Body};
+    use super::*;
+    use crate::clock::{system_clock, FixedClock};
+    use crate::cooking_time::{random_cooking_time, FixedCookingTime};
+    use crate::db::conn_for_request;
+    use crate::logging::{path_params, Directives};
+    use crate::routes::parse_body;
+    use crate::models::{OrderLine, MAX_PAGE_LIMIT};
+    use rusqlite::OpenFlags;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static NEXT_TEST_DB: AtomicUsize = AtomicUsize::new(0);
+
+    thread_local! {
+        // Name of the in-memory database used by the test running on this thread
+        static TEST_DB_URI: RefCell<String> = const { RefCell::new(String::new()) };
+    }
+
+    // Open a connection to the in-memory database of the current test
+    fn open_test_conn() -> PooledConnection {
+        let uri = TEST_DB_URI.with(|uri| uri.borrow().clone());
+        let mut conn = Connection::open_with_flags(uri, OpenFlags::default() | OpenFlags::SQLITE_OPEN_URI).expect("Failed to create test database");
+        conn.execute("PRAGMA foreign_keys = ON;", []).expect("Failed to enable foreign key support");
+        crate::db::enable_query_profiling(&mut conn);
+        conn.into()
+    }
+
+    // Set up the test database
+    // Every test gets its own named shared-cache database, so handlers can be handed extra connections to it
+    fn setup_test_db() -> PooledConnection {
+        println!("Initializing the test database...");
+        let uri = format!("file:test_db_{}?mode=memory&cache=shared", NEXT_TEST_DB.fetch_add(1, Ordering::SeqCst));
+        TEST_DB_URI.with(|current| *current.borrow_mut() = uri);
+        let conn = open_test_conn();
+        crate::db::create_schema(&conn).expect("Schema creation failed");
+        conn
+    }
+
+    // Another connection to the current test database. Handlers take ownership of their connection,
+    // so this lets a test call several handlers while keeping its own connection (and the database) alive
+    fn reconnect() -> PooledConnection {
+        open_test_conn()
+    }
+    /// Settings and other manager-only changes in tests are made as a manager
+    fn manager_context() -> RequestContext {
+        RequestContext { role: Some("manager".to_string()), ..Default::default() }
+    }
+    /// The response a client gets for a handler result, ApiError rejections included
+    async fn into_client_response<T: Reply>(result: Result<T, warp::Rejection>) -> warp::reply::Response {
+        match result {
+            Ok(reply) => reply.into_response(),
+            Err(rejection) => crate::routes::handle_rejection(rejection).await.unwrap().into_response(),
+        }
+    }
+
+    // Inserting static table and menu data
+    fn setup_static_data(conn: &Connection){
+        let values_to_insert = vec!["T-01", "T-02", "T-03"];
+
+        for value in values_to_insert {
+            conn.execute("INSERT INTO tables (code) VALUES (?1)", [value]).expect("Insertion Failed");
+        }
+        let values_to_insert = vec!["M-01", "M-02", "M-03", "M-04", "M-05"];
+
+        for value in values_to_insert {
+            conn.execute("INSERT INTO menus (name) VALUES (?1)", [value]).expect("Insertion Failed");
+        }
+
+    }
+
+    // Convert warp Response to serde Json Value
+    async fn convert_response_to_json(resp:  warp::http::Response<Body>)->serde_json::Value {
+        let body_bytes = warp::hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        let body_vec = body_bytes.to_vec();
+        let body_string = String::from_utf8_lossy(&body_vec);
+        let json_value: serde_json::Value = serde_json::from_str(&body_string).unwrap();
+        json_value
+    }
+
+    // Test Case: 01 Menu Creation
+    #[tokio::test]
+    async fn test_create_menu_handler() {
         let conn = setup_test_db();
         let menu = Menu {
             id: 0,
             name: "Menu-01".to_string(),
+            price: 9.5,
+            category: None,
+            station: None,
+            tax_category: None,
+            cost: None,
         };
         let result = create_menu_handler(conn, menu).await;
         match result {
@@ -486,42 +1821,40 @@ mod tests {
         let order = OrderRequestBody {
             table_id: 1,
             menu_ids: vec![1, 2],
+            items: None,
+            customer_id: None,
+            force: false,
+            force_unavailable: false,
+            party_size: None,
+            on_existing: OnExisting::Append,
         };
-        let result = create_order_handler(conn, order).await;
+        let result = create_order_handler(conn, order, RequestContext::default(), system_clock(), random_cooking_time()).await;
         // Will raise error, since table and menu not found
-        match result {
-            Ok(rep)=>{
-                let resp = rep.into_response();
-                assert_eq!(resp.status(), warp::http::StatusCode::INTERNAL_SERVER_ERROR);
-                let json_data = convert_response_to_json(resp).await;
-                assert_eq!(json_data["error"].as_str(), Some("Error creating order FOREIGN KEY constraint failed"));
-            }
-            Err(_)=>{
-                panic!("Unhandled Error");
-            }
-        }
+        let resp = into_client_response(result).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"], json!({"code": "not_found", "message": "Table 1 not found"}));
     }
     #[tokio::test]
     async fn test_create_order_handler_wrong_data2() {
-        let mut conn = setup_test_db();
-        setup_static_data(&mut conn);
+        let conn = setup_test_db();
+        setup_static_data(&conn);
         let order = OrderRequestBody {
             table_id: 1,
             menu_ids: vec![],
+            items: None,
+            customer_id: None,
+            force: false,
+            force_unavailable: false,
+            party_size: None,
+            on_existing: OnExisting::Append,
         };
-        let result = create_order_handler(conn, order).await;
+        let result = create_order_handler(conn, order, RequestContext::default(), system_clock(), random_cooking_time()).await;
         // Will fail, since menu_ids empty
-        match result {
-            Ok(rep)=>{
-                let resp = rep.into_response();
-                assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
-                let json_data = convert_response_to_json(resp).await;
-                assert_eq!(json_data["error"].as_str(), Some("Please Add Items"));
-            }
-            Err(_)=>{
-                panic!("Unhandled Error");
-            }
-        }
+        let resp = into_client_response(result).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"].as_str(), Some("Please Add Items"));
     }
 
     // Test Case: 04 Order creation with correct data
@@ -532,9 +1865,15 @@ mod tests {
         let order = OrderRequestBody {
             table_id: 1,
             menu_ids: vec![1, 2],
+            items: None,
+            customer_id: None,
+            force: false,
+            force_unavailable: false,
+            party_size: None,
+            on_existing: OnExisting::Append,
         };
 
-        let result = create_order_handler(conn, order).await;
+        let result = create_order_handler(conn, order, RequestContext::default(), system_clock(), random_cooking_time()).await;
         // Will create a new order for table_id 1 and menu 1, 2
         match result {
             Ok(rep)=>{
@@ -580,7 +1919,7 @@ mod tests {
 
         // Commit the transaction
         tx.commit().expect("Commit Failed");
-        let result = delete_order_item_handler(conn, 1, 2).await;
+        let result = delete_order_item_handler(conn, 1, 2, RemoveItemQuery::default(), RequestContext::default()).await;
         // Will remove menu 2 from the order, menu 1 will be still there
         match result {
             Ok(rep)=>{
@@ -621,7 +1960,7 @@ mod tests {
 
         // Commit the transaction
         tx.commit().expect("Commit Failed");
-        let result = delete_order_item_handler(conn, 1, 1).await;
+        let result = delete_order_item_handler(conn, 1, 1, RemoveItemQuery::default(), RequestContext::default()).await;
         // Will remove menu 1 from the order, and since no item i order, order will be deleted
         match result {
             Ok(rep)=>{
@@ -662,7 +2001,7 @@ mod tests {
 
         // Commit the transaction
         tx.commit().expect("Commit Failed");
-        let result = delete_order_item_handler(conn, 1, 1).await;
+        let result = delete_order_item_handler(conn, 1, 1, RemoveItemQuery::default(), RequestContext::default()).await;
         // Will update the quantity of menu 1
         match result {
             Ok(rep)=>{
@@ -723,7 +2062,7 @@ mod tests {
                     // If item not found raise NotFound
                     warp::http::StatusCode::NOT_FOUND=>{
                         let json_data = convert_response_to_json(resp).await;
-                        assert_eq!(json_data["error"].as_str(), Some("No Item Found"));
+                        assert_eq!(json_data["error"]["message"].as_str(), Some("No Item Found"));
                     },
                     _ => {}
                 }
@@ -734,4 +2073,3715 @@ mod tests {
         }
 
     }
-}
\ No newline at end of file
+
+    // Test Case: 09 Table performance report aggregates orders and revenue per table
+    #[tokio::test]
+    async fn test_table_performance_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 4.5 WHERE id = 1", []).expect("Price update failed");
+        conn.execute("UPDATE menus SET price = 10.0 WHERE id = 2", []).expect("Price update failed");
+
+        // Table 1: one order inside the window with 2 x M-01 and 1 x M-02
+        conn.execute("INSERT INTO orders (table_id, created_at) VALUES (1, '2024-03-10 12:00:00')", []).expect("Order Creation Failed");
+        let order_id = conn.last_insert_rowid();
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, 1, 10, 2)", [order_id]).expect("OrderItems creation failed");
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, 2, 7, 1)", [order_id]).expect("OrderItems creation failed");
+
+        // Table 2: one order inside the window with 3 x M-02
+        conn.execute("INSERT INTO orders (table_id, created_at) VALUES (2, '2024-03-11 19:30:00')", []).expect("Order Creation Failed");
+        let order_id = conn.last_insert_rowid();
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, 2, 7, 3)", [order_id]).expect("OrderItems creation failed");
+
+        // Table 3: order outside the window, must not be counted
+        conn.execute("INSERT INTO orders (table_id, created_at) VALUES (3, '2024-04-02 12:00:00')", []).expect("Order Creation Failed");
+        let order_id = conn.last_insert_rowid();
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, 2, 7, 1)", [order_id]).expect("OrderItems creation failed");
+
+        let result = table_performance_handler(conn, "2024-03-01".to_string(), "2024-03-31".to_string()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let report = json_data.as_array().expect("Report should be a list");
+                assert_eq!(report.len(), 3);
+                assert_eq!(report[0]["table_code"].as_str(), Some("T-01"));
+                assert_eq!(report[0]["orders_served"].as_i64(), Some(1));
+                assert_eq!(report[0]["revenue"].as_f64(), Some(19.0));
+                assert_eq!(report[1]["table_code"].as_str(), Some("T-02"));
+                assert_eq!(report[1]["orders_served"].as_i64(), Some(1));
+                assert_eq!(report[1]["revenue"].as_f64(), Some(30.0));
+                assert_eq!(report[2]["orders_served"].as_i64(), Some(0));
+                assert_eq!(report[2]["revenue"].as_f64(), Some(0.0));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 10 Menu list follows the configured default sort
+    #[tokio::test]
+    async fn test_list_menu_handler_default_sort(){
+        let conn = setup_test_db();
+        conn.execute("INSERT INTO menus (name, price, category) VALUES ('Soup', 6.0, 'starters')", []).expect("Insertion Failed");
+        conn.execute("INSERT INTO menus (name, price, category) VALUES ('Cola', 2.5, 'drinks')", []).expect("Insertion Failed");
+        conn.execute("INSERT INTO menus (name, price, category) VALUES ('Burger', 12.0, 'mains')", []).expect("Insertion Failed");
+        let names = |json_data: &serde_json::Value| -> Vec<String> {
+            json_data["items"].as_array().unwrap().iter().map(|menu| menu["name"].as_str().unwrap().to_string()).collect()
+        };
+
+        // Without a setting menus come back in id order
+        let resp = into_client_response(list_menu_handler(reconnect(), None, false, PageQuery::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(names(&convert_response_to_json(resp).await), vec!["Soup", "Cola", "Burger"]);
+
+        // Changing the setting changes the default ordering
+        let result = update_setting_handler(reconnect(), "default_menu_sort".to_string(), SettingUpdate { value: json!("price") }, manager_context()).await;
+        assert_eq!(into_client_response(result).await.status(), warp::http::StatusCode::OK);
+        let resp = into_client_response(list_menu_handler(reconnect(), None, false, PageQuery::default()).await).await;
+        assert_eq!(names(&convert_response_to_json(resp).await), vec!["Cola", "Soup", "Burger"]);
+
+        // An explicit sort still wins over the setting
+        let resp = into_client_response(list_menu_handler(reconnect(), Some("name".to_string()), false, PageQuery::default()).await).await;
+        assert_eq!(names(&convert_response_to_json(resp).await), vec!["Burger", "Cola", "Soup"]);
+
+        // Columns outside the whitelist are rejected, both as a setting and as a param
+        let result = update_setting_handler(reconnect(), "default_menu_sort".to_string(), SettingUpdate { value: json!("name; DROP TABLE menus") }, manager_context()).await;
+        assert_eq!(into_client_response(result).await.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(list_menu_handler(conn, Some("cost".to_string()), false, PageQuery::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 11 Voided order is kept in history but excluded from revenue
+    #[tokio::test]
+    async fn test_void_order_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 8.0 WHERE id = 1", []).expect("Price update failed");
+        conn.execute("INSERT INTO orders (table_id, created_at) VALUES (1, '2024-05-01 18:00:00')", []).expect("Order Creation Failed");
+        let order_id = conn.last_insert_rowid();
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, 1, 6, 2)", [order_id]).expect("OrderItems creation failed");
+
+        // A reason is mandatory
+        let result = void_order_handler(reconnect(), 1, VoidOrderRequest { reason: " ".to_string() }).await;
+        assert_eq!(into_client_response(result).await.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        let result = void_order_handler(reconnect(), 1, VoidOrderRequest { reason: "Wrong table keyed in".to_string() }).await;
+        assert_eq!(into_client_response(result).await.status(), warp::http::StatusCode::OK);
+
+        // Absent from revenue
+        let resp = into_client_response(table_performance_handler(reconnect(), "2024-05-01".to_string(), "2024-05-01".to_string()).await).await;
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data[0]["orders_served"].as_i64(), Some(0));
+        assert_eq!(json_data[0]["revenue"].as_f64(), Some(0.0));
+
+        // Present in history with its items and reason
+        let resp = into_client_response(list_order_handler(reconnect(), PageQuery::default()).await).await;
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["items"][0]["id"].as_i64(), Some(order_id));
+        assert_eq!(json_data["items"][0]["status"].as_str(), Some("void"));
+        assert_eq!(json_data["items"][0]["void_reason"].as_str(), Some("Wrong table keyed in"));
+        assert_eq!(json_data["items"][0]["menus"][0]["quantity"].as_i64(), Some(2));
+
+        // The table no longer has a running order, so voiding again is a 404
+        let result = void_order_handler(conn, 1, VoidOrderRequest { reason: "Again".to_string() }).await;
+        assert_eq!(into_client_response(result).await.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 12 On the pass lists cooking items with elapsed minutes
+    #[tokio::test]
+    async fn test_on_the_pass_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("INSERT INTO orders (table_id, created_at) VALUES (1, datetime('now'))", []).expect("Order Creation Failed");
+        let order_id = conn.last_insert_rowid();
+        // Fired 12 minutes ago
+        conn.execute(
+            "INSERT INTO order_items (order_id, menu_id, cooking_time, item_status, fired_at) VALUES (?1, 1, 15, 'cooking', datetime('now', '-12 minutes'))",
+            [order_id],
+        ).expect("OrderItems creation failed");
+        // Still waiting to be fired, must not show up
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time) VALUES (?1, 2, 7)", [order_id]).expect("OrderItems creation failed");
+
+        let result = on_the_pass_handler(conn).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let items = json_data.as_array().expect("Pass should be a list");
+                assert_eq!(items.len(), 1);
+                assert_eq!(items[0]["menu_name"].as_str(), Some("M-01"));
+                assert_eq!(items[0]["table_code"].as_str(), Some("T-01"));
+                assert_eq!(items[0]["elapsed_minutes"].as_i64(), Some(12));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 13 fired_at is set when an item starts cooking and kept on a redundant transition
+    #[tokio::test]
+    async fn test_update_item_status_handler_fired_at(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("INSERT INTO orders (table_id, created_at) VALUES (1, datetime('now'))", []).expect("Order Creation Failed");
+        let order_id = conn.last_insert_rowid();
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time) VALUES (?1, 1, 6)", [order_id]).expect("OrderItems creation failed");
+        let fired_at = |conn: &Connection| -> Option<String> {
+            conn.query_row("SELECT fired_at FROM order_items WHERE menu_id = 1", [], |row| row.get(0)).unwrap()
+        };
+        assert_eq!(fired_at(&conn), None);
+
+        // First transition stamps fired_at
+        let resp = into_client_response(update_item_status_handler(reconnect(), 1, 1, ItemStatusUpdate { status: "cooking".to_string() }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["item_status"].as_str(), Some("cooking"));
+        assert!(json_data["fired_at"].as_str().is_some());
+
+        // Pretend it was fired a while ago, firing it again must not move the timestamp
+        conn.execute("UPDATE order_items SET fired_at = '2024-01-01 10:00:00' WHERE menu_id = 1", []).expect("Backdating failed");
+        let resp = into_client_response(update_item_status_handler(reconnect(), 1, 1, ItemStatusUpdate { status: "cooking".to_string() }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(fired_at(&conn).as_deref(), Some("2024-01-01 10:00:00"));
+
+        // Moving on keeps it, resetting to pending clears it
+        update_item_status_handler(reconnect(), 1, 1, ItemStatusUpdate { status: "ready".to_string() }).await.unwrap();
+        assert_eq!(fired_at(&conn).as_deref(), Some("2024-01-01 10:00:00"));
+        update_item_status_handler(reconnect(), 1, 1, ItemStatusUpdate { status: "pending".to_string() }).await.unwrap();
+        assert_eq!(fired_at(&conn), None);
+
+        // Unknown statuses and items not on the table
+        let resp = into_client_response(update_item_status_handler(reconnect(), 1, 1, ItemStatusUpdate { status: "burnt".to_string() }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(update_item_status_handler(reconnect(), 1, 5, ItemStatusUpdate { status: "cooking".to_string() }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 14 Import creates new tables and menus and updates existing menus
+    #[tokio::test]
+    async fn test_import_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let data: ImportRequest = serde_json::from_value(json!({
+            "tables": [{"code": "T-01"}, {"code": "Patio-1"}, {"code": "Patio-2"}],
+            "menus": [
+                {"name": "M-01", "price": 7.25, "category": "mains"},
+                {"name": "Lemonade", "price": 3.0, "category": "drinks"}
+            ]
+        })).unwrap();
+
+        let result = import_handler(reconnect(), data).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["tables_created"].as_i64(), Some(2));
+                assert_eq!(json_data["tables_existing"].as_i64(), Some(1));
+                assert_eq!(json_data["menus_created"].as_i64(), Some(1));
+                assert_eq!(json_data["menus_updated"].as_i64(), Some(1));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let tables: i64 = conn.query_row("SELECT COUNT(*) FROM tables", [], |row| row.get(0)).unwrap();
+        assert_eq!(tables, 5);
+        let price: f64 = conn.query_row("SELECT price FROM menus WHERE name = 'M-01'", [], |row| row.get(0)).unwrap();
+        assert_eq!(price, 7.25);
+        let category: String = conn.query_row("SELECT category FROM menus WHERE name = 'Lemonade'", [], |row| row.get(0)).unwrap();
+        assert_eq!(category, "drinks");
+
+        // A bad entry rejects the whole payload
+        let data: ImportRequest = serde_json::from_value(json!({
+            "tables": [{"code": "Bar-1"}],
+            "menus": [{"name": " "}]
+        })).unwrap();
+        let resp = into_client_response(import_handler(reconnect(), data).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let tables: i64 = conn.query_row("SELECT COUNT(*) FROM tables", [], |row| row.get(0)).unwrap();
+        assert_eq!(tables, 5);
+    }
+
+    // Test Case: 15 Order numbers follow a per day sequence and are not reused after a delete
+    #[tokio::test]
+    async fn test_order_number_sequence(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_number = |order_id: i64| -> String {
+            conn.query_row("SELECT order_number FROM orders WHERE id = ?1", [order_id], |row| row.get(0)).unwrap()
+        };
+
+        let first = OrderResponse::create_at(&conn, 1, "2024-06-01 11:00:00").expect("Order Creation Failed");
+        let second = OrderResponse::create_at(&conn, 2, "2024-06-01 12:30:00").expect("Order Creation Failed");
+        assert_eq!(order_number(first), "#001");
+        assert_eq!(order_number(second), "#002");
+
+        // A deleted order does not free its number for the next one while a later number is in use
+        OrderResponse::delete(&conn, first).expect("Order deletion failed");
+        let third = OrderResponse::create_at(&conn, 1, "2024-06-01 13:00:00").expect("Order Creation Failed");
+        assert_eq!(order_number(third), "#003");
+
+        // A new day starts over, using the configured format
+        Setting::set(&conn, "order_number_prefix", "A-").unwrap();
+        Setting::set(&conn, "order_number_digits", "4").unwrap();
+        let next_day = OrderResponse::create_at(&conn, 3, "2024-06-02 09:15:00").expect("Order Creation Failed");
+        assert_eq!(order_number(next_day), "A-0001");
+    }
+
+    // Test Case: 16 Fetch an order by its order number on a given day
+    #[tokio::test]
+    async fn test_get_order_by_number_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        // Two different days both have a #001
+        let yesterday = OrderResponse::create_at(&conn, 1, "2024-06-01 20:00:00").expect("Order Creation Failed");
+        OrderResponse::void(&conn, yesterday, "Test").expect("Void failed");
+        let today = OrderResponse::create_at(&conn, 2, "2024-06-02 12:00:00").expect("Order Creation Failed");
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time) VALUES (?1, 3, 9)", [today]).expect("OrderItems creation failed");
+
+        let result = get_order_by_number_handler(reconnect(), "#001".to_string(), Some("2024-06-02".to_string()), system_clock()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["id"].as_i64(), Some(today));
+                assert_eq!(json_data["table_name"].as_str(), Some("T-02"));
+                assert_eq!(json_data["menus"][0]["menu_name"].as_str(), Some("M-03"));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        // Closed orders are still found, and the prefix is optional
+        let resp = into_client_response(get_order_by_number_handler(reconnect(), "001".to_string(), Some("2024-06-01".to_string()), system_clock()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["id"].as_i64(), Some(yesterday));
+        assert_eq!(json_data["status"].as_str(), Some("void"));
+
+        let resp = into_client_response(get_order_by_number_handler(conn, "#002".to_string(), Some("2024-06-02".to_string()), system_clock()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 17 Menu list keeps answering while orders are written concurrently
+    #[tokio::test]
+    async fn test_list_menu_handler_during_heavy_writes(){
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicBool;
+
+        // WAL and locking only matter for a real file
+        let path = std::env::temp_dir().join(format!("restaurant_stress_{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let conn = crate::db::open_db_conn(&path).expect("Failed to open test database");
+        crate::db::create_schema(&conn).expect("Schema creation failed");
+        setup_static_data(&conn);
+
+        // Keep creating and closing orders until the reader is done
+        let running = Arc::new(AtomicBool::new(true));
+        let writer = {
+            let running = running.clone();
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let mut conn = crate::db::open_db_conn(&path).expect("Failed to open test database");
+                let mut written = 0;
+                while running.load(Ordering::SeqCst) {
+                    let tx = conn.transaction().expect("Transaction Ceation Failed");
+                    let order_id = OrderResponse::create(&tx, 1).expect("Order Creation Failed");
+                    OrderItem::create(&tx, order_id, 1, 5).expect("OrderItems creation failed");
+                    OrderResponse::void(&tx, order_id, "stress").expect("Void failed");
+                    tx.commit().expect("Commit Failed");
+                    written += 1;
+                }
+                written
+            })
+        };
+
+        for _ in 0..200 {
+            let conn = crate::db::open_db_conn(&path).expect("Failed to open test database");
+            let resp = list_menu_handler(conn.into(), None, false, PageQuery::default()).await.unwrap().into_response();
+            assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        }
+        running.store(false, Ordering::SeqCst);
+        let written = writer.join().expect("Writer panicked");
+        assert!(written > 0);
+
+        drop(conn);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    // Test Case: 18 Tip suggestions for a known total
+    #[tokio::test]
+    async fn test_tip_suggestions_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 12.5 WHERE id = 1", []).expect("Price update failed");
+        conn.execute("UPDATE menus SET price = 7.99 WHERE id = 2", []).expect("Price update failed");
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, 1, 10, 2)", [order_id]).expect("OrderItems creation failed");
+        conn.execute("INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, 2, 5, 1)", [order_id]).expect("OrderItems creation failed");
+
+        // Total is 32.99
+        let result = tip_suggestions_handler(reconnect(), 1).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["total"].as_f64(), Some(32.99));
+                let suggestions = json_data["suggestions"].as_array().unwrap();
+                assert_eq!(suggestions.len(), 3);
+                assert_eq!(suggestions[0]["percent"].as_i64(), Some(15));
+                assert_eq!(suggestions[0]["tip"].as_f64(), Some(4.95));
+                assert_eq!(suggestions[1]["tip"].as_f64(), Some(5.94));
+                assert_eq!(suggestions[2]["tip"].as_f64(), Some(6.6));
+                assert_eq!(suggestions[2]["total_with_tip"].as_f64(), Some(39.59));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        // Configured percentages are used
+        Setting::set(&conn, "tip_percentages", "10").unwrap();
+        let resp = into_client_response(tip_suggestions_handler(reconnect(), 1).await).await;
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["suggestions"][0]["tip"].as_f64(), Some(3.3));
+
+        // No order on the table
+        let resp = into_client_response(tip_suggestions_handler(conn, 2).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 19 Attach a customer to an order and read it back
+    #[tokio::test]
+    async fn test_attach_customer_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let customer = Customer { id: 0, name: "Ada".to_string(), phone: Some("555-0101".to_string()) };
+        let resp = into_client_response(create_customer_handler(reconnect(), customer).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let customer_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+
+        // Set on create
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], customer_id: Some(customer_id), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        let order = OrderResponse::get(&conn, order_id).unwrap().unwrap();
+        assert_eq!(order.customer.as_ref().map(|customer| customer.name.as_str()), Some("Ada"));
+
+        // Set afterwards on a running order
+        let order = OrderRequestBody { table_id: 2, menu_ids: vec![2], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        assert!(OrderResponse::get(&conn, order_id).unwrap().unwrap().customer.is_none());
+        let result = attach_customer_handler(reconnect(), 2, AttachCustomerRequest { customer_id }).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["customer"]["phone"].as_str(), Some("555-0101"));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let resp = into_client_response(list_order_handler(reconnect(), PageQuery::default()).await).await;
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["items"][1]["customer"]["id"].as_i64(), Some(customer_id));
+
+        // Unknown customer and table without an order
+        let resp = into_client_response(attach_customer_handler(reconnect(), 2, AttachCustomerRequest { customer_id: 99 }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let resp = into_client_response(attach_customer_handler(conn, 3, AttachCustomerRequest { customer_id }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 20 Customer history lists paid orders newest first
+    #[tokio::test]
+    async fn test_customer_history_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 4.5 WHERE id = 1", []).expect("Price update failed");
+        conn.execute("UPDATE menus SET price = 12.0 WHERE id = 2", []).expect("Price update failed");
+        let customer_id = Customer::create(&conn, &Customer { id: 0, name: "Ada".to_string(), phone: None }).unwrap();
+
+        // First visit, paid earlier
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 1], customer_id: Some(customer_id), ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        let resp = into_client_response(pay_order_handler(reconnect(), 1, PayOrderQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await["total"].as_f64(), Some(9.0));
+        conn.execute("UPDATE orders SET paid_at = '2024-05-01 19:00:00' WHERE table_id = 1", []).expect("Backdating failed");
+
+        // Second visit on the same table, which is free again after paying
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], customer_id: Some(customer_id), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        pay_order_handler(reconnect(), 1, PayOrderQuery::default(), RequestContext::default()).await.unwrap();
+
+        // Running orders are not history yet
+        let order = OrderRequestBody { table_id: 2, menu_ids: vec![2], customer_id: Some(customer_id), ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+
+        let result = customer_history_handler(reconnect(), customer_id).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let orders = json_data.as_array().unwrap();
+                assert_eq!(orders.len(), 2);
+                assert_eq!(orders[0]["total"].as_f64(), Some(12.0));
+                assert_eq!(orders[1]["total"].as_f64(), Some(9.0));
+                assert_eq!(orders[1]["paid_at"].as_str(), Some("2024-05-01 19:00:00"));
+                assert!(orders[0]["created_at"].is_string());
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        let resp = into_client_response(customer_history_handler(conn, 99).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 21 Paying an order credits loyalty points to its customer
+    #[tokio::test]
+    async fn test_loyalty_points_on_payment(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 12.75 WHERE id = 1", []).expect("Price update failed");
+        let customer_id = Customer::create(&conn, &Customer { id: 0, name: "Ada".to_string(), phone: None }).unwrap();
+
+        // 2 x 12.75 = 25.50 earns 25 points
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 1], customer_id: Some(customer_id), ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        let resp = into_client_response(pay_order_handler(reconnect(), 1, PayOrderQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await["points_earned"].as_i64(), Some(25));
+
+        let result = customer_points_handler(reconnect(), customer_id).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["points"].as_i64(), Some(25));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        // Points add up with the configured rate, orders without a customer earn nothing
+        Setting::set(&conn, "loyalty_points_per_dollar", "2").unwrap();
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], customer_id: Some(customer_id), ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        pay_order_handler(reconnect(), 1, PayOrderQuery::default(), RequestContext::default()).await.unwrap();
+        let order = OrderRequestBody { table_id: 2, menu_ids: vec![1], ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        let resp = into_client_response(pay_order_handler(reconnect(), 2, PayOrderQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(convert_response_to_json(resp).await["points_earned"].as_i64(), Some(0));
+        assert_eq!(Customer::get(&conn, customer_id).unwrap().unwrap().points, 50);
+
+        let resp = into_client_response(customer_points_handler(conn, 99).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 22 Redeem loyalty points as a discount and reject over-redemption
+    #[tokio::test]
+    async fn test_redeem_points_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 10.0 WHERE id = 1", []).expect("Price update failed");
+        let customer_id = Customer::create(&conn, &Customer { id: 0, name: "Ada".to_string(), phone: None }).unwrap();
+        conn.execute("UPDATE customers SET points = 1500 WHERE id = ?1", [customer_id]).expect("Points update failed");
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 1], customer_id: Some(customer_id), ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+
+        // 300 points at 1 cent each take 3.00 off a 20.00 order
+        let result = redeem_points_handler(reconnect(), 1, RedeemPointsRequest { points: 300 }).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["discount"].as_f64(), Some(3.0));
+                assert_eq!(json_data["due"].as_f64(), Some(17.0));
+                assert_eq!(json_data["points_remaining"].as_i64(), Some(1200));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        // More points than the customer has
+        let resp = into_client_response(redeem_points_handler(reconnect(), 1, RedeemPointsRequest { points: 1201 }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        // Enough points but worth more than what is left to pay
+        Setting::set(&conn, "points_redemption_cents", "2").unwrap();
+        let resp = into_client_response(redeem_points_handler(reconnect(), 1, RedeemPointsRequest { points: 851 }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        // So many points that their worth does not fit in cents
+        let resp = into_client_response(redeem_points_handler(reconnect(), 1, RedeemPointsRequest { points: i64::MAX }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        assert_eq!(convert_response_to_json(resp).await["error"]["message"].as_str(), Some("Too many points to redeem"));
+
+        // Rejected redemptions change nothing and the discount is taken off the payment
+        assert_eq!(Customer::get(&conn, customer_id).unwrap().unwrap().points, 1200);
+        let resp = into_client_response(pay_order_handler(reconnect(), 1, PayOrderQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(convert_response_to_json(resp).await["total"].as_f64(), Some(17.0));
+
+        // Order without a customer
+        let order = OrderRequestBody { table_id: 2, menu_ids: vec![1], ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        let resp = into_client_response(redeem_points_handler(conn, 2, RedeemPointsRequest { points: 10 }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 23 Kitchen station queues only hold the items prepared at that station
+    #[tokio::test]
+    async fn test_station_queue_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET station = 'grill' WHERE id IN (1, 2)", []).expect("Station update failed");
+        conn.execute("UPDATE menus SET station = 'bar' WHERE id = 3", []).expect("Station update failed");
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 3, 4], ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        let order = OrderRequestBody { table_id: 2, menu_ids: vec![2, 3], ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        // Served items have left the station
+        conn.execute("UPDATE order_items SET item_status = 'served' WHERE menu_id = 3 AND order_id = 2", []).expect("Status update failed");
+
+        let result = station_queue_handler(reconnect(), "grill".to_string()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let menu_ids: Vec<i64> = json_data.as_array().unwrap().iter().map(|item| item["menu_id"].as_i64().unwrap()).collect();
+                assert_eq!(menu_ids, vec![1, 2]);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        let resp = station_queue_handler(reconnect(), "bar".to_string()).await.unwrap().into_response();
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data.as_array().unwrap().len(), 1);
+        assert_eq!(json_data[0]["menu_id"].as_i64(), Some(3));
+        assert_eq!(json_data[0]["table_code"].as_str(), Some("T-01"));
+
+        // Menus without a station go to the default one
+        let resp = station_queue_handler(conn, "default".to_string()).await.unwrap().into_response();
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data.as_array().unwrap().len(), 1);
+        assert_eq!(json_data[0]["menu_id"].as_i64(), Some(4));
+    }
+
+    // Test Case: 24 End of day report fills every section for a day of orders
+    #[tokio::test]
+    async fn test_eod_report_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 5.0, station = 'grill' WHERE id = 1", []).expect("Menu update failed");
+        conn.execute("UPDATE menus SET price = 2.5, station = 'bar' WHERE id = 2", []).expect("Menu update failed");
+        conn.execute("UPDATE menus SET price = 8.0 WHERE id = 3", []).expect("Menu update failed");
+        let order_1 = OrderResponse::create_at(&conn, 1, "2024-05-01 12:00:00").unwrap();
+        let order_2 = OrderResponse::create_at(&conn, 2, "2024-05-01 19:30:00").unwrap();
+        let voided = OrderResponse::create_at(&conn, 3, "2024-05-01 20:00:00").unwrap();
+        OrderResponse::void(&conn, voided, "Mistake").unwrap();
+        let next_day = OrderResponse::create_at(&conn, 3, "2024-05-02 12:00:00").unwrap();
+        for (order_id, menu_id, quantity) in [(order_1, 1, 2), (order_1, 2, 3), (order_2, 1, 1), (order_2, 3, 1), (voided, 3, 4), (next_day, 2, 5)] {
+            conn.execute(
+                "INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, ?2, 5, ?3)",
+                params![order_id, menu_id, quantity],
+            ).expect("OrderItems creation failed");
+        }
+        conn.execute("UPDATE orders SET discount_cents = 150 WHERE id = ?1", [order_2]).expect("Discount update failed");
+
+        let result = eod_report_handler(reconnect(), "2024-05-01".to_string()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                // 10.00 + 7.50 + 5.00 + 8.00 - 1.50
+                assert_eq!(json_data["revenue"].as_f64(), Some(29.0));
+                assert_eq!(json_data["discounts"].as_f64(), Some(1.5));
+                assert_eq!(json_data["order_count"].as_i64(), Some(2));
+                assert_eq!(json_data["void_count"].as_i64(), Some(1));
+                assert_eq!(json_data["items_sold"].as_i64(), Some(7));
+                assert_eq!(json_data["top_menus"][0]["menu_id"].as_i64(), Some(1));
+                assert_eq!(json_data["top_menus"][0]["quantity"].as_i64(), Some(3));
+                assert_eq!(json_data["top_menus"][0]["revenue"].as_f64(), Some(15.0));
+                assert_eq!(json_data["top_menus"].as_array().unwrap().len(), 3);
+                assert_eq!(json_data["stations"], json!([
+                    {"station": "bar", "items": 3},
+                    {"station": "default", "items": 1},
+                    {"station": "grill", "items": 3}
+                ]));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        let resp = into_client_response(eod_report_handler(conn, "2024-13-01".to_string()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 25 Creating the same table code in parallel returns one id and no errors
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_create_table_handler_race(){
+        use std::sync::Arc;
+        use tokio::sync::Barrier;
+
+        // Separate connections only race on a real file
+        let path = std::env::temp_dir().join(format!("restaurant_table_race_{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let conn = crate::db::open_db_conn(&path).expect("Failed to open test database");
+        crate::db::create_schema(&conn).expect("Schema creation failed");
+
+        const REQUESTS: usize = 4;
+        for round in 0..20 {
+            let code = format!("T-{:02}", round);
+            let barrier = Arc::new(Barrier::new(REQUESTS));
+            let mut requests = Vec::new();
+            for _ in 0..REQUESTS {
+                let conn = crate::db::open_db_conn(&path).expect("Failed to open test database");
+                let barrier = barrier.clone();
+                let table = Table { id: 0, code: code.clone() };
+                requests.push(tokio::spawn(async move {
+                    barrier.wait().await;
+                    create_table_handler(conn.into(), table).await.unwrap().into_response()
+                }));
+            }
+            let mut ids = Vec::new();
+            for request in requests {
+                let resp = request.await.expect("Request panicked");
+                assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+                ids.push(convert_response_to_json(resp).await["id"].as_i64().unwrap());
+            }
+            assert!(ids.iter().all(|id| *id == ids[0]), "Different ids for {}: {:?}", code, ids);
+        }
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM tables", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 20);
+
+        drop(conn);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    // Test Case: 26 Switching a category off blocks ordering its menus
+    #[tokio::test]
+    async fn test_set_category_availability_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET category = 'grill' WHERE id IN (1, 2)", []).expect("Category update failed");
+        conn.execute("UPDATE menus SET category = 'drinks' WHERE id = 3", []).expect("Category update failed");
+
+        let result = set_category_availability_handler(reconnect(), "grill".to_string(), false).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["updated"].as_i64(), Some(2));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        // Already off, nothing changes
+        let resp = into_client_response(set_category_availability_handler(reconnect(), "grill".to_string(), false).await).await;
+        assert_eq!(convert_response_to_json(resp).await["updated"].as_i64(), Some(0));
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![3, 1], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        assert_eq!(convert_response_to_json(resp).await["error"]["unavailable"], json!(["M-01"]));
+        assert!(OrderResponse::get_existing_order_id(&conn, 1).unwrap().is_none());
+
+        // Other categories can still be ordered
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![3], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+
+        // Back on
+        let resp = into_client_response(set_category_availability_handler(reconnect(), "grill".to_string(), true).await).await;
+        assert_eq!(convert_response_to_json(resp).await["updated"].as_i64(), Some(2));
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = into_client_response(create_order_handler(conn, order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+    }
+
+    // Test Case: 27 Rush multiplier scales the estimated wait and kitchen load
+    #[tokio::test]
+    async fn test_estimated_wait_with_rush_multiplier(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_1 = OrderResponse::create(&conn, 1).unwrap();
+        let order_2 = OrderResponse::create(&conn, 2).unwrap();
+        OrderItem::create(&conn, order_1, 1, 10).unwrap();
+        OrderItem::create(&conn, order_1, 2, 5).unwrap();
+        OrderItem::create(&conn, order_2, 3, 8).unwrap();
+        OrderItem::create(&conn, order_2, 4, 7).unwrap();
+        // Ready items no longer wait on the kitchen
+        conn.execute("UPDATE order_items SET item_status = 'ready' WHERE order_id = ?1 AND menu_id = 4", [order_2]).expect("Status update failed");
+
+        let resp = into_client_response(estimated_wait_handler(reconnect(), 1).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["estimated_wait_minutes"].as_i64(), Some(15));
+        assert_eq!(json_data["kitchen_load_minutes"].as_i64(), Some(23));
+
+        let resp = into_client_response(update_setting_handler(reconnect(), "rush_multiplier".to_string(), SettingUpdate { value: json!(1.5) }, manager_context()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let result = estimated_wait_handler(reconnect(), 1).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["estimated_wait_minutes"].as_i64(), Some(23));
+                assert_eq!(json_data["kitchen_load_minutes"].as_i64(), Some(35));
+                assert_eq!(json_data["rush_multiplier"].as_f64(), Some(1.5));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        // Stored cooking times are untouched
+        assert_eq!(OrderResponse::calculate_total_cooking_time(&conn, order_1).unwrap(), 15);
+
+        let resp = into_client_response(update_setting_handler(reconnect(), "rush_multiplier".to_string(), SettingUpdate { value: json!(0.5) }, manager_context()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(estimated_wait_handler(conn, 3).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 28 Every receipt reprint is recorded in the audit log
+    #[tokio::test]
+    async fn test_reprint_receipt_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 4.25 WHERE id = 1", []).expect("Price update failed");
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 1], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+
+        let result = reprint_receipt_handler(reconnect(), order_id, RequestContext { actor: Some("alice".to_string()), ..Default::default() }).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["lines"].as_array().unwrap().len(), 1);
+                assert_eq!(json_data["lines"][0]["quantity"].as_i64(), Some(2));
+                assert_eq!(json_data["total"].as_f64(), Some(8.5));
+                assert_eq!(json_data["reprint_count"].as_i64(), Some(1));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let resp = into_client_response(reprint_receipt_handler(reconnect(), order_id, RequestContext::default()).await).await;
+        assert_eq!(convert_response_to_json(resp).await["reprint_count"].as_i64(), Some(2));
+
+        let resp = into_client_response(order_audit_handler(reconnect(), order_id).await).await;
+        let json_data = convert_response_to_json(resp).await;
+        let entries = json_data.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry["action"] == "reprint" && entry["created_at"].is_string()));
+        assert_eq!(entries[0]["actor"].as_str(), Some("alice"));
+        assert!(entries[1]["actor"].is_null());
+
+        let resp = into_client_response(reprint_receipt_handler(conn, 99, RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 29 Adding the same menu to a table twice in a row is rejected unless forced
+    #[tokio::test]
+    async fn test_create_order_handler_debounce(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        Setting::set(&conn, "duplicate_add_window_seconds", "10").unwrap();
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+
+        // Rapid duplicate
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], ..Default::default() };
+        let result = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await;
+        let resp = into_client_response(result).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["duplicates"], json!(["M-02"]));
+        assert_eq!(OrderItem::get_item(&conn, 1, 2).unwrap().unwrap().quantity, 1);
+
+        // Forced duplicate
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], force: true, ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(OrderItem::get_item(&conn, 1, 2).unwrap().unwrap().quantity, 2);
+
+        // Other menus, other tables and adds outside the window go through
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![3], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let order = OrderRequestBody { table_id: 2, menu_ids: vec![1], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        conn.execute("UPDATE recent_adds SET added_at = datetime('now', '-11 seconds') WHERE table_id = 1", []).expect("Backdating failed");
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = into_client_response(create_order_handler(conn, order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+    }
+
+    // Test Case: 30 Floor dashboard labels open orders by age
+    #[tokio::test]
+    async fn test_floor_status_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let fresh = OrderResponse::create(&conn, 1).unwrap();
+        let waiting = OrderResponse::create(&conn, 2).unwrap();
+        let late = OrderResponse::create(&conn, 3).unwrap();
+        conn.execute("UPDATE orders SET created_at = datetime('now', '-20 minutes') WHERE id = ?1", [waiting]).expect("Backdating failed");
+        conn.execute("UPDATE orders SET created_at = datetime('now', '-45 minutes') WHERE id = ?1", [late]).expect("Backdating failed");
+
+        let result = floor_status_handler(reconnect(), system_clock()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let urgencies: Vec<(i64, &str)> = json_data.as_array().unwrap().iter()
+                    .map(|order| (order["order_id"].as_i64().unwrap(), order["urgency"].as_str().unwrap()))
+                    .collect();
+                assert_eq!(urgencies, vec![(late, "red"), (waiting, "amber"), (fresh, "green")]);
+                assert_eq!(json_data[0]["age_minutes"].as_i64(), Some(45));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        // Thresholds are configurable and closed orders leave the floor
+        Setting::set(&conn, "floor_amber_minutes", "30").unwrap();
+        Setting::set(&conn, "floor_red_minutes", "60").unwrap();
+        OrderResponse::pay(&conn, fresh).unwrap();
+        let resp = floor_status_handler(conn, system_clock()).await.unwrap().into_response();
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data.as_array().unwrap().len(), 2);
+        assert_eq!(json_data[0]["urgency"].as_str(), Some("amber"));
+        assert_eq!(json_data[1]["urgency"].as_str(), Some("green"));
+    }
+
+    // Test Case: 31 Comped item is left out of the total but stays in the kitchen queue
+    #[tokio::test]
+    async fn test_comp_item_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 6.0 WHERE id = 1", []).expect("Price update failed");
+        conn.execute("UPDATE menus SET price = 9.5 WHERE id = 2", []).expect("Price update failed");
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        let manager = RequestContext { actor: Some("mia".to_string()), role: Some("manager".to_string()), ..Default::default() };
+
+        // Managers only, with a reason
+        let waiter = RequestContext { actor: Some("sam".to_string()), role: Some("waiter".to_string()), ..Default::default() };
+        let request = CompItemRequest { reason: "Cold food".to_string() };
+        let resp = into_client_response(comp_item_handler(reconnect(), 1, 2, request, waiter).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+        let request = CompItemRequest { reason: " ".to_string() };
+        let resp = into_client_response(comp_item_handler(reconnect(), 1, 2, request, manager.clone()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        assert_eq!(OrderResponse::charge_cents(&conn, order_id).unwrap(), 1550);
+
+        let request = CompItemRequest { reason: "Cold food".to_string() };
+        let result = comp_item_handler(reconnect(), 1, 2, request, manager).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["comped"].as_bool(), Some(true));
+                assert_eq!(json_data["comp_reason"].as_str(), Some("Cold food"));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        assert_eq!(OrderResponse::charge_cents(&conn, order_id).unwrap(), 600);
+        let receipt = Receipt::for_order(&conn, order_id).unwrap().unwrap();
+        assert_eq!(receipt.lines.len(), 2);
+        assert_eq!(receipt.total, 6.0);
+        let queue = OrderItem::list_station_queue(&conn, "default").unwrap();
+        assert!(queue.iter().any(|item| item.menu_id == 2));
+        let audit = AuditEntry::list_for_order(&conn, order_id).unwrap();
+        assert_eq!(audit.len(), 1);
+        assert_eq!(audit[0].action, "comp");
+        assert_eq!(audit[0].actor.as_deref(), Some("mia"));
+
+        let request = CompItemRequest { reason: "Cold food".to_string() };
+        let resp = into_client_response(comp_item_handler(conn, 1, 5, request, RequestContext { role: Some("manager".to_string()), ..Default::default() }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 32 A handler running past the route timeout answers 504 and is aborted
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_route_timeout(){
+        use std::time::Duration;
+        use warp::Filter;
+
+        async fn slow_handler(delay: Duration) -> Result<impl warp::Reply, warp::Rejection> {
+            // Blocks the thread like a query waiting on a database lock
+            std::thread::sleep(delay);
+            Ok(warp::reply::json(&json!({"success": "done"})))
+        }
+        // These handlers do not use the database, a connection is only opened for its cancel handle
+        let cancel = || PooledConnection::from(Connection::open_in_memory().unwrap()).cancel_handle();
+        let route = warp::path!("slow" / u64)
+            .and_then(move |delay| crate::routes::with_timeout(Duration::from_millis(100), cancel(), slow_handler(Duration::from_millis(delay))))
+            .recover(crate::routes::handle_rejection);
+
+        let resp = warp::test::request().path("/slow/400").reply(&route).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::GATEWAY_TIMEOUT);
+        let json_data: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(json_data, json!({"error": {"code": "timeout", "message": "request timed out"}}));
+
+        let resp = warp::test::request().path("/slow/0").reply(&route).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+
+        // A timed out handler is aborted at its next await and does not get to finish its work
+        let finished = Arc::new(AtomicUsize::new(0));
+        let done = finished.clone();
+        let resp = crate::routes::with_timeout(Duration::from_millis(50), cancel(), async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            done.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, warp::Rejection>(warp::reply())
+        }).await;
+        let rejection = resp.err().unwrap();
+        assert_eq!(rejection.find::<ApiError>().unwrap().status(), warp::http::StatusCode::GATEWAY_TIMEOUT);
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(finished.load(Ordering::SeqCst), 0);
+
+        // A handler blocked in its queries runs on past the limit, but what it writes is rolled back
+        let conn = setup_test_db();
+        let writer = reconnect();
+        let cancel = writer.cancel_handle();
+        let resp = crate::routes::with_timeout(Duration::from_millis(50), cancel, async move {
+            let mut writer = writer;
+            let tx = writer.transaction().map_err(ApiError::from)?;
+            tx.execute("INSERT INTO tables (code) VALUES ('T-09')", []).map_err(ApiError::from)?;
+            std::thread::sleep(Duration::from_millis(200));
+            tx.commit().map_err(ApiError::from)?;
+            Ok::<_, warp::Rejection>(warp::reply())
+        }).await;
+        let rejection = resp.err().unwrap();
+        assert_eq!(rejection.find::<ApiError>().unwrap().status(), warp::http::StatusCode::GATEWAY_TIMEOUT);
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let tables: i64 = conn.query_row("SELECT COUNT(*) FROM tables", [], |row| row.get(0)).unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    // Test Case: 33 Menu of the day is stable for a date and rotates across dates
+    #[tokio::test]
+    async fn test_menu_of_the_day_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+
+        let resp = into_client_response(menu_of_the_day_handler(reconnect(), Some("2024-05-01".to_string()), system_clock()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let first_pick = convert_response_to_json(resp).await["menu"]["id"].as_i64().unwrap();
+        let result = menu_of_the_day_handler(reconnect(), Some("2024-05-01".to_string()), system_clock()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["menu"]["id"].as_i64(), Some(first_pick));
+                assert!(json_data["menu"]["price"].is_number());
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let resp = into_client_response(menu_of_the_day_handler(reconnect(), Some("2024-05-02".to_string()), system_clock()).await).await;
+        assert_ne!(convert_response_to_json(resp).await["menu"]["id"].as_i64(), Some(first_pick));
+
+        // A scheduled menu replaces the rotation for its date only
+        let resp = into_client_response(schedule_menu_of_the_day_handler(reconnect(), "2024-05-02".to_string(), MenuScheduleRequest { menu_id: first_pick }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let resp = into_client_response(menu_of_the_day_handler(reconnect(), Some("2024-05-02".to_string()), system_clock()).await).await;
+        assert_eq!(convert_response_to_json(resp).await["menu"]["id"].as_i64(), Some(first_pick));
+
+        let resp = into_client_response(schedule_menu_of_the_day_handler(reconnect(), "2024-05-02".to_string(), MenuScheduleRequest { menu_id: 99 }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let resp = into_client_response(menu_of_the_day_handler(conn, Some("May 1st".to_string()), system_clock()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 34 Prep accuracy compares estimated cooking time to fired to served minutes
+    #[tokio::test]
+    async fn test_prep_accuracy_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        OrderItem::create(&conn, order_id, 2, 8).unwrap();
+
+        // served_at is stamped when the item is served
+        OrderItem::set_status(&conn, 1, 1, "cooking").unwrap();
+        OrderItem::set_status(&conn, 1, 1, "served").unwrap();
+        assert!(OrderItem::get_item(&conn, 1, 1).unwrap().unwrap().served_at.is_some());
+        conn.execute(
+            "UPDATE order_items SET fired_at = '2024-05-01 18:00:00', served_at = '2024-05-01 18:13:30' WHERE menu_id = 1",
+            [],
+        ).expect("Backdating failed");
+        // Not served yet, left out
+        OrderItem::set_status(&conn, 1, 2, "cooking").unwrap();
+
+        let result = prep_accuracy_handler(conn).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data.as_array().unwrap().len(), 1);
+                assert_eq!(json_data[0]["menu_id"].as_i64(), Some(1));
+                assert_eq!(json_data[0]["avg_estimated_minutes"].as_f64(), Some(10.0));
+                assert_eq!(json_data[0]["avg_actual_minutes"].as_f64(), Some(13.5));
+                assert_eq!(json_data[0]["delta_minutes"].as_f64(), Some(3.5));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 35 Managers can force unavailable menus onto an order, which is audited
+    #[tokio::test]
+    async fn test_create_order_handler_force_unavailable(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET available = 0 WHERE id = 1", []).expect("Availability update failed");
+        let manager = RequestContext { actor: Some("dana".to_string()), role: Some("manager".to_string()), ..Default::default() };
+
+        // Rejected without the override
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, manager.clone(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+
+        // Only managers may override
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], force_unavailable: true, ..Default::default() };
+        let server = RequestContext { actor: Some("sam".to_string()), role: Some("server".to_string()), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, server, system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+        assert!(OrderResponse::get_existing_order_id(&conn, 1).unwrap().is_none());
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], force_unavailable: true, ..Default::default() };
+        let result = create_order_handler(reconnect(), order, manager, system_clock(), random_cooking_time()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+                let json_data = convert_response_to_json(resp).await;
+                let order_id = json_data["id"].as_i64().unwrap();
+                assert_eq!(OrderItem::list_order_items(&conn, 1).unwrap().len(), 2);
+                let entries = AuditEntry::list_for_order(&conn, order_id).unwrap();
+                assert_eq!(entries.len(), 1);
+                assert_eq!(entries[0].action, "force_unavailable");
+                assert_eq!(entries[0].actor.as_deref(), Some("dana"));
+                assert_eq!(entries[0].note.as_deref(), Some("M-01"));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 36 Ready items are listed per table with how long they have waited
+    #[tokio::test]
+    async fn test_ready_to_serve_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let first = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, first, 1, 10).unwrap();
+        OrderItem::create(&conn, first, 2, 10).unwrap();
+        let second = OrderResponse::create(&conn, 2).unwrap();
+        OrderItem::create(&conn, second, 3, 10).unwrap();
+        OrderItem::create(&conn, second, 4, 10).unwrap();
+
+        OrderItem::set_status(&conn, 1, 1, "ready").unwrap();
+        OrderItem::set_status(&conn, 1, 2, "ready").unwrap();
+        OrderItem::set_status(&conn, 2, 3, "ready").unwrap();
+        // Still cooking and already served items are not held
+        OrderItem::set_status(&conn, 2, 4, "served").unwrap();
+        conn.execute(
+            "UPDATE order_items SET ready_at = datetime('now', '-7 minutes') WHERE menu_id = 2",
+            [],
+        ).expect("Backdating failed");
+
+        let result = ready_to_serve_handler(conn, system_clock()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let tables = json_data.as_array().unwrap();
+                assert_eq!(tables.len(), 2);
+                assert_eq!(tables[0]["table_code"], "T-01");
+                let items = tables[0]["items"].as_array().unwrap();
+                // Longest waiting first
+                assert_eq!(items.len(), 2);
+                assert_eq!(items[0]["menu_id"].as_i64(), Some(2));
+                assert_eq!(items[0]["waiting_minutes"].as_i64(), Some(7));
+                assert_eq!(items[1]["waiting_minutes"].as_i64(), Some(0));
+                assert_eq!(tables[1]["table_code"], "T-02");
+                assert_eq!(tables[1]["items"].as_array().unwrap().len(), 1);
+                assert_eq!(tables[1]["items"][0]["menu_id"].as_i64(), Some(3));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 37 Order events carry the id of the request that created the order
+    #[tokio::test]
+    async fn test_order_event_request_id(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let mut receiver = events::subscribe();
+        let context = RequestContext { request_id: Some("req-order-37".to_string()), ..Default::default() };
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = create_order_handler(conn, order, context, system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let order_id = convert_response_to_json(resp).await["id"].as_i64();
+
+        // Other tests publish on the same channel
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(err) => panic!("{}", err),
+            };
+            if event.request_id.as_deref() == Some("req-order-37") {
+                assert_eq!(event.event, "order_created");
+                assert_eq!(Some(event.order_id), order_id);
+                assert_eq!(event.table_id, 1);
+                let sse_event = warp::sse::Event::default().event(event.event.clone()).json_data(&event).unwrap();
+                assert!(sse_event.to_string().contains("\"request_id\":\"req-order-37\""));
+                break;
+            }
+        }
+    }
+
+    // Test Case: 38 Covers count party sizes, one per order without one, and split the revenue per cover
+    #[tokio::test]
+    async fn test_covers_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 12.5 WHERE id = 1", []).expect("Price update failed");
+        conn.execute("UPDATE menus SET price = 4.0 WHERE id = 2", []).expect("Price update failed");
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 1, 2], party_size: Some(3), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        // No party size given, counted as one cover
+        let order = OrderRequestBody { table_id: 2, menu_ids: vec![2], ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        // Voided orders have no covers
+        let order = OrderRequestBody { table_id: 3, menu_ids: vec![1], party_size: Some(6), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        let void_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        OrderResponse::void(&conn, void_id, "walked out").unwrap();
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], party_size: Some(0), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        let today: String = conn.query_row("SELECT date('now')", [], |row| row.get(0)).unwrap();
+        let result = covers_handler(conn, today).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["order_count"].as_i64(), Some(2));
+                assert_eq!(json_data["covers"].as_i64(), Some(4));
+                assert_eq!(json_data["estimated_orders"].as_i64(), Some(1));
+                // 12.50 + 12.50 + 4.00 + 4.00 over 4 covers
+                assert_eq!(json_data["revenue"].as_f64(), Some(33.0));
+                assert_eq!(json_data["avg_spend_per_cover"].as_f64(), Some(8.25));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 39 Ready and served items cannot be removed unless a manager forces it
+    #[tokio::test]
+    async fn test_delete_order_item_handler_locked(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        OrderItem::create(&conn, order_id, 2, 10).unwrap();
+        OrderItem::create(&conn, order_id, 3, 10).unwrap();
+        OrderItem::create(&conn, order_id, 4, 10).unwrap();
+        OrderItem::set_status(&conn, 1, 2, "ready").unwrap();
+        OrderItem::set_status(&conn, 1, 3, "served").unwrap();
+
+        // Pending items can still be removed
+        let resp = into_client_response(delete_order_item_handler(reconnect(), 1, 1, RemoveItemQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+
+        let result = delete_order_item_handler(reconnect(), 1, 2, RemoveItemQuery::default(), RequestContext::default()).await;
+        let resp = into_client_response(result).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"].as_str(), Some("M-02 is already ready, it can no longer be changed"));
+        assert!(OrderItem::get_item(&conn, 1, 2).unwrap().is_some());
+        let resp = into_client_response(delete_order_item_handler(reconnect(), 1, 3, RemoveItemQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+
+        // Only managers can force it
+        let waiter = RequestContext { role: Some("waiter".to_string()), ..Default::default() };
+        let resp = into_client_response(delete_order_item_handler(reconnect(), 1, 2, RemoveItemQuery { force: true }, waiter).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+        let manager = RequestContext { actor: Some("mia".to_string()), role: Some("manager".to_string()), ..Default::default() };
+        let resp = into_client_response(delete_order_item_handler(reconnect(), 1, 2, RemoveItemQuery { force: true }, manager).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert!(OrderItem::get_item(&conn, 1, 2).unwrap().is_none());
+        let entries = AuditEntry::list_for_order(&conn, order_id).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "remove_locked_item");
+        assert_eq!(entries[0].note.as_deref(), Some("M-02: ready"));
+
+        // Only served items once the lock moves to served
+        Setting::set(&conn, "lock_items_from", "served").unwrap();
+        OrderItem::set_status(&conn, 1, 3, "ready").unwrap();
+        let resp = into_client_response(delete_order_item_handler(conn, 1, 3, RemoveItemQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+    }
+
+    // Test Case: 40 The pool reuses healthy connections and replaces broken or expired ones
+    #[tokio::test]
+    async fn test_pool_recycles_connections(){
+        use crate::db::DbPool;
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let path = std::env::temp_dir().join(format!("restaurant_pool_{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let pool = Arc::new(DbPool::new(&path, 2, Duration::from_secs(60)));
+        let has_scratch = |conn: &Connection| -> bool {
+            conn.query_row("SELECT COUNT(*) FROM temp.sqlite_master WHERE name = 'scratch'", [], |row| row.get::<_, i64>(0)).unwrap() > 0
+        };
+
+        let conn = pool.get().unwrap();
+        crate::db::create_schema(&conn).expect("Schema creation failed");
+        setup_static_data(&conn);
+        conn.execute_batch("CREATE TEMP TABLE scratch (x INTEGER)").unwrap();
+        drop(conn);
+        // Healthy connections come back from the pool
+        let conn = pool.get().unwrap();
+        assert!(has_scratch(&conn));
+
+        // Left inside a transaction, as a handler that forgot to commit would
+        conn.execute_batch("BEGIN; INSERT INTO tables (code) VALUES ('T-99');").unwrap();
+        drop(conn);
+        let result = list_table_handler(pool.get().unwrap(), PageQuery::default()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                // A fresh connection, and the stray insert was rolled back
+                assert_eq!(json_data["items"].as_array().unwrap().len(), 3);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let conn = pool.get().unwrap();
+        assert!(conn.is_autocommit());
+        assert!(!has_scratch(&conn));
+        drop(conn);
+
+        // Connections past their lifetime are not kept
+        let expiring = Arc::new(DbPool::new(&path, 2, Duration::ZERO));
+        let conn = expiring.get().unwrap();
+        conn.execute_batch("CREATE TEMP TABLE scratch (x INTEGER)").unwrap();
+        drop(conn);
+        assert!(!has_scratch(&expiring.get().unwrap()));
+
+        drop(pool);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    // Test Case: 41 Turnover averages the minutes from creation to payment of the orders paid that day
+    #[tokio::test]
+    async fn test_turnover_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        for (table_id, created_at, paid_at) in [
+            (1, "2024-05-01 18:00:00", "2024-05-01 18:30:00"),
+            (2, "2024-05-01 18:10:00", "2024-05-01 19:10:00"),
+            (3, "2024-05-01 19:00:00", "2024-05-01 20:30:00"),
+        ] {
+            let order_id = OrderResponse::create_at(&conn, table_id, created_at).unwrap();
+            OrderResponse::pay(&conn, order_id).unwrap();
+            conn.execute("UPDATE orders SET paid_at = ?2 WHERE id = ?1", params![order_id, paid_at]).expect("Backdating failed");
+        }
+        // Paid another day, and still open, both left out
+        let order_id = OrderResponse::create_at(&conn, 1, "2024-05-02 12:00:00").unwrap();
+        OrderResponse::pay(&conn, order_id).unwrap();
+        conn.execute("UPDATE orders SET paid_at = '2024-05-02 12:45:00' WHERE id = ?1", params![order_id]).expect("Backdating failed");
+        OrderResponse::create_at(&conn, 2, "2024-05-01 20:00:00").unwrap();
+
+        let result = turnover_handler(reconnect(), "2024-05-01".to_string()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["paid_orders"].as_i64(), Some(3));
+                // 30, 60 and 90 minutes
+                assert_eq!(json_data["avg_minutes"].as_f64(), Some(60.0));
+                assert_eq!(json_data["median_minutes"].as_f64(), Some(60.0));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let resp = turnover_handler(conn, "2024-05-02".to_string()).await.unwrap().into_response();
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["avg_minutes"].as_f64(), Some(45.0));
+    }
+
+    // Test Case: 42 Orders with nothing to charge are only paid with a comp reason
+    #[tokio::test]
+    async fn test_pay_order_handler_empty(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        // No items at all
+        OrderResponse::create(&conn, 1).unwrap();
+        let result = pay_order_handler(reconnect(), 1, PayOrderQuery::default(), RequestContext::default()).await;
+        let resp = into_client_response(result).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"].as_str(), Some("cannot pay an empty order"));
+        assert!(OrderResponse::get_existing_order_id(&conn, 1).unwrap().is_some());
+
+        // Every item comped
+        let order_id = OrderResponse::create(&conn, 2).unwrap();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        OrderItem::comp(&conn, 2, 1, "birthday").unwrap();
+        let blank = PayOrderQuery { comp_reason: Some("  ".to_string()) };
+        let resp = into_client_response(pay_order_handler(reconnect(), 2, blank, RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        let query = PayOrderQuery { comp_reason: Some("birthday dinner on the house".to_string()) };
+        let context = RequestContext { actor: Some("mia".to_string()), ..Default::default() };
+        let resp = into_client_response(pay_order_handler(reconnect(), 2, query, context).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await["total"].as_f64(), Some(0.0));
+        assert!(OrderResponse::get_existing_order_id(&conn, 2).unwrap().is_none());
+        let entries = AuditEntry::list_for_order(&conn, order_id).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].action, "comp_order");
+        assert_eq!(entries[0].actor.as_deref(), Some("mia"));
+        assert_eq!(entries[0].note.as_deref(), Some("birthday dinner on the house"));
+    }
+
+    // Test Case: 43 The enums endpoint lists exactly the statuses the API accepts
+    #[tokio::test]
+    async fn test_enums_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let result = enums_handler().await;
+        let json_data = match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                convert_response_to_json(resp).await
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        };
+        assert_eq!(json_data["order_status"], json!(["open", "preparing", "served", "paid", "void"]));
+        assert_eq!(json_data["item_status"], json!(["pending", "cooking", "ready", "served"]));
+
+        // Every listed item status is accepted, anything else is not
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        for status in json_data["item_status"].as_array().unwrap() {
+            let data = ItemStatusUpdate { status: status.as_str().unwrap().to_string() };
+            let resp = into_client_response(update_item_status_handler(reconnect(), 1, 1, data).await).await;
+            assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        }
+        let data = ItemStatusUpdate { status: "plated".to_string() };
+        let resp = into_client_response(update_item_status_handler(reconnect(), 1, 1, data).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        // Orders only ever take the listed statuses
+        OrderResponse::pay(&conn, order_id).unwrap();
+        let void_id = OrderResponse::create(&conn, 2).unwrap();
+        OrderResponse::void(&conn, void_id, "mistake").unwrap();
+        OrderResponse::create(&conn, 3).unwrap();
+        let statuses: Vec<String> = conn.prepare("SELECT DISTINCT status FROM orders ORDER BY status").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<Result<_, _>>().unwrap();
+        assert!(statuses.iter().all(|status| json_data["order_status"].as_array().unwrap().contains(&json!(status))));
+        assert_eq!(statuses.len(), 3);
+        // The listed order statuses are exactly the ones that parse, in lifecycle order
+        let parsed: Vec<OrderStatus> = ORDER_STATUSES.iter().map(|status| status.parse().unwrap()).collect();
+        assert_eq!(parsed, OrderStatus::ALL);
+    }
+
+    // Test Case: 44 Several operations are applied to a running order at once
+    #[tokio::test]
+    async fn test_modify_order_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        OrderItem::create(&conn, order_id, 2, 6).unwrap();
+
+        let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [
+            {"op": "add", "menu_id": 1},
+            {"op": "add", "menu_id": 3},
+            {"op": "set_quantity", "menu_id": 2, "quantity": 4},
+            {"op": "remove", "menu_id": 1},
+            {"op": "set_quantity", "menu_id": 4, "quantity": 2},
+        ]})).unwrap();
+        let result = modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["id"].as_i64(), Some(order_id));
+                let quantities: Vec<(i64, i64)> = json_data["items"].as_array().unwrap().iter()
+                    .map(|item| (item["menu_id"].as_i64().unwrap(), item["quantity"].as_i64().unwrap()))
+                    .collect();
+                assert_eq!(quantities, vec![(1, 1), (2, 4), (3, 1), (4, 2)]);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        assert_eq!(OrderItem::get_item(&conn, 1, 2).unwrap().unwrap().cooking_time, 24);
+
+        // Taking every item off deletes the order
+        let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [
+            {"op": "remove", "menu_id": 1},
+            {"op": "remove", "menu_id": 3},
+            {"op": "set_quantity", "menu_id": 2, "quantity": 1},
+            {"op": "remove", "menu_id": 2},
+            {"op": "set_quantity", "menu_id": 4, "quantity": 1},
+            {"op": "remove", "menu_id": 4},
+        ]})).unwrap();
+        let resp = modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await["items"], json!([]));
+        assert!(OrderResponse::get_existing_order_id(&conn, 1).unwrap().is_none());
+    }
+
+    // Test Case: 45 One invalid operation rolls back the whole modification
+    #[tokio::test]
+    async fn test_modify_order_handler_rollback(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+
+        let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [
+            {"op": "add", "menu_id": 4},
+            {"op": "set_quantity", "menu_id": 1, "quantity": 5},
+            {"op": "remove", "menu_id": 5},
+        ]})).unwrap();
+        let result = modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await;
+        let resp = into_client_response(result).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["operation"].as_i64(), Some(2));
+        assert_eq!(json_data["error"]["message"].as_str(), Some("Menu 5 is not on the order"));
+        let items = OrderItem::list_order_items(&conn, 1).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].quantity, 1);
+
+        // Ready items are locked, unknown menus and bad quantities are rejected
+        OrderItem::set_status(&conn, 1, 1, "ready").unwrap();
+        let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [{"op": "remove", "menu_id": 1}]})).unwrap();
+        let resp = into_client_response(modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [{"op": "add", "menu_id": 99}]})).unwrap();
+        let resp = into_client_response(modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [{"op": "set_quantity", "menu_id": 2, "quantity": 0}]})).unwrap();
+        let resp = into_client_response(modify_order_handler(conn, 1, data, RequestContext::default(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 46 Each line is taxed at the rate of its menu's tax category
+    #[tokio::test]
+    async fn test_tax_categories(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 12.5, tax_category = 'food' WHERE id = 1", []).expect("Menu update failed");
+        conn.execute("UPDATE menus SET price = 8.0, tax_category = 'alcohol' WHERE id = 2", []).expect("Menu update failed");
+        conn.execute("UPDATE menus SET price = 3.0 WHERE id = 3", []).expect("Menu update failed");
+        for (key, value) in [("tax_rate_food", json!(10)), ("tax_rate_alcohol", json!("20")), ("tax_rate_default", json!(5))] {
+            let resp = into_client_response(update_setting_handler(reconnect(), key.to_string(), SettingUpdate { value }, manager_context()).await).await;
+            assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        }
+        let resp = into_client_response(update_setting_handler(reconnect(), "tax_rate_food".to_string(), SettingUpdate { value: json!(120) }, manager_context()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        let item_id = OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        OrderItem::add_quantity_of_existing_order_item(&conn, item_id).unwrap();
+        OrderItem::create(&conn, order_id, 2, 10).unwrap();
+        OrderItem::create(&conn, order_id, 3, 10).unwrap();
+
+        let receipt = Receipt::for_order(&conn, order_id).unwrap().unwrap();
+        let taxes: Vec<(f64, f64)> = receipt.lines.iter().map(|line| (line.tax_rate, line.tax)).collect();
+        assert_eq!(taxes, vec![(10.0, 2.5), (20.0, 1.6), (5.0, 0.15)]);
+        assert_eq!(receipt.subtotal, 36.0);
+        assert_eq!(receipt.tax, 4.25);
+        assert_eq!(receipt.total, 40.25);
+
+        let result = pay_order_handler(conn, 1, PayOrderQuery::default(), RequestContext::default()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["total"].as_f64(), Some(40.25));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 47 Restoring a menu snapshot brings back the prices it captured
+    #[tokio::test]
+    async fn test_menu_snapshot_restore(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 10.0, category = 'mains' WHERE id = 1", []).expect("Menu update failed");
+        conn.execute("UPDATE menus SET price = 4.5 WHERE id = 2", []).expect("Menu update failed");
+
+        let resp = into_client_response(create_menu_snapshot_handler(reconnect(), MenuSnapshotRequest { name: "summer".to_string() }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        assert_eq!(convert_response_to_json(resp).await["menus"].as_i64(), Some(5));
+        let resp = into_client_response(create_menu_snapshot_handler(reconnect(), MenuSnapshotRequest { name: "summer".to_string() }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+
+        // Winter prices, and a winter only dish
+        conn.execute("UPDATE menus SET price = 12.0, category = 'winter mains' WHERE id = 1", []).expect("Menu update failed");
+        conn.execute("UPDATE menus SET price = 5.0, available = 0 WHERE id = 2", []).expect("Menu update failed");
+        conn.execute("INSERT INTO menus (name, price) VALUES ('Stew', 9.0)", []).expect("Insertion Failed");
+
+        let result = restore_menu_snapshot_handler(reconnect(), "summer".to_string()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["restored"].as_i64(), Some(5));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let first = Menu::get(&conn, 1).unwrap().unwrap();
+        assert_eq!((first.price, first.category.as_deref()), (10.0, Some("mains")));
+        let second = Menu::get(&conn, 2).unwrap().unwrap();
+        assert_eq!((second.price, second.available), (4.5, true));
+        assert!(!Menu::get(&conn, 6).unwrap().unwrap().available);
+
+        let resp = into_client_response(restore_menu_snapshot_handler(conn, "autumn".to_string()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 48 ?__fail=db forces the 500 path only while debug endpoints are on
+    #[tokio::test]
+    async fn test_fail_db_debug_param(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+
+        let resp = into_client_response(list_table_handler(conn_for_request(true, "__fail=db", reconnect), PageQuery::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+
+        let resp = into_client_response(list_table_handler(conn_for_request(true, "sort=name", reconnect), PageQuery::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+
+        let result = list_table_handler(conn_for_request(false, "__fail=db", || conn), PageQuery::default()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["items"].as_array().unwrap().len(), 3);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 49 Merging a duplicate menu combines its quantities into the kept menu
+    #[tokio::test]
+    async fn test_merge_menus(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("INSERT INTO orders (table_id) VALUES (1)", []).expect("Insertion Failed");
+        let order_id = conn.last_insert_rowid();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        OrderItem::create(&conn, order_id, 2, 5).unwrap();
+        OrderItem::create(&conn, order_id, 2, 5).unwrap();
+
+        let result = merge_menus_handler(reconnect(), 1, 2).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["id"].as_i64(), Some(1));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let items: Vec<(i64, i64)> = conn
+            .prepare("SELECT menu_id, quantity FROM order_items WHERE order_id = ?1").unwrap()
+            .query_map([order_id], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(items, vec![(1, 3)]);
+        assert!(Menu::get(&conn, 2).unwrap().is_none());
+
+        let resp = into_client_response(merge_menus_handler(conn, 1, 2).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 50 Publishing more order events than the channel holds, with nobody listening, never fails an order
+    #[tokio::test]
+    async fn test_order_events_without_subscribers(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        for table_id in (1..=3).cycle().take(250) {
+            let order = OrderRequestBody { table_id, menu_ids: vec![1], ..Default::default() };
+            let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+            assert!(resp.status().is_success(), "order failed with {}", resp.status());
+        }
+        let count: i64 = conn.query_row("SELECT SUM(quantity) FROM order_items", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 250);
+    }
+
+    // Test Case: 51 Items of running orders are filtered by cooking time, paid orders are left out
+    #[tokio::test]
+    async fn test_items_by_time_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("INSERT INTO orders (table_id) VALUES (1)", []).expect("Insertion Failed");
+        let open_order = conn.last_insert_rowid();
+        conn.execute("INSERT INTO orders (table_id, status) VALUES (2, 'paid')", []).expect("Insertion Failed");
+        let paid_order = conn.last_insert_rowid();
+        OrderItem::create(&conn, open_order, 1, 5).unwrap();
+        OrderItem::create(&conn, open_order, 2, 10).unwrap();
+        OrderItem::create(&conn, open_order, 3, 15).unwrap();
+        OrderItem::create(&conn, open_order, 4, 20).unwrap();
+        OrderItem::create(&conn, paid_order, 5, 12).unwrap();
+
+        let result = items_by_time_handler(reconnect(), 10, 15).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let menu_ids: Vec<i64> = json_data.as_array().unwrap().iter().map(|item| item["menu_id"].as_i64().unwrap()).collect();
+                assert_eq!(menu_ids, vec![2, 3]);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        let resp = into_client_response(items_by_time_handler(conn, 15, 10).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 52 Tables created without a code are numbered with the configured prefix
+    #[tokio::test]
+    async fn test_create_next_table(){
+        let conn = setup_test_db();
+
+        for expected in ["T-01", "T-02", "T-03"] {
+            let result = create_next_table_handler(reconnect()).await;
+            match result {
+                Ok(rep)=>{
+                    let resp = rep.into_response();
+                    assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+                    let json_data = convert_response_to_json(resp).await;
+                    assert_eq!(json_data["code"].as_str(), Some(expected));
+                }
+                Err(_)=>{
+                    panic!("Unhandled Error");
+                }
+            }
+        }
+
+        // A body without a code is auto-numbered too, following the configured format
+        Setting::set(&conn, "table_code_prefix", "P").unwrap();
+        Setting::set(&conn, "table_code_digits", "3").unwrap();
+        let table: Table = serde_json::from_value(json!({})).unwrap();
+        let resp = create_table_handler(reconnect(), table).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let codes: Vec<String> = Table::list(&conn, MAX_PAGE_LIMIT, 0).unwrap().into_iter().map(|table| table.code).collect();
+        assert_eq!(codes, vec!["T-01", "T-02", "T-03", "P001"]);
+    }
+
+    // Test Case: 53 The order diff lists the item changes made after the version the client holds
+    #[tokio::test]
+    async fn test_order_diff_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        let seen = OrderResponse::get(&conn, order_id).unwrap().unwrap().version;
+
+        let operations = vec![
+            OrderOperation::Add { menu_id: 3 },
+            OrderOperation::Remove { menu_id: 1 },
+        ];
+        let resp = modify_order_handler(reconnect(), 1, ModifyOrderRequest { operations }, RequestContext::default(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        OrderItem::set_status(&conn, 1, 2, "cooking").unwrap();
+
+        let result = order_diff_handler(reconnect(), 1, seen).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["since"].as_i64(), Some(seen));
+                assert_eq!(json_data["version"].as_i64(), Some(seen + 3));
+                assert_eq!(json_data["added"][0]["menu_id"].as_i64(), Some(3));
+                assert_eq!(json_data["removed"][0]["menu_id"].as_i64(), Some(1));
+                assert_eq!(json_data["changed"][0]["menu_id"].as_i64(), Some(2));
+                assert_eq!(json_data["changed"][0]["item_status"].as_str(), Some("cooking"));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        let resp = into_client_response(order_diff_handler(reconnect(), 1, seen + 4).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(order_diff_handler(conn, 2, 0).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 54 A menu sent many times in one request is written once with the summed quantity
+    #[tokio::test]
+    async fn test_create_order_collapses_repeated_menu_ids(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let started = std::time::Instant::now();
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1; 500], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        // The running order path adds to the existing row
+        let order = OrderRequestBody { table_id: 1, menu_ids: [vec![1; 500], vec![2; 500]].concat(), force: true, ..Default::default() };
+        let result = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(2), "took {:?}", started.elapsed());
+
+        let items: Vec<(i64, i64)> = conn
+            .prepare("SELECT menu_id, quantity FROM order_items ORDER BY id").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(items, vec![(1, 1000), (2, 500)]);
+    }
+
+    // Test Case: 55 Statements slower than a millisecond are kept with their timings
+    #[tokio::test]
+    async fn test_slow_queries_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let count: i64 = conn
+            .query_row("WITH RECURSIVE n(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM n WHERE x < 500000) SELECT COUNT(*) FROM n", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 500000);
+
+        let result = slow_queries_handler().await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let queries = json_data.as_array().unwrap();
+                assert!(queries.iter().any(|query| query["sql"].as_str().unwrap().contains("WITH RECURSIVE n(x)")));
+                assert!(queries.iter().all(|query| query["duration_ms"].as_f64().unwrap() > 0.0));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 56 Orders are refused from last orders until closing time
+    #[tokio::test]
+    async fn test_last_orders_cutoff(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        Setting::set(&conn, "closing_time", "22:00").unwrap();
+        Setting::set(&conn, "last_order_minutes", "30").unwrap();
+        let at = |time: &str| -> Arc<dyn Clock> {
+            let timestamp: i64 = conn.query_row("SELECT CAST(strftime('%s', ?1) AS INTEGER)", [time], |row| row.get(0)).unwrap();
+            Arc::new(FixedClock(timestamp))
+        };
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), at("2024-05-01 21:29:59"), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+
+        let order = OrderRequestBody { table_id: 2, menu_ids: vec![1], ..Default::default() };
+        let result = create_order_handler(reconnect(), order, RequestContext::default(), at("2024-05-01 21:30:00"), random_cooking_time()).await;
+        let resp = into_client_response(result).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"].as_str(), Some("last orders have passed"));
+
+        let resp = into_client_response(update_setting_handler(reconnect(), "closing_time".to_string(), SettingUpdate { value: json!("25:00") }, manager_context()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM orders", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 1);
+    }
+
+    // Test Case: 57 With a fixed clock order ages on the floor dashboard are exact
+    #[tokio::test]
+    async fn test_floor_status_with_fixed_clock(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        conn.execute("UPDATE orders SET created_at = '2024-05-01 12:00:00' WHERE id = ?1", [order_id]).expect("Backdating failed");
+        let at = |time: &str| -> Arc<dyn Clock> {
+            let timestamp: i64 = conn.query_row("SELECT CAST(strftime('%s', ?1) AS INTEGER)", [time], |row| row.get(0)).unwrap();
+            Arc::new(FixedClock(timestamp))
+        };
+
+        for (time, age, urgency) in [("2024-05-01 12:14:59", 14, "green"), ("2024-05-01 12:15:00", 15, "amber"), ("2024-05-01 12:30:00", 30, "red")] {
+            let result = floor_status_handler(reconnect(), at(time)).await;
+            match result {
+                Ok(rep)=>{
+                    let resp = rep.into_response();
+                    assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                    let json_data = convert_response_to_json(resp).await;
+                    assert_eq!(json_data[0]["age_minutes"].as_i64(), Some(age));
+                    assert_eq!(json_data[0]["urgency"].as_str(), Some(urgency));
+                }
+                Err(_)=>{
+                    panic!("Unhandled Error");
+                }
+            }
+        }
+    }
+
+    // Test Case: 58 ApiError rejections become {"error": {"code", "message"}} with their status
+    #[tokio::test]
+    async fn test_api_error_responses(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+
+        let resp = into_client_response(items_by_time_handler(conn_for_request(true, "__fail=db", reconnect), 5, 15).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["code"].as_str(), Some("database"));
+        assert_eq!(json_data["error"]["message"].as_str(), Some("Something Wrong!"));
+
+        let result = items_by_time_handler(conn, 15, 5).await;
+        match result {
+            Ok(_)=>{
+                panic!("min above max was accepted");
+            }
+            Err(rejection)=>{
+                assert!(matches!(rejection.find::<ApiError>(), Some(ApiError::Validation(_))));
+                let resp = crate::routes::handle_rejection(rejection).await.unwrap().into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["error"]["code"].as_str(), Some("validation"));
+                assert_eq!(json_data["error"]["message"].as_str(), Some("min must not be greater than max"));
+            }
+        }
+    }
+
+    // Test Case: 59 A manager can raise the prices of one category by a percentage
+    #[tokio::test]
+    async fn test_adjust_prices_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 9.99, category = 'mains' WHERE id = 1", []).expect("Menu update failed");
+        conn.execute("UPDATE menus SET price = 12.5, category = 'mains' WHERE id = 2", []).expect("Menu update failed");
+        conn.execute("UPDATE menus SET price = 4.0, category = 'drinks' WHERE id = 3", []).expect("Menu update failed");
+        let manager = RequestContext { role: Some("manager".to_string()), ..Default::default() };
+
+        let adjustment = PriceAdjustment { percent: 10.0, category: Some("mains".to_string()) };
+        let resp = into_client_response(adjust_prices_handler(reconnect(), adjustment, RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+
+        let adjustment = PriceAdjustment { percent: 10.0, category: Some("mains".to_string()) };
+        let result = adjust_prices_handler(reconnect(), adjustment, manager.clone()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["updated"].as_i64(), Some(2));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        // 9.99 * 1.1 = 10.989 rounds to the cent, drinks are untouched
+        assert_eq!(Menu::get(&conn, 1).unwrap().unwrap().price, 10.99);
+        assert_eq!(Menu::get(&conn, 2).unwrap().unwrap().price, 13.75);
+        assert_eq!(Menu::get(&conn, 3).unwrap().unwrap().price, 4.0);
+
+        let adjustment = PriceAdjustment { percent: -100.0, category: None };
+        let resp = into_client_response(adjust_prices_handler(conn, adjustment, manager).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 60 The order total sums quantity * price before tax, menus without a price count as 0
+    #[tokio::test]
+    async fn test_get_order_total_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 12.5 WHERE id = 1", []).expect("Price update failed");
+        conn.execute("UPDATE menus SET price = 4.25 WHERE id = 2", []).expect("Price update failed");
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 1, 2, 3], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+
+        let result = get_order_total_handler(reconnect(), 1).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["total"].as_f64(), Some(29.25));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+
+        // Tax is charged on the bill but is not part of the total
+        Setting::set(&conn, "tax_rate_default", "10").unwrap();
+        let resp = get_order_total_handler(reconnect(), 1).await.unwrap().into_response();
+        assert_eq!(convert_response_to_json(resp).await["total"].as_f64(), Some(29.25));
+        let order_id = OrderResponse::get_existing_order_id(&conn, 1).unwrap().unwrap();
+        assert_eq!(OrderResponse::charge_cents(&conn, order_id).unwrap(), 3218);
+
+        let resp = into_client_response(get_order_total_handler(conn, 2).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 61 With service included the receipt carries the service charge instead of tip suggestions
+    #[tokio::test]
+    async fn test_receipt_service_included(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 20.0 WHERE id = 1", []).expect("Price update failed");
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 1], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+
+        let resp = reprint_receipt_handler(reconnect(), order_id, RequestContext::default()).await.unwrap().into_response();
+        let json_data = convert_response_to_json(resp).await;
+        assert!(json_data.get("service_charge").is_none());
+        assert_eq!(json_data["total"].as_f64(), Some(40.0));
+        assert_eq!(json_data["tip_suggestions"].as_array().unwrap().len(), 3);
+
+        let resp = update_setting_handler(reconnect(), "service_included".to_string(), SettingUpdate { value: json!(true) }, manager_context()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        Setting::set(&conn, "service_charge_percent", "12.5").unwrap();
+
+        let result = reprint_receipt_handler(reconnect(), order_id, RequestContext::default()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["service_charge"].as_f64(), Some(5.0));
+                assert_eq!(json_data["total"].as_f64(), Some(45.0));
+                assert!(json_data.get("tip_suggestions").is_none());
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        // What is charged matches the receipt, and no tips are suggested
+        assert_eq!(OrderResponse::charge_cents(&conn, order_id).unwrap(), 4500);
+        let resp = tip_suggestions_handler(conn, 1).await.unwrap().into_response();
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["suggestions"].as_array().unwrap().len(), 0);
+    }
+
+    // Test Case: 62 An order only moves forward through its statuses and a paid table starts a fresh order
+    #[tokio::test]
+    async fn test_update_order_status_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+
+        let status = |status: &str| OrderStatusUpdate { status: status.to_string() };
+        let resp = update_order_status_handler(reconnect(), 1, status("preparing")).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(OrderResponse::status(&conn, order_id).unwrap(), OrderStatus::Preparing);
+
+        // No backward jumps, no unknown statuses and no voiding through here
+        for bad in ["open", "preparing", "void", "eaten"] {
+            let resp = into_client_response(update_order_status_handler(reconnect(), 1, status(bad)).await).await;
+            assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+            assert_eq!(convert_response_to_json(resp).await["error"]["code"], "validation");
+        }
+        let resp = update_order_status_handler(reconnect(), 1, status("served")).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let resp = update_order_status_handler(reconnect(), 1, status("paid")).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(OrderResponse::status(&conn, order_id).unwrap(), OrderStatus::Paid);
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 1).unwrap(), None);
+
+        let resp = into_client_response(update_order_status_handler(reconnect(), 1, status("served")).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], ..Default::default() };
+        let resp = create_order_handler(conn, order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_ne!(convert_response_to_json(resp).await["id"].as_i64().unwrap(), order_id);
+    }
+
+    // Test Case: 63 An order line with a quantity is written as a single row with that quantity
+    #[tokio::test]
+    async fn test_create_order_with_item_quantities(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let items = vec![OrderLine { menu_id: 1, quantity: 5, notes: None }, OrderLine { menu_id: 2, quantity: 1, notes: None }];
+        let order = OrderRequestBody { table_id: 1, items: Some(items), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+
+        // A zero quantity is refused, the old menu_ids form still adds one per id
+        let order = OrderRequestBody { table_id: 1, items: Some(vec![OrderLine { menu_id: 3, quantity: 0, notes: None }]), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let order: OrderRequestBody = serde_json::from_value(json!({"table_id": 1, "menu_ids": [2, 3], "force": true})).unwrap();
+        let result = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let items: Vec<(i64, i64)> = conn
+            .prepare("SELECT menu_id, quantity FROM order_items ORDER BY id").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(items, vec![(1, 5), (2, 2), (3, 1)]);
+    }
+
+    // Test Case: 64 The kitchen ticket wraps at 40 characters and indents item notes
+    #[tokio::test]
+    async fn test_order_ticket_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET name = 'Slow roasted pork belly with crackling and apple sauce' WHERE id = 2", []).expect("Menu update failed");
+        let items = vec![
+            OrderLine { menu_id: 1, quantity: 3, notes: Some("no onions".to_string()) },
+            OrderLine { menu_id: 2, quantity: 1, notes: Some("sauce on the side, extra crispy crackling for the birthday guest".to_string()) },
+        ];
+        let order = OrderRequestBody { table_id: 1, items: Some(items), ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+
+        let resp = into_client_response(order_ticket_handler(reconnect(), order_id + 1).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let result = order_ticket_handler(conn, order_id).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                assert!(resp.headers()["content-type"].to_str().unwrap().starts_with("text/plain"));
+                let body = String::from_utf8(warp::hyper::body::to_bytes(resp.into_body()).await.unwrap().to_vec()).unwrap();
+                let lines: Vec<&str> = body.lines().collect();
+                assert!(lines.iter().all(|line| line.chars().count() <= 40), "{}", body);
+                assert_eq!(lines[1], "Table T-01");
+                let first = lines.iter().position(|line| *line == "3 x M-01").unwrap();
+                assert_eq!(&lines[first + 1..first + 7], &[
+                    "    no onions",
+                    "1 x Slow roasted pork belly with",
+                    "crackling and apple sauce",
+                    "    sauce on the side, extra crispy",
+                    "    crackling for the birthday guest",
+                    &"-".repeat(40),
+                ]);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 65 A table with a running order gets the items appended, the request rejected or the items replaced
+    #[tokio::test]
+    async fn test_create_order_on_existing(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        let items = |conn: &Connection| -> Vec<(i64, i64)> {
+            conn.prepare("SELECT menu_id, quantity FROM order_items WHERE order_id = ?1 ORDER BY id").unwrap()
+                .query_map(params![order_id], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+                .collect::<rusqlite::Result<_>>().unwrap()
+        };
+
+        // Append is the default
+        let order: OrderRequestBody = serde_json::from_value(json!({"table_id": 1, "menu_ids": [2, 3], "force": true})).unwrap();
+        assert_eq!(order.on_existing, OnExisting::Append);
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(items(&conn), vec![(1, 1), (2, 2), (3, 1)]);
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![4], on_existing: OnExisting::Reject, ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        assert_eq!(convert_response_to_json(resp).await["error"]["id"].as_i64(), Some(order_id));
+        assert_eq!(items(&conn), vec![(1, 1), (2, 2), (3, 1)]);
+
+        // Replacing twice leaves the same items, the double tap check does not get in the way
+        for _ in 0..2 {
+            let order: OrderRequestBody = serde_json::from_value(json!({"table_id": 1, "menu_ids": [4, 4, 5], "on_existing": "replace"})).unwrap();
+            let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+            assert_eq!(resp.status(), warp::http::StatusCode::OK);
+            assert_eq!(items(&conn), vec![(4, 2), (5, 1)]);
+        }
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 1).unwrap(), Some(order_id));
+    }
+
+    // Test Case: 66 Orders and menus are listed a page at a time with the total count
+    #[tokio::test]
+    async fn test_list_handlers_pagination(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        for _ in 0..3 {
+            conn.execute("INSERT INTO orders (table_id, status) VALUES (1, 'paid')", []).expect("Order Creation Failed");
+        }
+        let page = |limit: Option<i64>, offset: Option<i64>| PageQuery { limit, offset };
+        let ids = |json_data: &serde_json::Value| -> Vec<i64> {
+            json_data["items"].as_array().unwrap().iter().map(|item| item["id"].as_i64().unwrap()).collect()
+        };
+
+        let resp = list_menu_handler(reconnect(), None, false, page(Some(2), Some(1))).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(ids(&json_data), vec![2, 3]);
+        assert_eq!(json_data["total"].as_i64(), Some(5));
+
+        let resp = list_order_handler(reconnect(), page(None, Some(2))).await.unwrap().into_response();
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(ids(&json_data), vec![3]);
+        assert_eq!(json_data["total"].as_i64(), Some(3));
+        assert_eq!(json_data["limit"].as_i64(), Some(50));
+
+        // Limits above the cap are lowered, negative offsets and empty pages are refused
+        let resp = list_order_handler(reconnect(), page(Some(1000), None)).await.unwrap().into_response();
+        assert_eq!(convert_response_to_json(resp).await["limit"].as_i64(), Some(200));
+        let resp = into_client_response(list_order_handler(reconnect(), page(None, Some(-1))).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(list_menu_handler(reconnect(), None, false, page(Some(0), None)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(list_menu_handler(conn, None, false, page(None, Some(-5))).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 67 Menus are found by a part of their name, wildcards in the query are taken literally
+    #[tokio::test]
+    async fn test_search_menu_handler(){
+        let conn = setup_test_db();
+        for name in ["Chicken Soup", "Tomato soup", "Soup_of_the_day", "100% Beef Burger", "Cola"] {
+            conn.execute("INSERT INTO menus (name) VALUES (?1)", [name]).expect("Insertion Failed");
+        }
+        let names = |json_data: &serde_json::Value| -> Vec<String> {
+            json_data.as_array().unwrap().iter().map(|menu| menu["name"].as_str().unwrap().to_string()).collect()
+        };
+
+        let resp = search_menu_handler(reconnect(), "soup".to_string()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(names(&convert_response_to_json(resp).await), vec!["Chicken Soup", "Soup_of_the_day", "Tomato soup"]);
+        let resp = search_menu_handler(reconnect(), "_of_".to_string()).await.unwrap().into_response();
+        assert_eq!(names(&convert_response_to_json(resp).await), vec!["Soup_of_the_day"]);
+        let resp = search_menu_handler(reconnect(), "0%".to_string()).await.unwrap().into_response();
+        assert_eq!(names(&convert_response_to_json(resp).await), vec!["100% Beef Burger"]);
+        let resp = search_menu_handler(reconnect(), "%".to_string()).await.unwrap().into_response();
+        assert_eq!(names(&convert_response_to_json(resp).await), vec!["100% Beef Burger"]);
+
+        let resp = search_menu_handler(reconnect(), "pizza".to_string()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await, json!([]));
+        let resp = into_client_response(search_menu_handler(conn, " ".to_string()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 68 Only tables that never had an order, even a paid or voided one, are unused
+    #[tokio::test]
+    async fn test_unused_tables_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("INSERT INTO orders (table_id, status) VALUES (1, 'paid')", []).expect("Order Creation Failed");
+        conn.execute("INSERT INTO orders (table_id, status) VALUES (3, 'void')", []).expect("Order Creation Failed");
+        let result = unused_tables_handler(conn).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data, json!([{"id": 2, "code": "T-02"}]));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 69 Prices with more decimal places than price_decimal_places are rejected
+    #[tokio::test]
+    async fn test_create_menu_price_decimal_places(){
+        let conn = setup_test_db();
+        let menu = |name: &str, price: f64| Menu { id: 0, name: name.to_string(), price, category: None, station: None, tax_category: None, cost: None };
+
+        let resp = into_client_response(create_menu_handler(reconnect(), menu("Pasta", 9.99)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let resp = into_client_response(create_menu_handler(reconnect(), menu("Risotto", 9.999)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        assert_eq!(convert_response_to_json(resp).await["error"]["message"], "price of Risotto can have at most 2 decimal places");
+        let data = ImportRequest { tables: vec![], menus: vec![menu("Gnocchi", 12.5), menu("Lasagne", 0.001)] };
+        let resp = into_client_response(import_handler(reconnect(), data).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        // Without minor units only whole prices are accepted
+        let resp = into_client_response(update_setting_handler(reconnect(), "price_decimal_places".to_string(), SettingUpdate { value: json!(0) }, manager_context()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let resp = into_client_response(create_menu_handler(reconnect(), menu("Ramen", 1200.5)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(create_menu_handler(reconnect(), menu("Ramen", 1200.0)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let names: Vec<String> = conn
+            .prepare("SELECT name FROM menus ORDER BY id").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(names, vec!["Pasta", "Ramen"]);
+    }
+
+    // Test Case: 70 The eta of a table is its longest cooking item, items cook in parallel
+    #[tokio::test]
+    async fn test_get_table_eta_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let resp = into_client_response(get_table_eta_handler(reconnect(), 1).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        assert_eq!(convert_response_to_json(resp).await["error"]["message"], "No running order for this table");
+
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        OrderItem::create(&conn, order_id, 1, 6).expect("OrderItems creation failed");
+        OrderItem::create(&conn, order_id, 2, 9).expect("OrderItems creation failed");
+        let resp = get_table_eta_handler(reconnect(), 1).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await, json!({"eta_minutes": 9}));
+
+        // Ready items no longer count
+        OrderItem::set_status(&conn, 1, 2, "ready").unwrap();
+        let resp = get_table_eta_handler(conn, 1).await.unwrap().into_response();
+        assert_eq!(convert_response_to_json(resp).await, json!({"eta_minutes": 6}));
+    }
+
+    // Test Case: 71 Cancelling deletes the order with its items, or nothing when the order cannot go
+    #[tokio::test]
+    async fn test_delete_order_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let count = |conn: &Connection, table: &str| -> i64 {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap()
+        };
+        for table_id in [1, 2] {
+            let order = OrderRequestBody { table_id, menu_ids: vec![1, 2], ..Default::default() };
+            let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+            assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        }
+
+        let result = delete_order_handler(reconnect(), 1).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                assert_eq!(convert_response_to_json(resp).await["success"], "Order cancelled");
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        assert_eq!(count(&conn, "orders"), 1);
+        assert_eq!(count(&conn, "order_items"), 2);
+        let resp = into_client_response(delete_order_handler(reconnect(), 1).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+
+        // The items delete succeeds but the audit log keeps the order, so everything is rolled back
+        let order_id = OrderResponse::get_existing_order_id(&conn, 2).unwrap().unwrap();
+        AuditEntry::record(&conn, order_id, "comp", Some("manager"), Some("M-01: cold")).unwrap();
+        let resp = into_client_response(delete_order_handler(reconnect(), 2).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        assert_eq!(count(&conn, "orders"), 1);
+        assert_eq!(count(&conn, "order_items"), 2);
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 2).unwrap(), Some(order_id));
+    }
+
+    // Test Case: 72 A backup restored into a fresh database gives the same data back
+    #[tokio::test]
+    async fn test_backup_restore_round_trip(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let manager = RequestContext { role: Some("manager".to_string()), ..Default::default() };
+        conn.execute("UPDATE menus SET price = 8.5, category = 'mains' WHERE id = 1", []).expect("Menu update failed");
+        Setting::set(&conn, "tip_percentages", "10,15").unwrap();
+        let items = vec![OrderLine { menu_id: 1, quantity: 2, notes: Some("no salt".to_string()) }, OrderLine { menu_id: 3, quantity: 1, notes: None }];
+        let order = OrderRequestBody { table_id: 2, items: Some(items), ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        OrderItem::set_status(&conn, 2, 3, "cooking").unwrap();
+
+        let resp = into_client_response(backup_handler(reconnect(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+        let resp = backup_handler(reconnect(), manager.clone()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["tables"]["menus"][0]["price"].as_f64(), Some(8.5));
+        let backup: Backup = serde_json::from_value(json_data).unwrap();
+
+        let path = std::env::temp_dir().join(format!("restaurant_restore_{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let open_fresh = || {
+            let conn = crate::db::open_db_conn(&path).expect("Failed to open test database");
+            conn.execute("PRAGMA foreign_keys = ON;", []).expect("Failed to enable foreign key support");
+            conn
+        };
+        let fresh = open_fresh();
+        crate::db::create_schema(&fresh).expect("Schema creation failed");
+        // Whatever was there before is replaced
+        fresh.execute("INSERT INTO tables (code) VALUES ('OLD')", []).expect("Insertion Failed");
+        let resp = restore_handler(fresh.into(), backup, manager).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+
+        let fresh = open_fresh();
+        let restored = Backup::create(&fresh).unwrap();
+        let original = Backup::create(&conn).unwrap();
+        for table in ["tables", "menus", "orders", "order_items", "order_item_changes", "settings"] {
+            assert_eq!(restored.tables[table], original.tables[table], "{}", table);
+        }
+        assert_eq!(restored, original);
+        // The change history keeps working after the restore
+        OrderItem::set_status(&fresh, 2, 3, "ready").unwrap();
+        let version: i64 = fresh.query_row("SELECT version FROM orders", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, original.tables["orders"][0]["version"].as_i64().unwrap() + 1);
+
+        drop(fresh);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    // Test Case: 73 Every list endpoint returns the same Page shape with the total over all pages
+    #[tokio::test]
+    async fn test_list_endpoints_page_shape(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        for table_id in [1, 2] {
+            conn.execute("INSERT INTO orders (table_id, status) VALUES (?1, 'paid')", [table_id]).expect("Order Creation Failed");
+        }
+        let page = || PageQuery { limit: Some(2), offset: Some(1) };
+        let assert_page = |json_data: &serde_json::Value, items: usize, total: i64| {
+            let mut keys: Vec<&String> = json_data.as_object().unwrap().keys().collect();
+            keys.sort();
+            assert_eq!(keys, vec!["items", "limit", "offset", "total"]);
+            assert_eq!(json_data["items"].as_array().unwrap().len(), items);
+            assert_eq!(json_data["total"].as_i64(), Some(total));
+            assert_eq!(json_data["limit"].as_i64(), Some(2));
+            assert_eq!(json_data["offset"].as_i64(), Some(1));
+        };
+
+        let resp = list_table_handler(reconnect(), page()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_page(&convert_response_to_json(resp).await, 2, 3);
+        let resp = list_menu_handler(reconnect(), None, false, page()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_page(&convert_response_to_json(resp).await, 2, 5);
+        let resp = list_order_handler(reconnect(), page()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_page(&convert_response_to_json(resp).await, 1, 2);
+
+        let resp = into_client_response(list_table_handler(conn, PageQuery { limit: None, offset: Some(-1) }).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 74 New order items get their cooking time from the source handed to the handler
+    #[tokio::test]
+    async fn test_cooking_time_source(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let cooking_times = |conn: &Connection| -> Vec<(i64, i64)> {
+            conn.prepare("SELECT menu_id, cooking_time FROM order_items ORDER BY id").unwrap()
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+                .collect::<rusqlite::Result<_>>().unwrap()
+        };
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2, 2], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), Arc::new(FixedCookingTime(7))).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        assert_eq!(cooking_times(&conn), vec![(1, 7), (2, 14)]);
+
+        // Adding to the running order, directly and through operations
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![3], force: true, ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), Arc::new(FixedCookingTime(9))).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let data = ModifyOrderRequest { operations: vec![OrderOperation::Add { menu_id: 4 }] };
+        let resp = modify_order_handler(reconnect(), 1, data, RequestContext::default(), Arc::new(FixedCookingTime(11))).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(cooking_times(&conn), vec![(1, 7), (2, 14), (3, 9), (4, 11)]);
+    }
+
+    // Test Case: 75 A menu is fetched by its id, unknown ids are a 404 and a broken database a 500
+    #[tokio::test]
+    async fn test_get_menu_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 4.5, category = 'drinks' WHERE id = 2", []).expect("Menu update failed");
+
+        let result = get_menu_handler(reconnect(), 2).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                assert_eq!(json_data["id"].as_i64(), Some(2));
+                assert_eq!(json_data["name"].as_str(), Some("M-02"));
+                assert_eq!(json_data["price"].as_f64(), Some(4.5));
+                assert_eq!(json_data["category"].as_str(), Some("drinks"));
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+        let resp = into_client_response(get_menu_handler(reconnect(), 99).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        assert_eq!(convert_response_to_json(resp).await["error"]["code"], "not_found");
+        let resp = into_client_response(get_menu_handler(conn_for_request(true, "__fail=db", reconnect), 2).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    // Test Case: 76 The prep breakdown shows how far along each item of the running order is
+    #[tokio::test]
+    async fn test_order_prep_breakdown_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let at = |time: &str| -> Arc<dyn Clock> {
+            let timestamp: i64 = conn.query_row("SELECT CAST(strftime('%s', ?1) AS INTEGER)", [time], |row| row.get(0)).unwrap();
+            Arc::new(FixedClock(timestamp))
+        };
+        let resp = into_client_response(order_prep_breakdown_handler(reconnect(), 1, at("2024-05-01 19:00:00")).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        for (menu_id, cooking_time) in [(1, 10), (2, 12), (3, 8), (4, 6)] {
+            OrderItem::create(&conn, order_id, menu_id, cooking_time).expect("OrderItems creation failed");
+        }
+        conn.execute_batch(
+            "UPDATE order_items SET item_status = 'cooking', fired_at = '2024-05-01 18:55:00' WHERE menu_id = 2;
+            UPDATE order_items SET item_status = 'cooking', fired_at = '2024-05-01 18:40:00' WHERE menu_id = 3;
+            UPDATE order_items SET item_status = 'served', fired_at = '2024-05-01 18:30:00', ready_at = '2024-05-01 18:37:00', served_at = '2024-05-01 18:39:00' WHERE menu_id = 4;",
+        ).expect("Status update failed");
+
+        let result = order_prep_breakdown_handler(reconnect(), 1, at("2024-05-01 19:00:00")).await;
+        match result {
+            Ok(rep)=>{
+                let resp = rep.into_response();
+                assert_eq!(resp.status(), warp::http::StatusCode::OK);
+                let json_data = convert_response_to_json(resp).await;
+                let breakdown: Vec<(i64, &str, i64, i64, i64)> = json_data.as_array().unwrap().iter().map(|item| (
+                    item["menu_id"].as_i64().unwrap(),
+                    item["item_status"].as_str().unwrap(),
+                    item["cooking_time"].as_i64().unwrap(),
+                    item["elapsed_minutes"].as_i64().unwrap(),
+                    item["remaining_minutes"].as_i64().unwrap(),
+                )).collect();
+                assert_eq!(breakdown, vec![
+                    (1, "pending", 10, 0, 10),
+                    (2, "cooking", 12, 5, 7),
+                    // Running late, nothing is left rather than a negative time
+                    (3, "cooking", 8, 20, 0),
+                    // Stopped counting once it was ready
+                    (4, "served", 6, 7, 0),
+                ]);
+            }
+            Err(_)=>{
+                panic!("Unhandled Error");
+            }
+        }
+    }
+
+    // Test Case: 77 Unknown body fields are ignored by default and rejected with a 400 in strict mode
+    #[tokio::test]
+    async fn test_unknown_body_fields() {
+        let body = json!({
+            "table_id": 1,
+            "items": [{"menu_id": 2, "quantity": 1, "nots": "no onions"}],
+            "fource": true,
+        });
+
+        let lenient: OrderRequestBody = parse_body(body.clone(), false).expect("Lenient parse failed");
+        assert_eq!(lenient.table_id, 1);
+
+        let strict = parse_body::<OrderRequestBody>(body, true);
+        let resp = into_client_response::<warp::reply::Json>(strict.map(|order| warp::reply::json(&order)).map_err(warp::reject::custom)).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"], "unknown fields: fource, items[0].nots");
+
+        // A body using only known fields passes in strict mode too
+        let known = json!({"table_id": 1, "items": [{"menu_id": 2, "quantity": 1, "notes": "no onions"}]});
+        let strict: OrderRequestBody = parse_body(known, true).expect("Strict parse failed");
+        assert_eq!(strict.items.unwrap()[0].notes.as_deref(), Some("no onions"));
+    }
+
+    // Test Case: 78 Creating a menu with a name already taken is a conflict pointing at the existing menu
+    #[tokio::test]
+    async fn test_create_duplicate_menu_handler(){
+        let conn = setup_test_db();
+        let menu = |price: f64| Menu { id: 0, name: "Menu-01".to_string(), price, category: None, station: None, tax_category: None, cost: None };
+
+        let resp = into_client_response(create_menu_handler(reconnect(), menu(9.5)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let resp = into_client_response(create_menu_handler(reconnect(), menu(11.0)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        assert_eq!(convert_response_to_json(resp).await, json!({"error": {"code": "conflict", "message": "Menu already exists", "id": 1}}));
+
+        // The schema refuses a duplicate name as well
+        let duplicate = conn.execute("INSERT INTO menus (name) VALUES ('Menu-01')", []);
+        assert!(duplicate.is_err());
+        let prices: Vec<f64> = conn
+            .prepare("SELECT price FROM menus").unwrap()
+            .query_map([], |row| row.get(0)).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(prices, vec![9.5]);
+    }
+
+    // Test Case: 79 Margins are the share of the price left after the cost, highest first
+    #[tokio::test]
+    async fn test_menu_margins_handler(){
+        let conn = setup_test_db();
+        let menu = |name: &str, price: f64, cost: Option<f64>| Menu { id: 0, name: name.to_string(), price, category: None, station: None, tax_category: None, cost };
+        for data in [menu("Burger", 12.0, Some(4.5)), menu("Soup", 6.0, Some(1.2)), menu("Water", 0.0, Some(0.3)), menu("Bread", 2.0, None)] {
+            let resp = create_menu_handler(reconnect(), data).await.unwrap().into_response();
+            assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        }
+
+        let resp = into_client_response(menu_margins_handler(reconnect(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+
+        let manager = RequestContext { role: Some("manager".to_string()), ..Default::default() };
+        let resp = menu_margins_handler(conn, manager).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await, json!([
+            {"menu_id": 2, "name": "Soup", "price": 6.0, "cost": 1.2, "margin_percent": 80.0},
+            {"menu_id": 1, "name": "Burger", "price": 12.0, "cost": 4.5, "margin_percent": 62.5},
+            {"menu_id": 3, "name": "Water", "price": 0.0, "cost": 0.3, "margin_percent": null},
+        ]));
+    }
+
+    // Test Case: 80 Request log lines pick the table and menu out of the path, RUST_LOG picks the levels
+    #[test]
+    fn test_request_logging(){
+        assert_eq!(path_params("/orders/3"), (Some(3), None));
+        assert_eq!(path_params("/tables/4/items/12/status"), (Some(4), Some(12)));
+        assert_eq!(path_params("/orders/4/items/12"), (Some(4), Some(12)));
+        assert_eq!(path_params("/menus/7"), (None, Some(7)));
+        assert_eq!(path_params("/menus/search"), (None, None));
+        assert_eq!(path_params("/tickets/9"), (None, None));
+
+        let directives = Directives::parse("warn, simple_restaurant_api=debug, simple_restaurant_api::db=off, warp=bogus");
+        assert!(directives.enabled("simple_restaurant_api::handlers", &tracing::Level::DEBUG));
+        assert!(!directives.enabled("simple_restaurant_api::handlers", &tracing::Level::TRACE));
+        assert!(!directives.enabled("simple_restaurant_api::db", &tracing::Level::ERROR));
+        assert!(directives.enabled("warp::server", &tracing::Level::WARN));
+        assert!(!directives.enabled("warp::server", &tracing::Level::INFO));
+    }
+
+    // Test Case: 81 86ing a menu makes it unavailable and tells the tables still waiting for it
+    #[tokio::test]
+    async fn test_eighty_six_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        for (table_id, menu_ids) in [(1, vec![1, 2]), (2, vec![2]), (3, vec![3])] {
+            let order = OrderRequestBody { table_id, menu_ids, ..Default::default() };
+            let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+            assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        }
+        // Table 2 already has its plate, it is not waiting anymore
+        conn.execute("UPDATE order_items SET item_status = 'served' WHERE menu_id = 2 AND order_id = (SELECT id FROM orders WHERE table_id = 2)", []).unwrap();
+        let mut receiver = events::subscribe();
+
+        let context = RequestContext { request_id: Some("req-86".to_string()), ..Default::default() };
+        let resp = eighty_six_handler(reconnect(), 2, context).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["available"], false);
+        let tables: Vec<(i64, &str)> = json_data["tables"].as_array().unwrap().iter()
+            .map(|table| (table["table_id"].as_i64().unwrap(), table["table_code"].as_str().unwrap()))
+            .collect();
+        assert_eq!(tables, vec![(1, "T-01")]);
+
+        let menu = Menu::get(&conn, 2).unwrap().unwrap();
+        assert!(!menu.available);
+
+        // Other tests publish on the same channel
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(err) => panic!("{}", err),
+            };
+            if event.request_id.as_deref() == Some("req-86") {
+                assert_eq!(event.event, "item_86ed");
+                assert_eq!((event.table_id, event.menu_id), (1, Some(2)));
+                break;
+            }
+        }
+
+        let resp = into_client_response(eighty_six_handler(reconnect(), 99, RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 82 Bulk table creation skips existing codes and creates nothing when an insert fails
+    #[tokio::test]
+    async fn test_create_tables_bulk_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let tables = |codes: &[&str]| codes.iter().map(|code| Table { id: 0, code: code.to_string() }).collect::<Vec<_>>();
+
+        let resp = create_tables_bulk_handler(reconnect(), tables(&["Patio-1", "T-02", "Patio-2", "Patio-1"])).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        assert_eq!(convert_response_to_json(resp).await, json!({"created": [4, 5], "skipped": ["T-02", "Patio-1"]}));
+
+        let resp = into_client_response(create_tables_bulk_handler(reconnect(), tables(&["Bar-1", " "])).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        // The second insert fails, the first one is rolled back with it
+        conn.execute_batch("CREATE TRIGGER no_bar_2 BEFORE INSERT ON tables WHEN NEW.code = 'Bar-2' BEGIN SELECT RAISE(ABORT, 'no'); END;").unwrap();
+        let resp = into_client_response(create_tables_bulk_handler(reconnect(), tables(&["Bar-1", "Bar-2"])).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM tables", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 5);
+    }
+
+    // Test Case: 83 Health needs the database, live does not
+    #[tokio::test]
+    async fn test_health_handlers(){
+        let _conn = setup_test_db();
+        let resp = health_handler(reconnect()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await, json!({"status": "ok"}));
+
+        let resp = health_handler(conn_for_request(true, "__fail=db", reconnect)).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(convert_response_to_json(resp).await, json!({"status": "db_unreachable"}));
+
+        let resp = live_handler().await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+    }
+
+    // Test Case: 84 Order items always come back in the order they were added
+    #[tokio::test]
+    async fn test_order_items_creation_order(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![3, 1, 5], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        // An item restored from a backup keeps its older creation time and goes first
+        conn.execute("UPDATE order_items SET created_at = '2024-01-01 12:00:00' WHERE menu_id = 5", []).unwrap();
+
+        let mut fetches = Vec::new();
+        for _ in 0..2 {
+            let resp = list_order_items_for_table_handler(reconnect(), 1).await.unwrap().into_response();
+            assert_eq!(resp.status(), warp::http::StatusCode::OK);
+            let json_data = convert_response_to_json(resp).await;
+            let menu_ids: Vec<i64> = json_data.as_array().unwrap().iter().map(|item| item["menu_id"].as_i64().unwrap()).collect();
+            fetches.push(menu_ids);
+        }
+        assert_eq!(fetches[0], vec![5, 3, 1, 2]);
+        assert_eq!(fetches[0], fetches[1]);
+    }
+
+    // Test Case: 85 The order eta is now plus the busiest station's remaining time, scaled during a rush
+    #[tokio::test]
+    async fn test_order_eta_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let at = |time: &str| -> Arc<dyn Clock> {
+            let timestamp: i64 = conn.query_row("SELECT CAST(strftime('%s', ?1) AS INTEGER)", [time], |row| row.get(0)).unwrap();
+            Arc::new(FixedClock(timestamp))
+        };
+        let resp = into_client_response(order_eta_handler(reconnect(), 1, at("2024-05-01 19:00:00")).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        for (menu_id, cooking_time) in [(1, 10), (2, 12), (3, 8), (4, 6)] {
+            OrderItem::create(&conn, order_id, menu_id, cooking_time).expect("OrderItems creation failed");
+        }
+        conn.execute_batch(
+            "UPDATE menus SET station = 'grill' WHERE id IN (1, 2);
+            UPDATE menus SET station = 'pastry' WHERE id = 3;
+            UPDATE order_items SET item_status = 'cooking', fired_at = '2024-05-01 18:55:00' WHERE menu_id = 2;
+            UPDATE order_items SET item_status = 'served' WHERE menu_id = 4;",
+        ).expect("Setup failed");
+
+        // The grill still has 10 minutes plus 7 of the 12 being cooked, the pastry station only 8
+        let resp = order_eta_handler(reconnect(), 1, at("2024-05-01 19:00:00")).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["remaining_minutes"], 17);
+        assert_eq!(json_data["estimated_ready_at"], "2024-05-01T19:17:00Z");
+
+        let resp = update_setting_handler(reconnect(), "rush_multiplier".to_string(), SettingUpdate { value: json!(2) }, manager_context()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let resp = order_eta_handler(reconnect(), 1, at("2024-05-01 19:00:00")).await.unwrap().into_response();
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["order_id"].as_i64(), Some(order_id));
+        assert_eq!(json_data["estimated_ready_at"], "2024-05-01T19:34:00Z");
+    }
+
+    // Test Case: 86 Long list replies hint at paging without being cut short
+    #[tokio::test]
+    async fn test_large_list_hint(){
+        let conn = setup_test_db();
+        for index in 1..=120 {
+            conn.execute("INSERT INTO menus (name) VALUES (?1)", [format!("Menu-{:03}", index)]).expect("Insertion Failed");
+        }
+
+        let page = PageQuery { limit: Some(MAX_PAGE_LIMIT), offset: None };
+        let resp = list_menu_handler(reconnect(), None, false, page).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(resp.headers().get("x-result-truncated-hint").map(|value| value.to_str().unwrap()), Some("true"));
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["items"].as_array().unwrap().len(), 120);
+
+        let resp = list_menu_handler(reconnect(), None, false, PageQuery::default()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert!(resp.headers().get("x-result-truncated-hint").is_none());
+
+        // The threshold is a setting
+        let resp = update_setting_handler(reconnect(), "large_list_items".to_string(), SettingUpdate { value: json!(10) }, manager_context()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let resp = list_menu_handler(reconnect(), None, false, PageQuery::default()).await.unwrap().into_response();
+        assert!(resp.headers().get("x-result-truncated-hint").is_some());
+    }
+
+    // Test Case: 87 A menu can be renamed and repriced, but not onto another menu's name
+    #[tokio::test]
+    async fn test_update_menu_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let menu = |name: &str, price: f64| MenuUpdate { name: name.to_string(), price: Some(price) };
+
+        let resp = update_menu_handler(reconnect(), 1, menu("Margherita", 11.5)).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!((json_data["id"].as_i64(), json_data["name"].as_str(), json_data["price"].as_f64()), (Some(1), Some("Margherita"), Some(11.5)));
+
+        // Keeping its own name is not a collision
+        let resp = update_menu_handler(reconnect(), 1, menu("Margherita", 12.0)).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+
+        let resp = into_client_response(update_menu_handler(reconnect(), 2, menu("Margherita", 9.0)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        let resp = into_client_response(update_menu_handler(reconnect(), 99, menu("Calzone", 9.0)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let resp = into_client_response(update_menu_handler(reconnect(), 2, menu(" ", 9.0)).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+
+        let name: String = conn.query_row("SELECT name FROM menus WHERE id = 2", [], |row| row.get(0)).unwrap();
+        assert_eq!(name, "M-02");
+    }
+
+    // Test Case: 88 The server answers until it is told to shut down, then stops
+    #[tokio::test]
+    async fn test_graceful_shutdown(){
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (shutdown, shutdown_received) = tokio::sync::oneshot::channel::<()>();
+        let (addr, server) = crate::server::bind(([127, 0, 0, 1], 0), async {
+            let _ = shutdown_received.await;
+        });
+        let server = tokio::spawn(server);
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.expect("Connection failed");
+        stream.write_all(b"GET /live HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"));
+
+        shutdown.send(()).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), server)
+            .await
+            .expect("Server did not shut down")
+            .unwrap();
+        assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+    }
+
+    // Test Case: 89 Created menus, tables and orders point the Location header at themselves
+    #[tokio::test]
+    async fn test_create_location_header(){
+        let conn = setup_test_db();
+        let location = |resp: &warp::reply::Response| resp.headers().get("location").map(|value| value.to_str().unwrap().to_string());
+
+        let menu = Menu { id: 0, name: "Menu-01".to_string(), price: 9.5, category: None, station: None, tax_category: None, cost: None };
+        let resp = create_menu_handler(reconnect(), menu).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let menu_location = location(&resp);
+        let menu_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        assert_eq!(menu_location, Some(format!("/menus/{}", menu_id)));
+
+        let resp = create_table_handler(reconnect(), Table { id: 0, code: "T-01".to_string() }).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let table_location = location(&resp);
+        let table_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        assert_eq!(table_location, Some(format!("/tables/{}", table_id)));
+
+        let order = OrderRequestBody { table_id, menu_ids: vec![menu_id], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        assert_eq!(location(&resp), Some(format!("/orders/{}", table_id)));
+
+        // Adding to the running order creates nothing new
+        let order = OrderRequestBody { table_id, menu_ids: vec![menu_id], force: true, ..Default::default() };
+        let resp = create_order_handler(conn, order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(location(&resp), None);
+    }
+
+    // Test Case: 90 An order is saved with all of its items or not at all
+    #[tokio::test]
+    async fn test_create_order_is_atomic(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let count = |table: &str| -> i64 { conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0)).unwrap() };
+
+        // Menu 99 does not exist, its item fails after the first two were written
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2, 99], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!((count("orders"), count("order_items")), (0, 0));
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+
+        // Adding to the running order, the quantity bump of menu 1 and the new menu 3 are undone as well
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 3, 99], force: true, ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::INTERNAL_SERVER_ERROR);
+        let items: Vec<(i64, i64)> = conn
+            .prepare("SELECT menu_id, quantity FROM order_items ORDER BY id").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(items, vec![(1, 1)]);
+    }
+
+    // Test Case: 91 Serving a table serves all of its ready items and nothing else
+    #[tokio::test]
+    async fn test_serve_table_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let resp = into_client_response(serve_table_handler(reconnect(), 1).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2, 3], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        for (menu_id, status) in [(1, "ready"), (2, "ready"), (3, "cooking")] {
+            let resp = update_item_status_handler(reconnect(), 1, menu_id, ItemStatusUpdate { status: status.to_string() }).await.unwrap().into_response();
+            assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        }
+
+        let resp = serve_table_handler(reconnect(), 1).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await["served"], 2);
+        let items: Vec<(i64, String, bool)> = conn
+            .prepare("SELECT menu_id, item_status, served_at IS NOT NULL FROM order_items ORDER BY menu_id").unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).unwrap()
+            .collect::<rusqlite::Result<_>>().unwrap();
+        assert_eq!(items, vec![(1, "served".to_string(), true), (2, "served".to_string(), true), (3, "cooking".to_string(), false)]);
+    }
+
+    // Test Case: 92 Menus can be listed by category and the categories are listed once each
+    #[tokio::test]
+    async fn test_list_menus_by_category_handler(){
+        let conn = setup_test_db();
+        for (name, category) in [("Cola", Some("drinks")), ("Lemonade", Some("drinks")), ("Wings", Some("appetizers")), ("Bread", None)] {
+            let menu = Menu { id: 0, name: name.to_string(), price: 3.0, category: category.map(str::to_string), station: None, tax_category: None, cost: None };
+            Menu::create(&conn, &menu).unwrap();
+        }
+
+        let resp = list_menus_by_category_handler(reconnect(), "drinks".to_string()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let menus = convert_response_to_json(resp).await;
+        let names: Vec<&str> = menus.as_array().unwrap().iter().map(|menu| menu["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["Cola", "Lemonade"]);
+
+        let resp = list_menus_by_category_handler(reconnect(), "desserts".to_string()).await.unwrap().into_response();
+        assert_eq!(convert_response_to_json(resp).await, json!([]));
+
+        let resp = list_menu_categories_handler(reconnect()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(convert_response_to_json(resp).await, json!(["appetizers", "drinks"]));
+    }
+
+    // Test Case: 93 Adding past the max_line_quantity setting, or past what an i64 holds, is refused
+    #[tokio::test]
+    async fn test_line_quantity_limit(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let resp = into_client_response(update_setting_handler(reconnect(), "max_line_quantity".to_string(), SettingUpdate { value: json!(5) }, manager_context()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+
+        let order = |quantity| OrderRequestBody { table_id: 1, items: Some(vec![OrderLine { menu_id: 1, quantity, notes: None }]), ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order(6), RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(create_order_handler(reconnect(), order(3), RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let resp = into_client_response(create_order_handler(reconnect(), order(3), RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        assert_eq!(convert_response_to_json(resp).await["error"]["message"], "Quantity of menu 1 must not exceed 5");
+
+        let modify = ModifyOrderRequest { operations: vec![OrderOperation::SetQuantity { menu_id: 1, quantity: i64::MAX }] };
+        let resp = into_client_response(modify_order_handler(reconnect(), 1, modify, RequestContext::default(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let quantity: i64 = conn.query_row("SELECT quantity FROM order_items WHERE menu_id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(quantity, 3);
+    }
+
+    // Test Case: 94 Throughput counts the portions served inside the trailing window only
+    #[tokio::test]
+    async fn test_throughput_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        for menu_id in 1..=4 {
+            OrderItem::create(&conn, order_id, menu_id, 10).expect("OrderItems creation failed");
+        }
+        conn.execute_batch(
+            "UPDATE order_items SET item_status = 'served', served_at = '2024-05-01 18:50:00', quantity = 2 WHERE menu_id = 1;
+            UPDATE order_items SET item_status = 'served', served_at = '2024-05-01 18:59:00' WHERE menu_id = 2;
+            UPDATE order_items SET item_status = 'served', served_at = '2024-05-01 18:30:00' WHERE menu_id = 3;
+            UPDATE order_items SET item_status = 'ready' WHERE menu_id = 4;",
+        ).expect("Setup failed");
+        let timestamp: i64 = conn.query_row("SELECT CAST(strftime('%s', '2024-05-01 19:00:00') AS INTEGER)", [], |row| row.get(0)).unwrap();
+
+        let resp = throughput_handler(reconnect(), 15, Arc::new(FixedClock(timestamp))).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["served"], 3);
+        assert_eq!(json_data["per_minute"], 0.2);
+
+        let resp = into_client_response(throughput_handler(reconnect(), 0, Arc::new(FixedClock(timestamp))).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 95 A table code of only whitespace, or longer than 32 characters, is refused
+    #[tokio::test]
+    async fn test_create_table_validates_code(){
+        let conn = setup_test_db();
+        let table: Table = serde_json::from_value(json!({"code": "  "})).unwrap();
+        let resp = into_client_response(create_table_handler(reconnect(), table).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        assert_eq!(convert_response_to_json(resp).await["error"]["message"], "Table code cannot be empty");
+
+        let table = Table { id: 0, code: "T".repeat(33) };
+        let resp = into_client_response(create_table_handler(reconnect(), table).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM tables", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+
+        let table = Table { id: 0, code: "T-01".to_string() };
+        let resp = into_client_response(create_table_handler(reconnect(), table).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+    }
+
+    // Test Case: 96 The bill of a table lists every line and rounds the tax of each to the cent
+    #[tokio::test]
+    async fn test_generate_bill_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let resp = into_client_response(generate_bill_handler(reconnect(), 1).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+
+        conn.execute_batch("UPDATE menus SET price = 4.99 WHERE id = 1; UPDATE menus SET price = 2.5 WHERE id = 2;").expect("Setup failed");
+        let resp = update_setting_handler(reconnect(), "tax_rate_default".to_string(), SettingUpdate { value: json!(8.25) }, manager_context()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        OrderItem::create_with_quantity(&conn, order_id, 1, 10, 3).expect("OrderItems creation failed");
+        OrderItem::create(&conn, order_id, 2, 5).expect("OrderItems creation failed");
+
+        let resp = generate_bill_handler(reconnect(), 1).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let bill = convert_response_to_json(resp).await;
+        assert_eq!(bill["lines"][0], json!({"menu_name": "M-01", "quantity": 3, "unit_price": 4.99, "line_total": 14.97}));
+        assert_eq!(bill["lines"][1]["line_total"], 2.5);
+        assert_eq!(bill["subtotal"], 17.47);
+        assert_eq!(bill["tax_rate"], 8.25);
+        // 1.235 on the first line and 0.206 on the second, rounded per line
+        assert_eq!(bill["tax_amount"], 1.45);
+        assert_eq!(bill["grand_total"], 18.92);
+    }
+
+    // Test Case: 97 Appending to the running order of a table deleted out of band is NOT FOUND
+    #[tokio::test]
+    async fn test_append_to_deleted_table(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        conn.execute_batch("PRAGMA foreign_keys = OFF; DELETE FROM tables WHERE id = 1; PRAGMA foreign_keys = ON;").expect("Delete failed");
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        assert_eq!(convert_response_to_json(resp).await, json!({"error": {"code": "not_found", "message": "table no longer exists"}}));
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM order_items WHERE menu_id = 2", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 0);
+    }
+
+    // Test Case: 98 The kitchen websocket sends the open items first, then an event for every new or removed item
+    #[tokio::test]
+    async fn test_kitchen_ws_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 2).expect("Order Creation Failed");
+        OrderItem::create(&conn, order_id, 3, 5).expect("OrderItems creation failed");
+        let filter = warp::ws().and_then(|ws| kitchen_ws_handler(reconnect(), ws));
+        let mut client = warp::test::ws().handshake(filter).await.expect("Handshake failed");
+
+        let snapshot: serde_json::Value = serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+        assert_eq!(snapshot["event"], "snapshot");
+        assert_eq!(snapshot["items"].as_array().unwrap().len(), 1);
+        assert_eq!(snapshot["items"][0]["menu_id"], 3);
+
+        let context = RequestContext { request_id: Some("req-ws-98".to_string()), ..Default::default() };
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, context.clone(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let resp = delete_order_item_handler(reconnect(), 1, 2, RemoveItemQuery { force: false }, context).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+
+        // Other tests publish on the same channel
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            let event: serde_json::Value = serde_json::from_str(client.recv().await.unwrap().to_str().unwrap()).unwrap();
+            if event["request_id"] == "req-ws-98" {
+                received.push((event["event"].as_str().unwrap().to_string(), event["table_id"].as_i64(), event["menu_id"].as_i64()));
+            }
+        }
+        assert_eq!(received, vec![("order_created".to_string(), Some(1), None), ("item_removed".to_string(), Some(1), Some(2))]);
+    }
+
+    // Test Case: 99 New items are held as pending unless auto_fire is on, then they start cooking
+    #[tokio::test]
+    async fn test_auto_fire_setting(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let item = OrderItem::get_item(&conn, 1, 1).unwrap().unwrap();
+        assert_eq!((item.item_status.as_str(), item.fired_at.is_some()), ("pending", false));
+
+        let resp = update_setting_handler(reconnect(), "auto_fire".to_string(), SettingUpdate { value: json!(true) }, manager_context()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let item = OrderItem::get_item(&conn, 1, 2).unwrap().unwrap();
+        assert_eq!((item.item_status.as_str(), item.fired_at.is_some()), ("cooking", true));
+        // Items already on the order keep their status
+        let item = OrderItem::get_item(&conn, 1, 1).unwrap().unwrap();
+        assert_eq!(item.item_status, "pending");
+    }
+
+    // Test Case: 100 Tables are listed by code, occupied ones with their running order and item count
+    #[tokio::test]
+    async fn test_list_tables_with_status_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 2, items: Some(vec![OrderLine { menu_id: 1, quantity: 2, notes: None }, OrderLine { menu_id: 3, quantity: 1, notes: None }]), ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+
+        let resp = list_tables_with_status_handler(reconnect()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let tables = convert_response_to_json(resp).await;
+        assert_eq!(tables[0], json!({"id": 1, "code": "T-01", "occupied": false}));
+        assert_eq!(tables[1], json!({"id": 2, "code": "T-02", "occupied": true, "order_id": order_id, "item_count": 3}));
+        assert_eq!(tables.as_array().unwrap().len(), 3);
+    }
+
+    // Test Case: 101 The timeline of an order runs from its creation through its changes to its payment
+    #[tokio::test]
+    async fn test_order_timeline_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 5", []).expect("Setup failed");
+        let resp = into_client_response(order_timeline_handler(reconnect(), 1).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        let order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        let data = ModifyOrderRequest { operations: vec![OrderOperation::SetQuantity { menu_id: 1, quantity: 2 }] };
+        let resp = modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let resp = pay_order_handler(reconnect(), 1, PayOrderQuery::default(), RequestContext::default()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+
+        let resp = order_timeline_handler(reconnect(), order_id).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let timeline = convert_response_to_json(resp).await;
+        let actions: Vec<&str> = timeline.as_array().unwrap().iter().map(|entry| entry["action"].as_str().unwrap()).collect();
+        assert_eq!(actions, vec!["created", "item_added", "item_changed", "paid"]);
+        assert_eq!(timeline[1]["note"], "M-01");
+        assert!(timeline[3]["at"].is_string());
+    }
+
+    // Test Case: 102 A batch creates every order on its own, a failing one is reported without stopping the rest
+    #[tokio::test]
+    async fn test_batch_create_orders_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute_batch("INSERT INTO tables (code) VALUES ('T-04'); INSERT INTO tables (code) VALUES ('T-05');").expect("Setup failed");
+        let orders = (1..=5)
+            .map(|table_id| OrderRequestBody { table_id, menu_ids: if table_id == 4 { vec![99] } else { vec![1, 2] }, ..Default::default() })
+            .collect();
+        let resp = batch_create_orders_handler(reconnect(), orders, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        let results = json_data["results"].as_array().unwrap();
+        let statuses: Vec<(i64, i64)> = results.iter().map(|result| (result["table_id"].as_i64().unwrap(), result["status"].as_i64().unwrap())).collect();
+        assert_eq!(statuses, vec![(1, 201), (2, 201), (3, 201), (4, 500), (5, 201)]);
+        assert!(results[0]["body"]["id"].is_i64());
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM orders", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, 4);
+
+        let resp = into_client_response(batch_create_orders_handler(reconnect(), vec![], RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+    }
+
+    // Test Case: 103 Pooled connections run in WAL mode with a busy timeout, so parallel orders never hit a lock error
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_create_order_handler_concurrent_writes(){
+        use tokio::sync::Barrier;
+
+        // Locking only matters for a real file
+        let path = std::env::temp_dir().join(format!("restaurant_concurrent_orders_{}.db", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let pool = Arc::new(crate::db::DbPool::new(&path, 8, std::time::Duration::from_secs(60)));
+        let conn = pool.get().expect("Failed to open test database");
+        crate::db::create_schema(&conn).expect("Schema creation failed");
+        setup_static_data(&conn);
+        let journal_mode: String = conn.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        let busy_timeout: i64 = conn.query_row("PRAGMA busy_timeout", [], |row| row.get(0)).unwrap();
+        let foreign_keys: i64 = conn.query_row("PRAGMA foreign_keys", [], |row| row.get(0)).unwrap();
+        assert_eq!((journal_mode.as_str(), busy_timeout, foreign_keys), ("wal", 5000, 1));
+
+        const REQUESTS: i64 = 8;
+        for table_id in 4..=REQUESTS {
+            conn.execute("INSERT INTO tables (code) VALUES (?1)", [format!("T-{:02}", table_id)]).expect("Insertion Failed");
+        }
+        let barrier = Arc::new(Barrier::new(REQUESTS as usize));
+        let mut requests = Vec::new();
+        for table_id in 1..=REQUESTS {
+            let conn = pool.get().expect("Failed to open test database");
+            let barrier = barrier.clone();
+            let order = OrderRequestBody { table_id, menu_ids: vec![1, 2, 3], ..Default::default() };
+            requests.push(tokio::spawn(async move {
+                barrier.wait().await;
+                create_order_handler(conn, order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response()
+            }));
+        }
+        for request in requests {
+            let resp = request.await.expect("Request panicked");
+            assert_eq!(resp.status(), warp::http::StatusCode::CREATED, "{:?}", convert_response_to_json(resp).await);
+        }
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM orders", [], |row| row.get(0)).unwrap();
+        assert_eq!(count, REQUESTS);
+
+        drop(conn);
+        drop(pool);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path, suffix));
+        }
+    }
+
+    // Test Case: 104 Setting an item's quantity scales its cooking time, 0 takes it off and a negative one is refused
+    #[tokio::test]
+    async fn test_set_order_item_quantity_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        OrderItem::create(&conn, order_id, 1, 6).expect("OrderItems creation failed");
+        OrderItem::create(&conn, order_id, 2, 5).expect("OrderItems creation failed");
+
+        let resp = into_client_response(set_order_item_quantity_handler(reconnect(), 1, 1, QuantityUpdate { quantity: -1 }, RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let resp = into_client_response(set_order_item_quantity_handler(reconnect(), 1, 3, QuantityUpdate { quantity: 2 }, RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+
+        let resp = set_order_item_quantity_handler(reconnect(), 1, 1, QuantityUpdate { quantity: 4 }, RequestContext::default()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let item = OrderItem::get_item(&conn, 1, 1).unwrap().unwrap();
+        assert_eq!((item.quantity, item.cooking_time), (4, 24));
+
+        let resp = set_order_item_quantity_handler(reconnect(), 1, 2, QuantityUpdate { quantity: 0 }, RequestContext::default()).await.unwrap().into_response();
+        assert_eq!(convert_response_to_json(resp).await["order_deleted"], false);
+        assert!(OrderItem::get_item(&conn, 1, 2).unwrap().is_none());
+        let resp = set_order_item_quantity_handler(reconnect(), 1, 1, QuantityUpdate { quantity: 0 }, RequestContext::default()).await.unwrap().into_response();
+        assert_eq!(convert_response_to_json(resp).await["order_deleted"], true);
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 1).unwrap(), None);
+    }
+
+    // Test Case: 105 Config falls back to its defaults and reports a PORT that is not a u16
+    #[test]
+    fn test_config_from_vars(){
+        use crate::config::Config;
+        use std::collections::HashMap;
+        let from = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+            Config::from_vars(|key| vars.get(key).cloned())
+        };
+
+        let config = from(&[]).unwrap();
+        assert_eq!((config.database_url.as_str(), config.addr().to_string()), ("restaurent.db", "127.0.0.1:3030".to_string()));
+
+        let config = from(&[("DATABASE_URL", "sqlite:///var/lib/restaurant.db"), ("BIND_ADDR", "0.0.0.0"), ("PORT", "8080")]).unwrap();
+        assert_eq!((config.database_url.as_str(), config.addr().to_string()), ("/var/lib/restaurant.db", "0.0.0.0:8080".to_string()));
+
+        let err = from(&[("PORT", "70000")]).unwrap_err();
+        assert_eq!(err.to_string(), "PORT must be a port number from 0 to 65535, got \"70000\"");
+        assert_eq!(from(&[("BIND_ADDR", "localhost")]).unwrap_err().var, "BIND_ADDR");
+    }
+
+    // Test Case: 106 An archived menu leaves the menu list and cannot be ordered, a past order still shows its name
+    // and its name can be used again
+    #[tokio::test]
+    async fn test_archive_menu_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        OrderItem::create(&conn, order_id, 1, 6).expect("OrderItems creation failed");
+        OrderResponse::pay(&conn, order_id).expect("Payment failed");
+
+        let resp = into_client_response(archive_menu_handler(reconnect(), 99).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let resp = archive_menu_handler(reconnect(), 1).await.unwrap().into_response();
+        assert_eq!(convert_response_to_json(resp).await["active"], false);
+
+        let resp = list_menu_handler(reconnect(), None, false, PageQuery::default()).await.unwrap().into_response();
+        let body = convert_response_to_json(resp).await;
+        assert_eq!(body["total"], 4);
+        assert!(body["items"].as_array().unwrap().iter().all(|menu| menu["id"] != 1));
+        let resp = list_menu_handler(reconnect(), None, true, PageQuery::default()).await.unwrap().into_response();
+        assert_eq!(convert_response_to_json(resp).await["total"], 5);
+
+        let receipt = Receipt::for_order(&conn, order_id).unwrap().unwrap();
+        assert_eq!(receipt.lines[0].menu_name, Menu::get(&conn, 1).unwrap().unwrap().name);
+        assert_eq!(Menu::unavailable_names(&conn, &[1, 2]).unwrap().len(), 1);
+
+        // The archived menu frees its name and its category once no active menu uses it
+        let archived_name = Menu::get(&conn, 1).unwrap().unwrap().name;
+        conn.execute("UPDATE menus SET category = 'Seasonal' WHERE id = 1", []).unwrap();
+        assert!(Menu::categories(&conn).unwrap().is_empty());
+        let menu = Menu { id: 0, name: archived_name, price: 4.0, category: None, station: None, tax_category: None, cost: None };
+        let resp = create_menu_handler(reconnect(), menu).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+    }
+
+    // Test Case: 107 Moving an order reseats it with its items, an occupied destination is refused
+    #[tokio::test]
+    async fn test_move_order_handler(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        OrderItem::create(&conn, order_id, 1, 6).expect("OrderItems creation failed");
+        let other_order_id = OrderResponse::create(&conn, 3).expect("Order Creation Failed");
+
+        let resp = move_order_handler(reconnect(), 1, 2).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let body = convert_response_to_json(resp).await;
+        assert_eq!((body["id"].as_i64(), body["table_id"].as_i64()), (Some(order_id), Some(2)));
+        assert_eq!(body["menus"].as_array().unwrap().len(), 1);
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 1).unwrap(), None);
+
+        let resp = into_client_response(move_order_handler(reconnect(), 2, 3).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 2).unwrap(), Some(order_id));
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 3).unwrap(), Some(other_order_id));
+
+        let resp = into_client_response(move_order_handler(reconnect(), 1, 2).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let resp = into_client_response(move_order_handler(reconnect(), 2, 99).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+    }
+
+    // Test Case: 108 Deleting a menu that is not on the table's order is NOT FOUND and leaves the order alone
+    #[tokio::test]
+    async fn test_delete_order_item_not_on_table(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        OrderItem::create(&conn, order_id, 1, 6).expect("OrderItems creation failed");
+
+        let resp = into_client_response(delete_order_item_handler(reconnect(), 1, 4, RemoveItemQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        assert_eq!(convert_response_to_json(resp).await, json!({"error": {"code": "not_found", "message": "Item not on this table"}}));
+        let resp = into_client_response(delete_order_item_handler(reconnect(), 2, 1, RemoveItemQuery::default(), RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        assert_eq!(OrderItem::get_item(&conn, 1, 1).unwrap().unwrap().quantity, 1);
+    }
+
+    // Test Case: 109 Ordering more of a menu whose line is already ready is a CONFLICT, other lines still take more
+    #[tokio::test]
+    async fn test_create_order_on_locked_item(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::CREATED);
+        for status in ["cooking", "ready"] {
+            update_item_status_handler(reconnect(), 1, 1, ItemStatusUpdate { status: status.to_string() }).await.unwrap();
+        }
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2, 1], force: true, ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        assert_eq!(OrderItem::get_item(&conn, 1, 1).unwrap().unwrap().quantity, 1);
+        assert_eq!(OrderItem::get_item(&conn, 1, 2).unwrap().unwrap().quantity, 1);
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![2], force: true, ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(OrderItem::get_item(&conn, 1, 2).unwrap().unwrap().quantity, 2);
+    }
+
+    // Test Case: 110 Replacing an order with a ready line is a CONFLICT and keeps every line
+    #[tokio::test]
+    async fn test_replace_order_with_locked_item(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![1, 2], ..Default::default() };
+        create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        for status in ["cooking", "ready"] {
+            update_item_status_handler(reconnect(), 1, 1, ItemStatusUpdate { status: status.to_string() }).await.unwrap();
+        }
+
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![3], on_existing: OnExisting::Replace, ..Default::default() };
+        let resp = into_client_response(create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        assert!(OrderItem::get_item(&conn, 1, 1).unwrap().is_some());
+        assert!(OrderItem::get_item(&conn, 1, 2).unwrap().is_some());
+        assert!(OrderItem::get_item(&conn, 1, 3).unwrap().is_none());
+
+        // Once nothing is locked the order can be replaced
+        update_setting_handler(reconnect(), "lock_items_from".to_string(), SettingUpdate { value: json!("off") }, manager_context()).await.unwrap();
+        let order = OrderRequestBody { table_id: 1, menu_ids: vec![3], on_existing: OnExisting::Replace, ..Default::default() };
+        let resp = create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert!(OrderItem::get_item(&conn, 1, 1).unwrap().is_none());
+    }
+
+    // Test Case: 111 Backup routes are off without a configured token and refuse a missing or wrong one
+    #[tokio::test]
+    async fn test_require_backup_token(){
+        use crate::routes::require_backup_token;
+        let route = |expected: Option<&str>| {
+            warp::path!("backup")
+                .and(require_backup_token(expected.map(String::from)))
+                .map(warp::reply)
+                .recover(crate::routes::handle_rejection)
+        };
+
+        let resp = warp::test::request().path("/backup").header("x-backup-token", "secret").reply(&route(None)).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let resp = warp::test::request().path("/backup").reply(&route(Some("secret"))).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+        let resp = warp::test::request().path("/backup").header("x-backup-token", "secreT").reply(&route(Some("secret"))).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+        let resp = warp::test::request().path("/backup").header("x-backup-token", "secret").reply(&route(Some("secret"))).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+    }
+
+    // Test Case: 112 A discount larger than what is left after removing items brings the amount due to 0, not below
+    #[tokio::test]
+    async fn test_discount_above_remaining_total(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 10 WHERE id IN (1, 2)", []).unwrap();
+        let customer_id = Customer::create(&conn, &Customer { id: 0, name: "Ada".to_string(), phone: None }).unwrap();
+        let order_id = OrderResponse::create(&conn, 1).expect("Order Creation Failed");
+        OrderItem::create(&conn, order_id, 1, 6).expect("OrderItems creation failed");
+        OrderItem::create(&conn, order_id, 2, 6).expect("OrderItems creation failed");
+        OrderResponse::set_customer(&conn, order_id, customer_id).unwrap();
+        OrderResponse::redeem_points(&conn, order_id, customer_id, 0, 1500).unwrap();
+
+        delete_order_item_handler(reconnect(), 1, 2, RemoveItemQuery::default(), RequestContext::default()).await.unwrap();
+        assert_eq!(OrderResponse::due_cents(&conn, order_id).unwrap(), 0);
+        let receipt = Receipt::for_order(&conn, order_id).unwrap().unwrap();
+        assert_eq!((receipt.discount, receipt.total), (10.0, 0.0));
+        let payment = OrderResponse::pay(&conn, order_id).unwrap();
+        assert_eq!((payment.total_cents, payment.points_earned), (0, 0));
+    }
+
+    // Test Case: 113 Duplicate menu names left by an older database are renamed before the name index is created
+    #[tokio::test]
+    async fn test_schema_renames_duplicate_menus(){
+        let conn = setup_test_db();
+        conn.execute("DROP INDEX menus_active_name", []).unwrap();
+        for name in ["Soup", "Soup", "Soup", "Salad"] {
+            conn.execute("INSERT INTO menus (name) VALUES (?1)", [name]).unwrap();
+        }
+
+        crate::db::create_schema(&conn).expect("Schema creation failed");
+        let mut stmt = conn.prepare("SELECT name FROM menus ORDER BY id").unwrap();
+        let names: Vec<String> = stmt.query_map([], |row| row.get(0)).unwrap().map(Result::unwrap).collect();
+        assert_eq!(names, ["Soup", "Soup (2)", "Soup (3)", "Salad"]);
+        assert!(conn.execute("INSERT INTO menus (name) VALUES ('Soup')", []).is_err());
+    }
+
+    // Test Case: 114 Unknown routes and unreadable bodies get the same error shape as handler errors
+    #[tokio::test]
+    async fn test_rejection_error_shape(){
+        let route = warp::post()
+            .and(warp::path!("menus"))
+            .and(warp::body::json::<Menu>())
+            .map(|_| warp::reply())
+            .recover(crate::routes::handle_rejection);
+
+        let resp = warp::test::request().method("POST").path("/nowhere").reply(&route).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::NOT_FOUND);
+        let json_data: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(json_data, json!({"error": {"code": "not_found", "message": "Route not found"}}));
+
+        let resp = warp::test::request().method("POST").path("/menus").body("{").reply(&route).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let json_data: serde_json::Value = serde_json::from_slice(resp.body()).unwrap();
+        assert_eq!(json_data, json!({"error": {"code": "validation", "message": "Failed to deserialize request body"}}));
+
+        // Details sit next to code and message
+        let err = ApiError::Conflict("Some items are unavailable".to_string()).with_details(json!({"unavailable": ["M-01"]}));
+        assert_eq!(err.status(), warp::http::StatusCode::CONFLICT);
+        assert_eq!(err.to_json(), json!({"error": {"code": "conflict", "message": "Some items are unavailable", "unavailable": ["M-01"]}}));
+    }
+
+    // Test Case: 115 Archived menus cannot be added to a running order through a modification
+    #[tokio::test]
+    async fn test_modify_order_rejects_archived_menu(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        assert!(Menu::archive(&conn, 4).unwrap());
+
+        let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [{"op": "add", "menu_id": 4}]})).unwrap();
+        let resp = into_client_response(modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"].as_str(), Some("M-04 is unavailable"));
+        assert!(OrderItem::get_item(&conn, 1, 4).unwrap().is_none());
+    }
+
+    // Test Case: 116 More portions of an 86'd menu already on the order are refused, fewer are still allowed
+    #[tokio::test]
+    async fn test_modify_order_rejects_unavailable_existing_line(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        let order_item_id = OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        OrderItem::set_quantity(&conn, order_item_id, 3).unwrap();
+        assert!(Menu::set_availability(&conn, 1, false).unwrap());
+
+        for operation in [json!({"op": "add", "menu_id": 1}), json!({"op": "set_quantity", "menu_id": 1, "quantity": 4})] {
+            let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [operation]})).unwrap();
+            let resp = into_client_response(modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await).await;
+            assert_eq!(resp.status(), warp::http::StatusCode::BAD_REQUEST);
+            let json_data = convert_response_to_json(resp).await;
+            assert_eq!(json_data["error"]["message"].as_str(), Some("M-01 is unavailable"));
+        }
+        assert_eq!(OrderItem::get_item(&conn, 1, 1).unwrap().unwrap().quantity, 3);
+
+        let data: ModifyOrderRequest = serde_json::from_value(json!({"operations": [{"op": "set_quantity", "menu_id": 1, "quantity": 2}]})).unwrap();
+        let resp = into_client_response(modify_order_handler(reconnect(), 1, data, RequestContext::default(), random_cooking_time()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(OrderItem::get_item(&conn, 1, 1).unwrap().unwrap().quantity, 2);
+    }
+
+    // Test Case: 117 Force-removing the last locked item of an order keeps the item, the order and its audit history together
+    #[tokio::test]
+    async fn test_delete_order_item_handler_forced_last_item(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, order_id, 2, 10).unwrap();
+        OrderItem::set_status(&conn, 1, 2, "ready").unwrap();
+
+        let manager = RequestContext { actor: Some("mia".to_string()), role: Some("manager".to_string()), ..Default::default() };
+        let resp = into_client_response(delete_order_item_handler(reconnect(), 1, 2, RemoveItemQuery { force: true }, manager).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"].as_str(), Some("This order has audit history, void it instead"));
+        // Nothing was written, not even the audit entry of the refused removal
+        assert!(OrderItem::get_item(&conn, 1, 2).unwrap().is_some());
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 1).unwrap(), Some(order_id));
+        assert!(AuditEntry::list_for_order(&conn, order_id).unwrap().is_empty());
+    }
+
+    // Test Case: 118 Renaming a menu without a price keeps its price
+    #[tokio::test]
+    async fn test_update_menu_handler_keeps_price(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 9.5 WHERE id = 1", []).unwrap();
+
+        let data: MenuUpdate = serde_json::from_value(json!({"name": "Margherita"})).unwrap();
+        let resp = update_menu_handler(reconnect(), 1, data).await.unwrap().into_response();
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!((json_data["name"].as_str(), json_data["price"].as_f64()), (Some("Margherita"), Some(9.5)));
+        let price: f64 = conn.query_row("SELECT price FROM menus WHERE id = 1", [], |row| row.get(0)).unwrap();
+        assert_eq!(price, 9.5);
+    }
+
+    // Test Case: 119 Only managers can change settings
+    #[tokio::test]
+    async fn test_update_setting_handler_managers_only(){
+        let conn = setup_test_db();
+        let waiter = RequestContext { role: Some("waiter".to_string()), ..Default::default() };
+        let resp = into_client_response(update_setting_handler(reconnect(), "rush_multiplier".to_string(), SettingUpdate { value: json!(2) }, waiter).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"].as_str(), Some("Only a manager can change settings"));
+        let resp = into_client_response(update_setting_handler(reconnect(), "rush_multiplier".to_string(), SettingUpdate { value: json!(2) }, RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::FORBIDDEN);
+        assert_ne!(Setting::get(&conn, "rush_multiplier").unwrap().as_deref(), Some("2"));
+
+        let resp = into_client_response(update_setting_handler(reconnect(), "rush_multiplier".to_string(), SettingUpdate { value: json!(2) }, manager_context()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::OK);
+        assert_eq!(Setting::get(&conn, "rush_multiplier").unwrap().as_deref(), Some("2"));
+    }
+
+    // Test Case: 120 Setting the last item of an audited order to 0 is refused and keeps the item
+    #[tokio::test]
+    async fn test_set_order_item_quantity_audited_last_item(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        let order_id = OrderResponse::create(&conn, 1).unwrap();
+        OrderItem::create(&conn, order_id, 1, 10).unwrap();
+        AuditEntry::record(&conn, order_id, "comp_item", Some("mia"), Some("M-01")).unwrap();
+
+        let resp = into_client_response(set_order_item_quantity_handler(reconnect(), 1, 1, QuantityUpdate { quantity: 0 }, RequestContext::default()).await).await;
+        assert_eq!(resp.status(), warp::http::StatusCode::CONFLICT);
+        let json_data = convert_response_to_json(resp).await;
+        assert_eq!(json_data["error"]["message"].as_str(), Some("This order has audit history, void it instead"));
+        assert_eq!(OrderItem::get_item(&conn, 1, 1).unwrap().unwrap().quantity, 1);
+        assert_eq!(OrderResponse::get_existing_order_id(&conn, 1).unwrap(), Some(order_id));
+    }
+
+    // Test Case: 121 Repricing menus leaves paid orders as they were charged, running orders follow the new price
+    #[tokio::test]
+    async fn test_adjust_prices_keeps_paid_receipts(){
+        let conn = setup_test_db();
+        setup_static_data(&conn);
+        conn.execute("UPDATE menus SET price = 10.0 WHERE id = 1", []).expect("Price update failed");
+        for table_id in [1, 2] {
+            let order = OrderRequestBody { table_id, menu_ids: vec![1, 1], ..Default::default() };
+            create_order_handler(reconnect(), order, RequestContext::default(), system_clock(), random_cooking_time()).await.unwrap();
+        }
+        let resp = into_client_response(pay_order_handler(reconnect(), 1, PayOrderQuery::default(), RequestContext::default()).await).await;
+        let paid_order_id = convert_response_to_json(resp).await["id"].as_i64().unwrap();
+        let before = Receipt::for_order(&conn, paid_order_id).unwrap().unwrap();
+
+        let adjustment = PriceAdjustment { percent: 50.0, category: None };
+        adjust_prices_handler(reconnect(), adjustment, manager_context()).await.unwrap();
+
+        let after = Receipt::for_order(&conn, paid_order_id).unwrap().unwrap();
+        assert_eq!((after.lines[0].unit_price, after.subtotal, after.total), (10.0, 20.0, before.total));
+        let running_order_id = OrderResponse::get_existing_order_id(&conn, 2).unwrap().unwrap();
+        assert_eq!(OrderResponse::total(&conn, running_order_id).unwrap(), 30.0);
+    }
+}
@@ -0,0 +1,143 @@
+// src/logging.rs
+use std::fmt::{self, Write as _};
+use std::io::Write as _;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+use warp::log::{Info, Log};
+
+/// Levels logged when RUST_LOG is not set
+const DEFAULT_DIRECTIVES: &str = "info";
+
+/// Log filter read from RUST_LOG, e.g. `warn,simple_restaurant_api=debug`. A bare level applies to every target,
+/// `target=level` to targets starting with `target`, the longest matching target wins
+#[derive(Debug, PartialEq)]
+pub(crate) struct Directives {
+    default: LevelFilter,
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Directives {
+
+    /// Directives that cannot be parsed are skipped
+    pub(crate) fn parse(spec: &str) -> Directives {
+        let mut directives = Directives { default: LevelFilter::OFF, targets: Vec::new() };
+        for directive in spec.split(',').map(str::trim).filter(|directive| !directive.is_empty()) {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    if let Ok(level) = LevelFilter::from_str(level.trim()) {
+                        directives.targets.push((target.trim().to_string(), level));
+                    }
+                }
+                None => {
+                    if let Ok(level) = LevelFilter::from_str(directive) {
+                        directives.default = level;
+                    }
+                }
+            }
+        }
+        directives
+    }
+
+    pub(crate) fn enabled(&self, target: &str, level: &tracing::Level) -> bool {
+        let filter = self.targets
+            .iter()
+            .filter(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map_or(self.default, |(_, level)| *level);
+        *level <= filter
+    }
+}
+
+/// Writes every enabled event as one line to stderr, `LEVEL target: message key=value ...`. Spans are not recorded
+struct LineSubscriber {
+    directives: Directives,
+    next_span: AtomicU64,
+}
+
+impl Subscriber for LineSubscriber {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        self.directives.enabled(metadata.target(), metadata.level())
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let metadata = event.metadata();
+        let mut line = LineVisitor::default();
+        event.record(&mut line);
+        let _ = writeln!(std::io::stderr(), "{:>5} {}: {}{}", metadata.level(), metadata.target(), line.message, line.fields);
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[derive(Default)]
+struct LineVisitor {
+    message: String,
+    fields: String,
+}
+
+impl Visit for LineVisitor {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+}
+
+/// Install the stderr logger filtered by RUST_LOG, info and above when it is not set.
+/// Fails when another logger is already installed, which then keeps receiving the logs
+pub fn init() -> Result<(), tracing::subscriber::SetGlobalDefaultError> {
+    let spec = std::env::var("RUST_LOG").unwrap_or_else(|_| DEFAULT_DIRECTIVES.to_string());
+    let subscriber = LineSubscriber { directives: Directives::parse(&spec), next_span: AtomicU64::new(1) };
+    tracing::subscriber::set_global_default(subscriber)
+}
+
+/// Logs method, path, status and elapsed time of every request, with the table_id and menu_id from the path.
+/// Server errors are logged as errors
+pub fn requests() -> Log<impl Fn(Info<'_>) + Copy> {
+    warp::log::custom(|info: Info<'_>| {
+        let (table_id, menu_id) = path_params(info.path());
+        let status = info.status().as_u16();
+        let elapsed_ms = info.elapsed().as_secs_f64() * 1000.0;
+        if info.status().is_server_error() {
+            tracing::error!(method = %info.method(), path = info.path(), status, elapsed_ms, table_id, menu_id, "request failed");
+        } else {
+            tracing::info!(method = %info.method(), path = info.path(), status, elapsed_ms, table_id, menu_id, "request");
+        }
+    })
+}
+
+/// The table_id and menu_id in a path such as /orders/{table_id}/items/{menu_id} or /menus/{menu_id}
+pub(crate) fn path_params(path: &str) -> (Option<i64>, Option<i64>) {
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let id = |index: usize| segments.get(index).and_then(|segment| segment.parse::<i64>().ok());
+    match segments.as_slice() {
+        ["orders" | "tables", _, "items", ..] => (id(1), id(3)),
+        ["orders" | "tables", ..] => (id(1), None),
+        ["menus", ..] => (None, id(1)),
+        _ => (None, None),
+    }
+}
@@ -1,21 +1,41 @@
 // src/main.rs
+// The combined warp filter type of all the routes is deeply nested
+#![recursion_limit = "512"]
 mod models;
 mod handlers;
 mod db;
 mod routes;
-use warp::Filter;
+mod events;
+mod error;
+mod clock;
+mod cooking_time;
+mod slow_queries;
+mod backup;
+mod logging;
+mod server;
+mod config;
 
 #[tokio::main]
 async fn main() {
+    // Log to stderr, filtered by RUST_LOG
+    if let Err(err) = logging::init() {
+        tracing::warn!("Keeping the installed logger: {}", err);
+    }
+
+    // DATABASE_URL, BIND_ADDR and PORT
+    let config = match config::Config::from_env() {
+        Ok(config) => config,
+        Err(err) => {
+            tracing::error!("Invalid configuration: {}", err);
+            std::process::exit(1);
+        }
+    };
+
     // Initialize DB
-    db::initialize_db();
+    db::initialize_db(&config.database_url);
     
-    // Combine all routes
-    let routes = routes::restaurent_routes();
-
-    // Start the warp server
-    println!("Running the server");
-    warp::serve(routes.with(warp::trace::request()))
-        .run(([127, 0, 0, 1], 3030))
-        .await;
+    // Start the warp server, requests in flight get to finish on Ctrl-C or SIGTERM
+    let (addr, server) = server::bind(config.addr(), server::shutdown_signal());
+    tracing::info!("Running the server on {}", addr);
+    server.await;
 }
\ No newline at end of file
@@ -1,6 +1,5 @@
 // src/models.rs
 use rusqlite::params;
-use serde;
 use rusqlite::Connection;
 use serde::{Serialize, Deserialize};
 
@@ -8,7 +7,10 @@ use serde::{Serialize, Deserialize};
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Table {
     #[serde(skip)]
+    #[allow(dead_code)]
     pub id: i64,
+    /// Left out or empty to get the next auto-numbered code
+    #[serde(default)]
     pub code: String,
 }
 
@@ -19,12 +21,47 @@ pub struct TableResponse {
     pub code: String,
 }
 
+/// A table for the host stand, with its running order when it is occupied
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TableStatusResponse {
+    pub id: i64,
+    pub code: String,
+    pub occupied: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order_id: Option<i64>,
+    /// Portions on the running order
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub item_count: Option<i64>,
+}
+
 /// For Creating a Menu from Request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Menu {
     #[serde(skip)]
+    #[allow(dead_code)]
     pub id: i64,
     pub name: String,
+    #[serde(default)]
+    pub price: f64,
+    #[serde(default)]
+    pub category: Option<String>,
+    /// Kitchen station preparing this menu. Menus without one go to DEFAULT_STATION
+    #[serde(default)]
+    pub station: Option<String>,
+    /// Taxed at the tax_rate_<tax_category> setting, tax_rate_default when missing
+    #[serde(default)]
+    pub tax_category: Option<String>,
+    /// What making the menu costs, only used for margins
+    #[serde(default)]
+    pub cost: Option<f64>,
+}
+
+/// For renaming or repricing a Menu from Request. A missing price keeps the current one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuUpdate {
+    pub name: String,
+    #[serde(default)]
+    pub price: Option<f64>,
 }
 
 /// For Menu Response
@@ -32,29 +69,431 @@ pub struct Menu {
 pub struct MenuResponse {
     pub id: i64,
     pub name: String,
+    pub price: f64,
+    pub category: Option<String>,
+    pub station: Option<String>,
+    pub available: bool,
+    pub tax_category: Option<String>,
+    pub active: bool,
 }
 
-/// For Creating a Order from Request
+/// For taking a named snapshot of the menus from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuSnapshotRequest {
+    pub name: String,
+}
+
+/// For switching menus on or off from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AvailabilityUpdate {
+    pub available: bool,
+}
+
+/// For raising or lowering menu prices by a percentage from Request, all menus or only one category
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriceAdjustment {
+    pub percent: f64,
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+/// Query parameters for the menu of the day. Defaults to today
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuOfTheDayQuery {
+    pub date: Option<String>,
+}
+
+/// For scheduling the menu of the day from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuScheduleRequest {
+    pub menu_id: i64,
+}
+
+/// Query parameters for listing menus
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuListQuery {
+    pub sort: Option<String>,
+    /// Archived menus are only listed when this is true
+    #[serde(default)]
+    pub include_inactive: bool,
+}
+
+/// Query parameter for listing the menus of one category
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuCategoryQuery {
+    pub category: String,
+}
+
+/// Query parameters for searching menus by name
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuSearchQuery {
+    #[serde(default)]
+    pub q: String,
+}
+
+/// Query parameters for the paged lists. limit defaults to DEFAULT_PAGE_LIMIT and is capped at MAX_PAGE_LIMIT
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PageQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+pub const DEFAULT_PAGE_LIMIT: i64 = 50;
+pub const MAX_PAGE_LIMIT: i64 = 200;
+
+impl PageQuery {
+    /// (limit, offset) to use in the query. A limit below 1 or a negative offset is an error, not clamped
+    pub fn resolve(&self) -> Result<(i64, i64), String> {
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        let offset = self.offset.unwrap_or(0);
+        if limit < 1 {
+            return Err("limit must be at least 1".to_string());
+        }
+        if offset < 0 {
+            return Err("offset must not be negative".to_string());
+        }
+        Ok((limit.min(MAX_PAGE_LIMIT), offset))
+    }
+}
+
+/// One page of a list with the number of rows in the whole list, for building pagers.
+/// Every list endpoint that pages returns this shape
 #[derive(Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Fields menus can be sorted by. `id` is the order used when nothing is configured
+pub const MENU_SORT_FIELDS: [&str; 4] = ["id", "name", "price", "category"];
+
+/// For Creating a Order from Request
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct OrderRequestBody {
     pub table_id: i64,
+    /// One entry per portion, left out when items is sent
+    #[serde(default)]
     pub menu_ids: Vec<i64>,
+    /// Menus with the quantity of each, used instead of menu_ids when sent
+    #[serde(default)]
+    pub items: Option<Vec<OrderLine>>,
+    #[serde(default)]
+    pub customer_id: Option<i64>,
+    /// Add the menus even when the same ones were just added to this table
+    #[serde(default)]
+    pub force: bool,
+    /// Add the menus even when some are unavailable, managers only
+    #[serde(default)]
+    pub force_unavailable: bool,
+    /// Number of guests at the table, used for the covers report
+    #[serde(default)]
+    pub party_size: Option<i64>,
+    /// What to do when the table already has a running order
+    #[serde(default)]
+    pub on_existing: OnExisting,
+}
+
+/// How creating an order treats a running order on the table
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OnExisting {
+    /// Add the items to the running order
+    #[default]
+    Append,
+    /// Refuse with CONFLICT
+    Reject,
+    /// Take every item off the running order and put the requested ones on it, safe to retry
+    Replace,
+}
+
+/// A menu and how many of it to add to an order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderLine {
+    pub menu_id: i64,
+    pub quantity: u32,
+    /// For the kitchen, e.g. "no onions". Replaces the notes already on the item
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// One change in an atomic order modification
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum OrderOperation {
+    /// One more of the menu, added to the order when it is not on it yet
+    Add { menu_id: i64 },
+    /// One less of the menu, taken off the order at quantity 1
+    Remove { menu_id: i64 },
+    SetQuantity { menu_id: i64, quantity: i64 },
+}
+
+/// For applying several changes to a running Order at once
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModifyOrderRequest {
+    pub operations: Vec<OrderOperation>,
+}
+
+/// For Creating a Customer from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Customer {
+    #[serde(skip)]
+    #[allow(dead_code)]
+    pub id: i64,
+    pub name: String,
+    #[serde(default)]
+    pub phone: Option<String>,
+}
+
+/// For Customer Response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerResponse {
+    pub id: i64,
+    pub name: String,
+    pub phone: Option<String>,
+    pub points: i64,
+}
+
+/// For reseating the running order of one table at another from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MoveOrderRequest {
+    pub from_table_id: i64,
+    pub to_table_id: i64,
+}
+
+/// For attaching a Customer to an Order from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AttachCustomerRequest {
+    pub customer_id: i64,
 }
 
 /// For Order Response
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrderResponse {
     pub id: i64,
+    pub order_number: Option<String>,
     pub table_id: i64,
     pub table_name: String,
     pub total_cooking_time: i32, // Property calculated based on order_items
+    pub status: String,
+    pub void_reason: Option<String>,
+    pub paid_at: Option<String>,
+    pub discount: f64,
+    pub customer: Option<CustomerResponse>,
+    /// Bumped on every item change, the base for /orders/{table_id}/diff
+    pub version: i64,
     pub menus: Vec<OrderItemResponse>, 
 }
 
+/// One change to an order's items. Quantities are None on the side where the item did not exist
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderItemChange {
+    pub version: i64,
+    pub menu_id: i64,
+    pub menu_name: Option<String>,
+    pub quantity_before: Option<i64>,
+    pub quantity_after: Option<i64>,
+    pub item_status: Option<String>,
+}
+
+/// Item changes of an order made after version `since`, oldest first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderDiff {
+    pub order_id: i64,
+    pub since: i64,
+    pub version: i64,
+    pub added: Vec<OrderItemChange>,
+    pub removed: Vec<OrderItemChange>,
+    pub changed: Vec<OrderItemChange>,
+}
+
+/// Outcome of paying an Order
+#[derive(Debug)]
+pub struct Payment {
+    pub total_cents: i64,
+    pub points_earned: i64,
+}
+
+/// For redeeming loyalty points on an Order from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RedeemPointsRequest {
+    pub points: i64,
+}
+
+/// A paid order in a Customer's history
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CustomerOrderResponse {
+    pub order_id: i64,
+    pub order_number: Option<String>,
+    pub table_id: i64,
+    pub total: f64,
+    pub created_at: Option<String>,
+    pub paid_at: Option<String>,
+}
+
+/// An open Order on the floor dashboard. `urgency` is green, amber or red depending on its age
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FloorOrderResponse {
+    pub order_id: i64,
+    pub order_number: Option<String>,
+    pub table_id: i64,
+    pub table_code: String,
+    pub created_at: Option<String>,
+    pub age_minutes: i64,
+    pub urgency: String,
+}
+
+/// Query parameters for looking up an order by number. Defaults to today
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderNumberQuery {
+    pub date: Option<String>,
+}
+
+/// Query parameters for removing an item from a running order
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RemoveItemQuery {
+    /// Remove the item even when it is already ready or served, managers only
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Query parameters for /orders/{table_id}/diff, the order version the client last saw
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderDiffQuery {
+    pub since: i64,
+}
+
+/// Query parameters for paying an order
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PayOrderQuery {
+    /// Required to close an order with nothing to charge, e.g. everything was comped
+    pub comp_reason: Option<String>,
+}
+
+/// For voiding an Order from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VoidOrderRequest {
+    pub reason: String,
+}
+
+/// Printable receipt of an Order. Amounts are computed in cents and returned in currency units
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Receipt {
+    pub order_id: i64,
+    pub order_number: Option<String>,
+    pub table_code: String,
+    pub status: String,
+    pub created_at: Option<String>,
+    pub paid_at: Option<String>,
+    pub lines: Vec<ReceiptLine>,
+    pub subtotal: f64,
+    pub tax: f64,
+    /// Only on receipts where service is included, see the service_included setting
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_charge: Option<f64>,
+    /// Loyalty discounts are taken off after tax
+    pub discount: f64,
+    pub total: f64,
+    /// Tips at the configured tip_percentages, left off when service is included
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tip_suggestions: Vec<TipSuggestion>,
+    /// How many times this receipt was reprinted, including this copy
+    pub reprint_count: i64,
+}
+
+/// Itemized bill of the running Order of a table, the receipt before payment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bill {
+    pub order_id: i64,
+    pub table_code: String,
+    pub lines: Vec<BillLine>,
+    pub subtotal: f64,
+    /// Percent all charged lines are taxed at, null when their tax categories have different rates
+    pub tax_rate: Option<f64>,
+    pub tax_amount: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub service_charge: Option<f64>,
+    pub discount: f64,
+    pub grand_total: f64,
+}
+
+/// One menu on a Bill. Comped lines have a line_total of 0
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BillLine {
+    pub menu_name: String,
+    pub quantity: i64,
+    pub unit_price: f64,
+    pub line_total: f64,
+}
+
+/// Text ticket of an Order for the kitchen printers, see `OrderTicket::to_text`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderTicket {
+    pub order_id: i64,
+    pub order_number: Option<String>,
+    pub table_code: String,
+    pub created_at: Option<String>,
+    pub items: Vec<OrderItemResponse>,
+}
+
+/// A menu on a Receipt
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReceiptLine {
+    pub menu_name: String,
+    pub quantity: i64,
+    pub unit_price: f64,
+    pub amount: f64,
+    /// Percent applied to amount, from the menu's tax category
+    pub tax_rate: f64,
+    pub tax: f64,
+    pub comped: bool,
+}
+
+/// A recorded action on an Order, kept to trace who did what
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub order_id: i64,
+    pub action: String,
+    pub actor: Option<String>,
+    pub note: Option<String>,
+    pub created_at: String,
+}
+
+/// One step in the life of an Order: its creation, every item change, every audited action and its payment
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TimelineEntry {
+    pub action: String,
+    pub actor: Option<String>,
+    /// The menu of item changes, the note of audited actions
+    pub note: Option<String>,
+    pub at: String,
+}
+
+/// Who is making a request, taken from the x-actor and x-role headers,
+/// and the request id from x-request-id (generated when missing)
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub actor: Option<String>,
+    pub role: Option<String>,
+    pub request_id: Option<String>,
+}
+
+/// Role allowed to comp items and override checks
+pub const MANAGER_ROLE: &str = "manager";
+
+impl RequestContext {
+    pub fn is_manager(&self) -> bool {
+        self.role.as_deref() == Some(MANAGER_ROLE)
+    }
+}
+
 /// For OrderItem creation from Request
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OrderItem {
     #[serde(skip)]
+    #[allow(dead_code)]
     pub id: i64,
     pub order_id: i64,
     pub menu_id: i64,
@@ -70,153 +509,1619 @@ pub struct OrderItemResponse{
     pub menu_name: String,
     pub cooking_time: i64,
     pub quantity: i64,
+    pub item_status: String,
+    pub fired_at: Option<String>,
+    pub served_at: Option<String>,
+    pub comped: bool,
+    pub comp_reason: Option<String>,
+    pub notes: Option<String>,
 }
 
-/// Functions for Table Model
-impl Table {
+/// Statuses an order item moves through in the kitchen
+pub const ITEM_STATUSES: [&str; 4] = ["pending", "cooking", "ready", "served"];
 
-    // Function to create the table
-    pub fn create(conn: &rusqlite::Connection, table: &Table) -> rusqlite::Result<i64> {
-        conn.execute(
-            "INSERT INTO tables (code) VALUES (?1)",
-            params![table.code],
-        )?;
-        // Get the last inserted row's ID
-        let last_inserted_id = conn.last_insert_rowid();
-        Ok(last_inserted_id)
+/// Statuses of an order: open, preparing and served while the table is eating, then paid or void.
+/// Spelled by `OrderStatus::as_str`, so the list and the enum cannot drift apart
+pub const ORDER_STATUSES: [&str; OrderStatus::ALL.len()] = {
+    let mut statuses = [""; OrderStatus::ALL.len()];
+    let mut index = 0;
+    while index < statuses.len() {
+        statuses[index] = OrderStatus::ALL[index].as_str();
+        index += 1;
     }
+    statuses
+};
 
-    // Function to list all the tables
-    pub fn list(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<TableResponse>> {
-        let mut stmt = conn.prepare("SELECT * FROM tables")?;
-        let rows = stmt.query_map(params![], |row| {
-            Ok(TableResponse {
-                id: row.get(0)?,
-                code: row.get(1)?,
-            })
-        })?;
+/// Where an order is in its lifecycle, stored as text in orders.status.
+/// It only moves forward open -> preparing -> served -> paid, void is set by voiding the order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OrderStatus {
+    Open,
+    Preparing,
+    Served,
+    Paid,
+    Void,
+}
 
-        Ok(rows.map(|result| result.unwrap()).collect())
-    }
+impl OrderStatus {
+    /// Every status in lifecycle order
+    pub const ALL: [OrderStatus; 5] = [OrderStatus::Open, OrderStatus::Preparing, OrderStatus::Served, OrderStatus::Paid, OrderStatus::Void];
 
-    // Utility Function for Table
-    pub fn get_existing_table_id(conn: &Connection, table: &Table) -> Result<Option<i64>, rusqlite::Error> {
-        let query = "SELECT id FROM tables WHERE code = ?1";
-        let mut stmt = conn.prepare(query)?;
-        let mut rows = stmt.query(params![table.code])?;
-        if let Some(row) = rows.next()? {
-            Ok(Some(row.get(0)?))
-        } else {
-            Ok(None)
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            OrderStatus::Open => "open",
+            OrderStatus::Preparing => "preparing",
+            OrderStatus::Served => "served",
+            OrderStatus::Paid => "paid",
+            OrderStatus::Void => "void",
         }
     }
 
-
+    /// A running order can move to any later status of the lifecycle, never back. Void is not reached this way
+    pub fn can_move_to(&self, next: OrderStatus) -> bool {
+        next > *self && !matches!(self, OrderStatus::Paid | OrderStatus::Void) && next != OrderStatus::Void
+    }
 }
 
-/// Functions for Menu Model
-impl Menu {
-    // Function to create menu item
-    pub fn create(conn: &rusqlite::Connection, menu: &Menu) -> rusqlite::Result<i64> {
-        conn.execute(
-            "INSERT INTO menus (name) VALUES (?1)",
-            params![menu.name],
-        )?;
-        // Get the last inserted row's ID
-        let last_inserted_id = conn.last_insert_rowid();
-        Ok(last_inserted_id)
-    }
+impl std::str::FromStr for OrderStatus {
+    type Err = String;
 
-    // Function to list all the menu items
-    pub fn list(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<MenuResponse>> {
-        let mut stmt = conn.prepare("SELECT * FROM menus")?;
-        let rows = stmt.query_map(params![], |row| {
-            Ok(MenuResponse {
-                id: row.get(0)?,
-                name: row.get(1)?,
-            })
-        })?;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        OrderStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == value)
+            .ok_or_else(|| format!("status must be one of {}", ORDER_STATUSES.join(", ")))
+    }
+}
 
-        Ok(rows.map(|result| result.unwrap()).collect())
+impl rusqlite::types::ToSql for OrderStatus {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        Ok(self.as_str().into())
     }
+}
 
-    // Utility Function for Table
-    pub fn get_existing_menu_id(conn: &Connection, menu: &Menu) -> Result<Option<i64>, rusqlite::Error> {
-        let query = "SELECT id FROM menus WHERE name = ?1";
-        let mut stmt = conn.prepare(query)?;
-        let mut rows = stmt.query(params![menu.name])?;
-        if let Some(row) = rows.next()? {
-            Ok(Some(row.get(0)?))
-        } else {
-            Ok(None)
-        }
+impl rusqlite::types::FromSql for OrderStatus {
+    fn column_result(value: rusqlite::types::ValueRef<'_>) -> rusqlite::types::FromSqlResult<Self> {
+        value.as_str()?.parse().map_err(|err: String| rusqlite::types::FromSqlError::Other(err.into()))
     }
 }
 
-/// Functions for Order Model
-impl OrderResponse {
+/// Estimated wait for a running order and the load on the kitchen, in minutes.
+/// Both are scaled by the rush_multiplier setting
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WaitEstimate {
+    pub order_id: i64,
+    pub estimated_wait_minutes: i64,
+    pub kitchen_load_minutes: i64,
+    pub rush_multiplier: f64,
+}
 
-    /* CRUD Functions for Order Model */
+/// Station that prepares menus which were not given one
+pub const DEFAULT_STATION: &str = "default";
 
-    // Create Function for Order Model
-    pub fn create(conn: &rusqlite::Connection, table_id: i64) -> rusqlite::Result<i64> {
-        conn.execute(
-            "INSERT INTO orders (table_id) VALUES (?1)",
-            params![table_id],
-        )?;
-        // Get the last inserted row's ID
-        let last_inserted_id = conn.last_insert_rowid();
-        Ok(last_inserted_id)
-    }
-    
-    /// List all orders
-    pub fn list(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<OrderResponse>> {
-        let mut stmt = conn.prepare("SELECT orders.id, orders.table_id, t.code FROM orders JOIN tables as t on orders.table_id=t.id")?;
-        let rows = stmt.query_map(params![], |row| {
-            let order_response = OrderResponse {
-                id: row.get(0)?,
-                table_id: row.get(1)?,
-                table_name: row.get(3)?,
-                total_cooking_time: OrderResponse::calculate_total_cooking_time(conn, row.get(0)?)?, // Calculate total_cooking_time
-                menus: OrderItem::list_all_order_items(conn, row.get(0)?)?
-            };
-            Ok(order_response)
-        })?;
+/// An item waiting on or being prepared at a kitchen station
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StationQueueItemResponse {
+    pub order_id: i64,
+    pub order_number: Option<String>,
+    pub table_id: i64,
+    pub table_code: String,
+    pub menu_id: i64,
+    pub menu_name: String,
+    pub quantity: i64,
+    pub item_status: String,
+}
 
-        Ok(rows.map(|result| result.unwrap()).collect())
-    }
+/// For comping an OrderItem from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompItemRequest {
+    pub reason: String,
+}
 
-    /* Utility Functions for Order Model. This block will contain some utility function to call on Order Model */
+/// For setting the quantity of an OrderItem from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct QuantityUpdate {
+    pub quantity: i64,
+}
 
-    /// Get order_id from table_id, check if already there is order running for this table or not
-    pub fn get_existing_order_id(conn: &Connection, table_id: i64) -> Result<Option<i64>, rusqlite::Error> {
-        let query = "SELECT id FROM orders WHERE table_id = ?1";
-        let mut stmt = conn.prepare(query)?;
-        let mut rows = stmt.query(params![table_id])?;
-        if let Some(row) = rows.next()? {
-            Ok(Some(row.get(0)?))
-        } else {
-            Ok(None)
+/// For changing the status of an OrderItem from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ItemStatusUpdate {
+    pub status: String,
+}
+
+/// For moving a running order to another status from Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrderStatusUpdate {
+    pub status: String,
+}
+
+/// For the kitchen "on the pass" view. One row per item that is currently cooking
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PassItemResponse {
+    pub order_id: i64,
+    pub order_number: Option<String>,
+    pub table_id: i64,
+    pub table_code: String,
+    pub menu_id: i64,
+    pub menu_name: String,
+    pub quantity: i64,
+    pub fired_at: String,
+    pub elapsed_minutes: i64,
+}
+
+/// Where an item of a running order is in its preparation, in minutes.
+/// Elapsed runs from fired_at until the item is ready, remaining is what is left of its cooking_time
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrepBreakdownItem {
+    pub menu_id: i64,
+    pub menu_name: String,
+    pub quantity: i64,
+    pub item_status: String,
+    pub cooking_time: i64,
+    pub elapsed_minutes: i64,
+    pub remaining_minutes: i64,
+}
+
+/// An item waiting at the pass to be taken to its table
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadyItemResponse {
+    pub order_id: i64,
+    pub order_number: Option<String>,
+    pub menu_id: i64,
+    pub menu_name: String,
+    pub quantity: i64,
+    pub ready_at: String,
+    pub waiting_minutes: i64,
+}
+
+/// Ready items of one table
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadyTableResponse {
+    pub table_id: i64,
+    pub table_code: String,
+    pub items: Vec<ReadyItemResponse>,
+}
+
+/// A table whose running order still waits for a menu from the kitchen
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AwaitingTable {
+    pub table_id: i64,
+    pub table_code: String,
+    pub order_id: i64,
+}
+
+/// For importing Tables and Menus from a single Request
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportRequest {
+    #[serde(default)]
+    pub tables: Vec<Table>,
+    #[serde(default)]
+    pub menus: Vec<Menu>,
+}
+
+/// Counts returned by an import
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ImportSummary {
+    pub tables_created: i64,
+    pub tables_existing: i64,
+    pub menus_created: i64,
+    pub menus_updated: i64,
+}
+
+/// Result of creating many tables at once, codes that already existed are skipped
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BulkTableSummary {
+    pub created: Vec<i64>,
+    pub skipped: Vec<String>,
+}
+
+/// Tip suggestions for a running order. Amounts are computed in cents and returned in currency units
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TipSuggestions {
+    pub order_id: i64,
+    pub total: f64,
+    pub suggestions: Vec<TipSuggestion>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TipSuggestion {
+    pub percent: i64,
+    pub tip: f64,
+    pub total_with_tip: f64,
+}
+
+/// For Setting Response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+}
+
+/// For updating a Setting from Request. Accepts strings as well as plain numbers and booleans
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingUpdate {
+    pub value: serde_json::Value,
+}
+
+/// Date window used by the report endpoints. Dates are inclusive and formatted as YYYY-MM-DD
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DateRangeQuery {
+    pub from: String,
+    pub to: String,
+}
+
+/// Cooking time window in minutes for /kitchen/items, both ends inclusive
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CookingTimeQuery {
+    pub min: i64,
+    pub max: i64,
+}
+
+/// Trailing window in minutes for /kitchen/throughput, THROUGHPUT_WINDOW_MINUTES when left out
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ThroughputQuery {
+    pub window_minutes: Option<i64>,
+}
+
+pub const THROUGHPUT_WINDOW_MINUTES: i64 = 15;
+
+/// End of day summary. Void orders are left out of every section
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EodReport {
+    pub date: String,
+    /// Net sales: quantity * price minus discounts. Tax and service charge are not revenue, unlike the amounts
+    /// on receipts and payments
+    pub revenue: f64,
+    pub discounts: f64,
+    pub order_count: i64,
+    pub void_count: i64,
+    pub items_sold: i64,
+    pub top_menus: Vec<TopMenu>,
+    pub stations: Vec<StationCount>,
+}
+
+/// Guests served on a day. Orders without a party size count as one cover
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CoversReport {
+    pub date: String,
+    pub order_count: i64,
+    pub covers: i64,
+    /// Orders counted as one cover because no party size was given
+    pub estimated_orders: i64,
+    /// Net sales as in the end of day report, before tax and service charge
+    pub revenue: f64,
+    pub avg_spend_per_cover: f64,
+}
+
+/// How long tables kept their order, from creation to payment, for orders paid on a day
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TurnoverReport {
+    pub date: String,
+    pub paid_orders: i64,
+    pub avg_minutes: f64,
+    pub median_minutes: f64,
+}
+
+/// Price, cost and margin of a menu for GET /menus/margins
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MenuMargin {
+    pub menu_id: i64,
+    pub name: String,
+    pub price: f64,
+    pub cost: f64,
+    /// Share of the price left after the cost, None for free menus
+    pub margin_percent: Option<f64>,
+}
+
+/// A best selling menu in the end of day report
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TopMenu {
+    pub menu_id: i64,
+    pub menu_name: String,
+    pub quantity: i64,
+    /// quantity * price of the menu, before discounts and tax
+    pub revenue: f64,
+}
+
+/// Items prepared by a kitchen station in the end of day report
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StationCount {
+    pub station: String,
+    pub items: i64,
+}
+
+/// Estimated against actual preparation time of a menu, from items that were fired and served
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrepAccuracy {
+    pub menu_id: i64,
+    pub menu_name: String,
+    pub items: i64,
+    pub avg_estimated_minutes: f64,
+    pub avg_actual_minutes: f64,
+    /// Actual minus estimated, positive when the kitchen is slower than estimated
+    pub delta_minutes: f64,
+}
+
+/// For the per table performance report
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TablePerformance {
+    pub table_id: i64,
+    pub table_code: String,
+    pub orders_served: i64,
+    /// Net sales of the table's orders, discounts taken off, tax and service charge left out
+    pub revenue: f64,
+}
+
+/// Functions for Table Model
+/// Longest table code accepted, in characters
+pub const MAX_TABLE_CODE_LEN: usize = 32;
+
+impl Table {
+
+    /// A code must have something besides whitespace and at most MAX_TABLE_CODE_LEN characters
+    pub fn validate(&self) -> Result<(), String> {
+        let code = self.code.trim();
+        if code.is_empty() {
+            return Err("Table code cannot be empty".to_string());
+        }
+        if code.chars().count() > MAX_TABLE_CODE_LEN {
+            return Err(format!("Table code must be at most {} characters", MAX_TABLE_CODE_LEN));
+        }
+        Ok(())
+    }
+
+    // Function to create the table
+    pub fn create(conn: &rusqlite::Connection, table: &Table) -> rusqlite::Result<i64> {
+        conn.execute(
+            "INSERT INTO tables (code) VALUES (?1)",
+            params![table.code],
+        )?;
+        // Get the last inserted row's ID
+        let last_inserted_id = conn.last_insert_rowid();
+        Ok(last_inserted_id)
+    }
+
+    /// Create a table with the next free code of the configured prefix, e.g. T-04 after T-03.
+    /// The number is one past the highest existing one, computed in the INSERT itself so concurrent calls cannot collide
+    pub fn create_next(conn: &rusqlite::Connection) -> rusqlite::Result<TableResponse> {
+        let (prefix, digits) = Setting::table_code_format(conn)?;
+        conn.execute(
+            "INSERT INTO tables (code)
+            SELECT ?1 || printf('%0*d', ?2, COALESCE(MAX(CAST(substr(code, length(?1) + 1) AS INTEGER)), 0) + 1)
+            FROM tables
+            WHERE substr(code, 1, length(?1)) = ?1
+                AND length(code) > length(?1)
+                AND substr(code, length(?1) + 1) NOT GLOB '*[^0-9]*'",
+            params![prefix, digits],
+        )?;
+        let id = conn.last_insert_rowid();
+        let code = conn.query_row("SELECT code FROM tables WHERE id = ?1", params![id], |row| row.get(0))?;
+        Ok(TableResponse { id, code })
+    }
+
+    // Function to list a page of the tables
+    pub fn list(conn: &rusqlite::Connection, limit: i64, offset: i64) -> rusqlite::Result<Vec<TableResponse>> {
+        let mut stmt = conn.prepare("SELECT * FROM tables ORDER BY id LIMIT ?1 OFFSET ?2")?;
+        let rows = stmt.query_map(params![limit, offset], |row| {
+            Ok(TableResponse {
+                id: row.get(0)?,
+                code: row.get(1)?,
+            })
+        })?;
+
+        Ok(rows.map(|result| result.unwrap()).collect())
+    }
+
+    pub fn count(conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+        conn.query_row("SELECT COUNT(*) FROM tables", [], |row| row.get(0))
+    }
+
+    /// Tables that never had an order, paid and voided history included
+    pub fn list_unused(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<TableResponse>> {
+        let mut stmt = conn.prepare(
+            "SELECT t.id, t.code FROM tables as t
+            LEFT JOIN orders as o ON o.table_id = t.id
+            WHERE o.id IS NULL
+            ORDER BY t.id",
+        )?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok(TableResponse {
+                id: row.get(0)?,
+                code: row.get(1)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Every table by code, occupied when it has a running order
+    pub fn list_with_status(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<TableStatusResponse>> {
+        let mut stmt = conn.prepare(
+            "SELECT t.id, t.code, o.id, COALESCE(SUM(oi.quantity), 0)
+            FROM tables as t
+            LEFT JOIN orders as o ON o.table_id = t.id AND o.status NOT IN ('paid', 'void')
+            LEFT JOIN order_items as oi ON oi.order_id = o.id
+            GROUP BY t.id, o.id
+            ORDER BY t.code, t.id",
+        )?;
+        let rows = stmt.query_map(params![], |row| {
+            let order_id: Option<i64> = row.get(2)?;
+            Ok(TableStatusResponse {
+                id: row.get(0)?,
+                code: row.get(1)?,
+                occupied: order_id.is_some(),
+                order_id,
+                item_count: match order_id {
+                    Some(_) => Some(row.get(3)?),
+                    None => None,
+                },
+            })
+        })?;
+        rows.collect()
+    }
+
+    // Utility Function for Table
+    /// Create every table whose code is not taken yet, a code repeated in `tables` is created once.
+    /// Call it inside a transaction so a failure creates nothing
+    pub fn create_bulk(conn: &rusqlite::Connection, tables: &[Table]) -> rusqlite::Result<BulkTableSummary> {
+        let mut summary = BulkTableSummary::default();
+        for table in tables {
+            match Table::get_existing_table_id(conn, table)? {
+                Some(_) => summary.skipped.push(table.code.clone()),
+                None => summary.created.push(Table::create(conn, table)?),
+            }
+        }
+        Ok(summary)
+    }
+
+    pub fn exists(conn: &Connection, table_id: i64) -> rusqlite::Result<bool> {
+        conn.query_row("SELECT EXISTS (SELECT 1 FROM tables WHERE id = ?1)", params![table_id], |row| row.get(0))
+    }
+
+    pub fn get_existing_table_id(conn: &Connection, table: &Table) -> Result<Option<i64>, rusqlite::Error> {
+        let query = "SELECT id FROM tables WHERE code = ?1";
+        let mut stmt = conn.prepare(query)?;
+        let mut rows = stmt.query(params![table.code])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+
+}
+
+/// Columns selected for a MenuResponse, in the order `MenuResponse::from_row` reads them
+const MENU_COLUMNS: &str = "id, name, price, category, station, available, tax_category, active";
+
+impl MenuResponse {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<MenuResponse> {
+        Ok(MenuResponse {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            price: row.get(2)?,
+            category: row.get(3)?,
+            station: row.get(4)?,
+            available: row.get(5)?,
+            tax_category: row.get(6)?,
+            active: row.get(7)?,
+        })
+    }
+}
+
+/// Functions for Menu Model
+impl Menu {
+    // Function to create menu item
+    pub fn create(conn: &rusqlite::Connection, menu: &Menu) -> rusqlite::Result<i64> {
+        conn.execute(
+            "INSERT INTO menus (name, price, category, station, tax_category, cost_cents) VALUES (?1, ?2, ?3, ?4, ?5, CAST(ROUND(?6 * 100) AS INTEGER))",
+            params![menu.name, menu.price, menu.category, menu.station, menu.tax_category, menu.cost],
+        )?;
+        // Get the last inserted row's ID
+        let last_inserted_id = conn.last_insert_rowid();
+        Ok(last_inserted_id)
+    }
+
+    // Function to list a page of the menu items, sorted by one of MENU_SORT_FIELDS. Archived menus are left out
+    // unless include_inactive is set
+    pub fn list(conn: &rusqlite::Connection, sort: &str, include_inactive: bool, limit: i64, offset: i64) -> rusqlite::Result<Vec<MenuResponse>> {
+        // Only whitelisted columns ever reach the ORDER BY clause
+        let order_by = match sort {
+            "name" => "name, id",
+            "price" => "price, id",
+            "category" => "category, name, id",
+            _ => "id",
+        };
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM menus WHERE ?3 OR active = 1 ORDER BY {} LIMIT ?1 OFFSET ?2", MENU_COLUMNS, order_by))?;
+        let rows = stmt.query_map(params![limit, offset, include_inactive], MenuResponse::from_row)?;
+
+        Ok(rows.map(|result| result.unwrap()).collect())
+    }
+
+    pub fn count(conn: &rusqlite::Connection, include_inactive: bool) -> rusqlite::Result<i64> {
+        conn.query_row("SELECT COUNT(*) FROM menus WHERE ?1 OR active = 1", params![include_inactive], |row| row.get(0))
+    }
+
+    /// Take a menu off the menu for good without deleting it, past order items keep pointing at it.
+    /// False when there is no such menu
+    pub fn archive(conn: &rusqlite::Connection, menu_id: i64) -> rusqlite::Result<bool> {
+        let updated = conn.execute("UPDATE menus SET active = 0 WHERE id = ?1", params![menu_id])?;
+        Ok(updated > 0)
+    }
+
+    /// Active menus whose name contains `query`, ignoring ASCII case. % and _ in the query match only themselves
+    pub fn search(conn: &rusqlite::Connection, query: &str) -> rusqlite::Result<Vec<MenuResponse>> {
+        let escaped = query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM menus WHERE active = 1 AND name LIKE '%' || ?1 || '%' ESCAPE '\\' ORDER BY name, id", MENU_COLUMNS))?;
+        let rows = stmt.query_map(params![escaped], MenuResponse::from_row)?;
+        rows.collect()
+    }
+
+    /// Active menus whose category is exactly `category`, by name
+    pub fn list_by_category(conn: &rusqlite::Connection, category: &str) -> rusqlite::Result<Vec<MenuResponse>> {
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM menus WHERE category = ?1 AND active = 1 ORDER BY name, id", MENU_COLUMNS))?;
+        let rows = stmt.query_map(params![category], MenuResponse::from_row)?;
+        rows.collect()
+    }
+
+    /// Every category used by an active menu, in alphabetical order. Menus without a category are left out
+    pub fn categories(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<String>> {
+        let mut stmt = conn.prepare("SELECT DISTINCT category FROM menus WHERE category IS NOT NULL AND active = 1 ORDER BY category")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect()
+    }
+
+    // Function to get a single menu item
+    pub fn get(conn: &rusqlite::Connection, menu_id: i64) -> rusqlite::Result<Option<MenuResponse>> {
+        let query = format!("SELECT {} FROM menus WHERE id = ?1", MENU_COLUMNS);
+        let result = conn.query_row(&query, params![menu_id], MenuResponse::from_row);
+        match result {
+            Ok(menu) => Ok(Some(menu)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Menu featured on `date` (YYYY-MM-DD). A menu scheduled for the date wins, otherwise the
+    /// available menus take turns by day so every date always gets the same pick
+    pub fn of_the_day(conn: &rusqlite::Connection, date: &str) -> rusqlite::Result<Option<MenuResponse>> {
+        let query = format!("
+        SELECT {} FROM menus
+        WHERE id = COALESCE(
+            (SELECT menu_id FROM menu_schedule WHERE date = date(?1)),
+            (SELECT id FROM menus WHERE available = 1 AND active = 1 ORDER BY id
+                LIMIT 1 OFFSET CAST(julianday(date(?1)) AS INTEGER) % MAX((SELECT COUNT(*) FROM menus WHERE available = 1 AND active = 1), 1))
+        )", MENU_COLUMNS);
+        let result = conn.query_row(&query, params![date], MenuResponse::from_row);
+        match result {
+            Ok(menu) => Ok(Some(menu)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Feature a menu on `date` (YYYY-MM-DD) instead of the daily rotation
+    pub fn schedule_of_the_day(conn: &rusqlite::Connection, date: &str, menu_id: i64) -> rusqlite::Result<()> {
+        conn.execute(
+            "INSERT INTO menu_schedule (date, menu_id) VALUES (date(?1), ?2) ON CONFLICT(date) DO UPDATE SET menu_id = excluded.menu_id",
+            params![date, menu_id],
+        )?;
+        Ok(())
+    }
+
+    /// Copy every menu (name, price, category, station, availability, tax category) into the snapshot `name`.
+    /// Fails with a UNIQUE violation when the snapshot already exists
+    pub fn snapshot(conn: &rusqlite::Connection, name: &str) -> rusqlite::Result<usize> {
+        conn.execute(
+            "INSERT INTO menu_snapshots (name, menu_id, menu_name, price, category, station, available, tax_category, created_at)
+            SELECT ?1, id, name, price, category, station, available, tax_category, datetime('now') FROM menus",
+            params![name],
+        )
+    }
+
+    /// Put the menus back as they were in the snapshot `name`. Menus created after it are made unavailable.
+    /// Returns the number of menus restored, None when there is no such snapshot
+    pub fn restore_snapshot(conn: &rusqlite::Connection, name: &str) -> rusqlite::Result<Option<usize>> {
+        let count: i64 = conn.query_row("SELECT COUNT(*) FROM menu_snapshots WHERE name = ?1", params![name], |row| row.get(0))?;
+        if count == 0 {
+            return Ok(None);
+        }
+        let restored = conn.execute(
+            "UPDATE menus
+            SET name = s.menu_name, price = s.price, category = s.category, station = s.station,
+                available = s.available, tax_category = s.tax_category
+            FROM menu_snapshots as s
+            WHERE s.name = ?1 AND s.menu_id = menus.id",
+            params![name],
+        )?;
+        conn.execute(
+            "UPDATE menus SET available = 0 WHERE id NOT IN (SELECT menu_id FROM menu_snapshots WHERE name = ?1)",
+            params![name],
+        )?;
+        Ok(Some(restored))
+    }
+
+    /// Fold menu `remove_id` into `keep_id`: its order items move over, adding their quantity to the kept
+    /// menu's item when an order has both, then the menu is deleted. None when either menu does not exist
+    pub fn merge(conn: &rusqlite::Connection, keep_id: i64, remove_id: i64) -> rusqlite::Result<Option<usize>> {
+        if Menu::get(conn, keep_id)?.is_none() || Menu::get(conn, remove_id)?.is_none() {
+            return Ok(None);
+        }
+        let combined = conn.execute(
+            "UPDATE order_items
+            SET quantity = quantity + (SELECT SUM(r.quantity) FROM order_items as r WHERE r.order_id = order_items.order_id AND r.menu_id = ?2)
+            WHERE id IN (SELECT MIN(id) FROM order_items WHERE menu_id = ?1 GROUP BY order_id)
+                AND order_id IN (SELECT order_id FROM order_items WHERE menu_id = ?2)",
+            params![keep_id, remove_id],
+        )?;
+        conn.execute(
+            "DELETE FROM order_items WHERE menu_id = ?2 AND order_id IN (SELECT order_id FROM order_items WHERE menu_id = ?1)",
+            params![keep_id, remove_id],
+        )?;
+        let moved = conn.execute("UPDATE order_items SET menu_id = ?1 WHERE menu_id = ?2", params![keep_id, remove_id])?;
+        conn.execute("UPDATE menu_schedule SET menu_id = ?1 WHERE menu_id = ?2", params![keep_id, remove_id])?;
+        conn.execute("UPDATE OR IGNORE recent_adds SET menu_id = ?1 WHERE menu_id = ?2", params![keep_id, remove_id])?;
+        conn.execute("DELETE FROM recent_adds WHERE menu_id = ?1", params![remove_id])?;
+        conn.execute("DELETE FROM menu_snapshots WHERE menu_id = ?1", params![remove_id])?;
+        conn.execute("DELETE FROM menus WHERE id = ?1", params![remove_id])?;
+        Ok(Some(combined + moved))
+    }
+
+    /// Menus with a cost, highest margin first. Free menus have no margin and come last
+    pub fn margins(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<MenuMargin>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, name, price_cents, cost_cents,
+                ROUND((price_cents - cost_cents) * 100.0 / NULLIF(price_cents, 0), 2) AS margin_percent
+            FROM (SELECT id, name, CAST(ROUND(price * 100) AS INTEGER) AS price_cents, cost_cents FROM menus WHERE cost_cents IS NOT NULL)
+            ORDER BY margin_percent IS NULL, margin_percent DESC, id",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok(MenuMargin {
+                menu_id: row.get(0)?,
+                name: row.get(1)?,
+                price: to_amount(row.get(2)?),
+                cost: to_amount(row.get(3)?),
+                margin_percent: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Rename a menu, and reprice it when a price is given. Returns false when there is no such menu
+    pub fn update(conn: &rusqlite::Connection, menu_id: i64, menu: &MenuUpdate) -> rusqlite::Result<bool> {
+        let updated = conn.execute(
+            "UPDATE menus SET name = ?1, price = COALESCE(?2, price) WHERE id = ?3",
+            params![menu.name, menu.price, menu_id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    // Function to overwrite the price and category of an existing menu
+    pub fn update_price_and_category(conn: &rusqlite::Connection, menu_id: i64, menu: &Menu) -> rusqlite::Result<bool> {
+        let updated = conn.execute(
+            "UPDATE menus SET price = ?1, category = ?2 WHERE id = ?3",
+            params![menu.price, menu.category, menu_id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Multiply prices by (100 + percent) / 100, rounded to cents. Returns how many menus were repriced
+    pub fn adjust_prices(conn: &rusqlite::Connection, percent: f64, category: Option<&str>) -> rusqlite::Result<usize> {
+        conn.execute(
+            "UPDATE menus SET price = ROUND(price * (100 + ?1) / 100, 2) WHERE ?2 IS NULL OR category = ?2",
+            params![percent, category],
+        )
+    }
+
+    /// Switch all menus of a category on or off. Returns how many menus actually changed
+    pub fn set_category_availability(conn: &rusqlite::Connection, category: &str, available: bool) -> rusqlite::Result<usize> {
+        conn.execute(
+            "UPDATE menus SET available = ?2 WHERE category = ?1 AND available != ?2",
+            params![category, available],
+        )
+    }
+
+    /// Switch one menu on or off. Returns false when there is no such menu
+    pub fn set_availability(conn: &rusqlite::Connection, menu_id: i64, available: bool) -> rusqlite::Result<bool> {
+        let updated = conn.execute("UPDATE menus SET available = ?2 WHERE id = ?1", params![menu_id, available])?;
+        Ok(updated > 0)
+    }
+
+    /// Tables with the menu pending or cooking on their running order, by table
+    pub fn awaiting_tables(conn: &rusqlite::Connection, menu_id: i64) -> rusqlite::Result<Vec<AwaitingTable>> {
+        let mut stmt = conn.prepare(
+            "SELECT DISTINCT o.table_id, t.code, o.id
+            FROM order_items as oi
+            JOIN orders as o ON o.id = oi.order_id
+            JOIN tables as t ON t.id = o.table_id
+            WHERE oi.menu_id = ?1 AND oi.item_status IN ('pending', 'cooking') AND o.status NOT IN ('paid', 'void')
+            ORDER BY o.table_id",
+        )?;
+        let rows = stmt.query_map(params![menu_id], |row| {
+            Ok(AwaitingTable {
+                table_id: row.get(0)?,
+                table_code: row.get(1)?,
+                order_id: row.get(2)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Names of the given menus that are currently unavailable or archived
+    pub fn unavailable_names(conn: &rusqlite::Connection, menu_ids: &[i64]) -> rusqlite::Result<Vec<String>> {
+        let mut stmt = conn.prepare("SELECT name FROM menus WHERE id = ?1 AND (available = 0 OR active = 0)")?;
+        let mut names = Vec::new();
+        for menu_id in menu_ids {
+            match stmt.query_row(params![menu_id], |row| row.get::<_, String>(0)) {
+                Ok(name) => {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(names)
+    }
+
+    // Utility Function for Table
+    pub fn get_existing_menu_id(conn: &Connection, menu: &Menu) -> Result<Option<i64>, rusqlite::Error> {
+        let query = "SELECT id FROM menus WHERE name = ?1 AND active = 1";
+        let mut stmt = conn.prepare(query)?;
+        let mut rows = stmt.query(params![menu.name])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Unit price of an order item in cents: the price stored when its order was paid or voided, the menu price while
+/// the order is running. Queries using it must alias order_items as `oi` and menus as `m`
+const UNIT_CENTS: &str = "COALESCE(oi.unit_price_cents, CAST(ROUND(m.price * 100) AS INTEGER))";
+
+/// Charged amount of an order item in cents, UNIT_CENTS per portion. Comped items are free.
+/// Queries using it must alias order_items as `oi` and menus as `m`
+const LINE_CENTS: &str = "CASE WHEN oi.comped = 0 THEN oi.quantity * COALESCE(oi.unit_price_cents, CAST(ROUND(m.price * 100) AS INTEGER)) ELSE 0 END";
+
+/// Tax rate (percent) of an order item's menu, 0 when its tax category has no rate configured.
+/// Queries using it must alias menus as `m` and add TAX_RATE_JOIN
+const TAX_RATE: &str = "COALESCE(CAST(tax.value AS REAL), 0)";
+const TAX_RATE_JOIN: &str = "LEFT JOIN settings as tax ON tax.key = 'tax_rate_' || COALESCE(m.tax_category, 'default')";
+
+/// Tax on LINE_CENTS in cents, rounded per line
+fn line_tax_cents() -> String {
+    format!("CAST(ROUND(({}) * {} / 100) AS INTEGER)", LINE_CENTS, TAX_RATE)
+}
+
+/// Columns selected for an OrderResponse, in the order `OrderResponse::from_row` reads them.
+/// Queries using it must join tables as `t`
+const ORDER_COLUMNS: &str = "orders.id, orders.table_id, t.code, orders.status, orders.void_reason, orders.order_number, orders.customer_id, orders.paid_at, orders.discount_cents, orders.version";
+
+/// Functions for Customer Model
+impl Customer {
+
+    // Function to create a customer
+    pub fn create(conn: &rusqlite::Connection, customer: &Customer) -> rusqlite::Result<i64> {
+        conn.execute(
+            "INSERT INTO customers (name, phone) VALUES (?1, ?2)",
+            params![customer.name, customer.phone],
+        )?;
+        // Get the last inserted row's ID
+        let last_inserted_id = conn.last_insert_rowid();
+        Ok(last_inserted_id)
+    }
+
+    // Function to get a single customer
+    pub fn get(conn: &rusqlite::Connection, customer_id: i64) -> rusqlite::Result<Option<CustomerResponse>> {
+        let result = conn.query_row(
+            "SELECT id, name, phone, points FROM customers WHERE id = ?1",
+            params![customer_id],
+            |row| {
+                Ok(CustomerResponse {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    phone: row.get(2)?,
+                    points: row.get(3)?,
+                })
+            },
+        );
+        match result {
+            Ok(customer) => Ok(Some(customer)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Paid orders of a customer with their totals, newest first
+    pub fn history(conn: &rusqlite::Connection, customer_id: i64) -> rusqlite::Result<Vec<CustomerOrderResponse>> {
+        let query = "
+        SELECT o.id, o.order_number, o.table_id, o.created_at, o.paid_at
+        FROM orders as o
+        WHERE o.customer_id = ?1 AND o.status = 'paid'
+        ORDER BY o.paid_at DESC, o.id DESC";
+        let mut stmt = conn.prepare(query)?;
+        let rows = stmt.query_map(params![customer_id], |row| {
+            let order_id: i64 = row.get(0)?;
+            Ok(CustomerOrderResponse {
+                order_id,
+                order_number: row.get(1)?,
+                table_id: row.get(2)?,
+                total: to_amount(OrderResponse::due_cents(conn, order_id)?),
+                created_at: row.get(3)?,
+                paid_at: row.get(4)?,
+            })
+        })?;
+        rows.collect()
+    }
+}
+
+impl WaitEstimate {
+
+    /// Cooking time still ahead for the order's items and for every item in the kitchen.
+    /// Items count until they are ready. The stored cooking_time is never changed by the multiplier
+    pub fn for_order(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<WaitEstimate> {
+        let (order_minutes, kitchen_minutes): (i64, i64) = conn.query_row(
+            "SELECT COALESCE(SUM(oi.cooking_time) FILTER (WHERE oi.order_id = ?1), 0), COALESCE(SUM(oi.cooking_time), 0)
+            FROM order_items as oi
+            JOIN orders as o ON o.id = oi.order_id
+            WHERE oi.item_status IN ('pending', 'cooking') AND o.status != 'void'",
+            params![order_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let rush_multiplier = Setting::rush_multiplier(conn)?;
+        Ok(WaitEstimate {
+            order_id,
+            estimated_wait_minutes: (order_minutes as f64 * rush_multiplier).round() as i64,
+            kitchen_load_minutes: (kitchen_minutes as f64 * rush_multiplier).round() as i64,
+            rush_multiplier,
+        })
+    }
+}
+
+/// How many best selling menus the end of day report lists
+const EOD_TOP_MENUS: i64 = 5;
+
+impl TurnoverReport {
+
+    /// Average and median minutes between creation and payment of the orders paid on `date` (YYYY-MM-DD)
+    pub fn for_date(conn: &rusqlite::Connection, date: &str) -> rusqlite::Result<TurnoverReport> {
+        let mut stmt = conn.prepare(
+            "SELECT (julianday(paid_at) - julianday(created_at)) * 1440
+            FROM orders
+            WHERE status = 'paid' AND created_at IS NOT NULL AND date(paid_at) = date(?1)
+            ORDER BY 1",
+        )?;
+        let minutes = stmt.query_map(params![date], |row| row.get::<_, f64>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let count = minutes.len();
+        let (avg_minutes, median_minutes) = if count == 0 {
+            (0.0, 0.0)
+        } else {
+            let median = if count % 2 == 1 {
+                minutes[count / 2]
+            } else {
+                (minutes[count / 2 - 1] + minutes[count / 2]) / 2.0
+            };
+            (minutes.iter().sum::<f64>() / count as f64, median)
+        };
+        Ok(TurnoverReport {
+            date: date.to_string(),
+            paid_orders: count as i64,
+            avg_minutes: round_tenth(avg_minutes),
+            median_minutes: round_tenth(median_minutes),
+        })
+    }
+}
+
+impl CoversReport {
+
+    /// Covers and average spend per cover for non void orders created on `date` (YYYY-MM-DD)
+    pub fn for_date(conn: &rusqlite::Connection, date: &str) -> rusqlite::Result<CoversReport> {
+        let (order_count, covers, estimated_orders, discount_cents): (i64, i64, i64, i64) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(COALESCE(party_size, 1)), 0), COUNT(*) FILTER (WHERE party_size IS NULL),
+                COALESCE(SUM(discount_cents), 0)
+            FROM orders WHERE status != 'void' AND date(created_at) = date(?1)",
+            params![date],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )?;
+        let sales_cents: i64 = conn.query_row(
+            &format!("SELECT COALESCE(SUM({}), 0)
+            FROM order_items as oi
+            JOIN orders as o ON o.id = oi.order_id
+            JOIN menus as m ON m.id = oi.menu_id
+            WHERE o.status != 'void' AND date(o.created_at) = date(?1)", LINE_CENTS),
+            params![date],
+            |row| row.get(0),
+        )?;
+        let revenue_cents = sales_cents - discount_cents;
+        let avg_spend_per_cover = if covers > 0 {
+            to_amount((revenue_cents as f64 / covers as f64).round() as i64)
+        } else {
+            0.0
+        };
+        Ok(CoversReport {
+            date: date.to_string(),
+            order_count,
+            covers,
+            estimated_orders,
+            revenue: to_amount(revenue_cents),
+            avg_spend_per_cover,
+        })
+    }
+}
+
+impl EodReport {
+
+    /// Build the end of day report for orders created on `date` (YYYY-MM-DD)
+    pub fn for_date(conn: &rusqlite::Connection, date: &str) -> rusqlite::Result<EodReport> {
+        let (order_count, void_count, discount_cents): (i64, i64, i64) = conn.query_row(
+            "SELECT COUNT(*) FILTER (WHERE status != 'void'), COUNT(*) FILTER (WHERE status = 'void'),
+                COALESCE(SUM(discount_cents) FILTER (WHERE status != 'void'), 0)
+            FROM orders WHERE date(created_at) = date(?1)",
+            params![date],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let (items_sold, sales_cents): (i64, i64) = conn.query_row(
+            &format!("SELECT COALESCE(SUM(oi.quantity), 0), COALESCE(SUM({}), 0)
+            FROM order_items as oi
+            JOIN orders as o ON o.id = oi.order_id
+            JOIN menus as m ON m.id = oi.menu_id
+            WHERE o.status != 'void' AND date(o.created_at) = date(?1)", LINE_CENTS),
+            params![date],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut stmt = conn.prepare(&format!(
+            "SELECT m.id, m.name, SUM(oi.quantity), SUM({})
+            FROM order_items as oi
+            JOIN orders as o ON o.id = oi.order_id
+            JOIN menus as m ON m.id = oi.menu_id
+            WHERE o.status != 'void' AND date(o.created_at) = date(?1)
+            GROUP BY m.id, m.name
+            ORDER BY SUM(oi.quantity) DESC, m.id
+            LIMIT ?2",
+            LINE_CENTS
+        ))?;
+        let top_menus = stmt.query_map(params![date, EOD_TOP_MENUS], |row| {
+            Ok(TopMenu {
+                menu_id: row.get(0)?,
+                menu_name: row.get(1)?,
+                quantity: row.get(2)?,
+                revenue: to_amount(row.get(3)?),
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut stmt = conn.prepare(
+            "SELECT COALESCE(m.station, ?2), SUM(oi.quantity)
+            FROM order_items as oi
+            JOIN orders as o ON o.id = oi.order_id
+            JOIN menus as m ON m.id = oi.menu_id
+            WHERE o.status != 'void' AND date(o.created_at) = date(?1)
+            GROUP BY COALESCE(m.station, ?2)
+            ORDER BY COALESCE(m.station, ?2)",
+        )?;
+        let stations = stmt.query_map(params![date, DEFAULT_STATION], |row| {
+            Ok(StationCount {
+                station: row.get(0)?,
+                items: row.get(1)?,
+            })
+        })?.collect::<Result<Vec<_>, _>>()?;
+
+        Ok(EodReport {
+            date: date.to_string(),
+            revenue: to_amount(sales_cents - discount_cents),
+            discounts: to_amount(discount_cents),
+            order_count,
+            void_count,
+            items_sold,
+            top_menus,
+            stations,
+        })
+    }
+}
+
+/// Functions for Order Model
+impl OrderResponse {
+
+    /* CRUD Functions for Order Model */
+
+    // Create Function for Order Model
+    pub fn create(conn: &rusqlite::Connection, table_id: i64) -> rusqlite::Result<i64> {
+        let now: String = conn.query_row("SELECT datetime('now')", [], |row| row.get(0))?;
+        OrderResponse::create_at(conn, table_id, &now)
+    }
+
+    /// Create an order placed at `created_at`, giving it the next order number of that day (#001, #002, ...).
+    /// The number is computed inside the INSERT so two orders created at once cannot share it
+    pub fn create_at(conn: &rusqlite::Connection, table_id: i64, created_at: &str) -> rusqlite::Result<i64> {
+        let (prefix, digits) = Setting::order_number_format(conn)?;
+        // One past the highest number of the day, counting would hand out a number again once an order is deleted.
+        // Numbers under another prefix (the setting changed during the day) cannot clash and start over
+        conn.execute(
+            "INSERT INTO orders (table_id, created_at, order_number)
+            VALUES (?1, ?2, printf('%s%0*d', ?3, ?4, (
+                SELECT COALESCE(MAX(CAST(substr(order_number, length(?3) + 1) AS INTEGER)), 0) + 1
+                FROM orders
+                WHERE date(created_at) = date(?2) AND substr(order_number, 1, length(?3)) = ?3
+            )))",
+            params![table_id, created_at, prefix, digits],
+        )?;
+        // Get the last inserted row's ID
+        let last_inserted_id = conn.last_insert_rowid();
+        Ok(last_inserted_id)
+    }
+    
+    /// List a page of the orders oldest first, including paid and voided ones kept for history
+    pub fn list(conn: &rusqlite::Connection, limit: i64, offset: i64) -> rusqlite::Result<Vec<OrderResponse>> {
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM orders JOIN tables as t on orders.table_id=t.id ORDER BY orders.id LIMIT ?1 OFFSET ?2", ORDER_COLUMNS))?;
+        let rows = stmt.query_map(params![limit, offset], |row| OrderResponse::from_row(conn, row))?;
+
+        Ok(rows.map(|result| result.unwrap()).collect())
+    }
+
+    pub fn count(conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+        conn.query_row("SELECT COUNT(*) FROM orders", [], |row| row.get(0))
+    }
+
+    /// Get a single order with its items, whatever its status
+    pub fn get(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<Option<OrderResponse>> {
+        let query = format!("SELECT {} FROM orders JOIN tables as t on orders.table_id=t.id WHERE orders.id = ?1", ORDER_COLUMNS);
+        let result = conn.query_row(&query, params![order_id], |row| OrderResponse::from_row(conn, row));
+        match result {
+            Ok(order) => Ok(Some(order)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Find an order by the number printed on its ticket. Numbers restart every day, so the lookup is scoped to the
+    /// day (YYYY-MM-DD) the order was created. The number can be given with or without its prefix
+    pub fn get_by_number(conn: &rusqlite::Connection, order_number: &str, date: &str) -> rusqlite::Result<Option<OrderResponse>> {
+        let (prefix, _) = Setting::order_number_format(conn)?;
+        let query = "SELECT id FROM orders WHERE (order_number = ?1 OR order_number = ?2 || ?1) AND date(created_at) = date(?3)";
+        let result = conn.query_row(query, params![order_number, prefix, date], |row| row.get(0));
+        match result {
+            Ok(order_id) => OrderResponse::get(conn, order_id),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn from_row(conn: &rusqlite::Connection, row: &rusqlite::Row) -> rusqlite::Result<OrderResponse> {
+        let order_id: i64 = row.get(0)?;
+        Ok(OrderResponse {
+            id: order_id,
+            order_number: row.get(5)?,
+            table_id: row.get(1)?,
+            table_name: row.get(2)?,
+            total_cooking_time: OrderResponse::calculate_total_cooking_time(conn, order_id)?, // Calculate total_cooking_time
+            status: row.get(3)?,
+            void_reason: row.get(4)?,
+            paid_at: row.get(7)?,
+            discount: to_amount(row.get(8)?),
+            customer: match row.get(6)? {
+                Some(customer_id) => Customer::get(conn, customer_id)?,
+                None => None,
+            },
+            version: row.get(9)?,
+            menus: OrderItem::list_all_order_items(conn, order_id)?
+        })
+    }
+
+    /* Utility Functions for Order Model. This block will contain some utility function to call on Order Model */
+
+    /// Get order_id from table_id, check if already there is order running for this table or not
+    pub fn get_existing_order_id(conn: &Connection, table_id: i64) -> Result<Option<i64>, rusqlite::Error> {
+        let query = "SELECT id FROM orders WHERE table_id = ?1 AND status NOT IN ('paid', 'void')";
+        let mut stmt = conn.prepare(query)?;
+        let mut rows = stmt.query(params![table_id])?;
+        if let Some(row) = rows.next()? {
+            Ok(Some(row.get(0)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Put an order on another table, the caller checks that the table is free
+    pub fn move_to_table(conn: &Connection, order_id: i64, table_id: i64) -> rusqlite::Result<bool> {
+        let updated = conn.execute("UPDATE orders SET table_id = ?2 WHERE id = ?1", params![order_id, table_id])?;
+        Ok(updated > 0)
+    }
+
+    /// Calculate the total cooking time dynamically from current order_items
+    pub fn calculate_total_cooking_time(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<i32> {
+        let query = "
+        SELECT COALESCE(SUM(oi.cooking_time), 0)
+        FROM orders
+        JOIN order_items oi ON oi.order_id = orders.id
+        WHERE orders.id = ?1
+    ";
+
+        conn.query_row(query, params![order_id], |row| row.get(0))
+    }
+
+    /// Attach a customer to an order, replacing any previous one
+    pub fn set_customer(conn: &rusqlite::Connection, order_id: i64, customer_id: i64) -> rusqlite::Result<bool> {
+        let updated = conn.execute(
+            "UPDATE orders SET customer_id = ?2 WHERE id = ?1",
+            params![order_id, customer_id],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn set_party_size(conn: &rusqlite::Connection, order_id: i64, party_size: i64) -> rusqlite::Result<bool> {
+        let updated = conn.execute(
+            "UPDATE orders SET party_size = ?2 WHERE id = ?1",
+            params![order_id, party_size],
+        )?;
+        Ok(updated > 0)
+    }
+
+    pub fn status(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<OrderStatus> {
+        conn.query_row("SELECT status FROM orders WHERE id = ?1", params![order_id], |row| row.get(0))
+    }
+
+    /// Move an order to another status as is, use `pay` and `void` for paying and voiding it
+    pub fn set_status(conn: &rusqlite::Connection, order_id: i64, status: OrderStatus) -> rusqlite::Result<bool> {
+        let updated = conn.execute("UPDATE orders SET status = ?2 WHERE id = ?1", params![order_id, status])?;
+        Ok(updated > 0)
+    }
+
+    /// Delete an order with its items and their change history, nothing is kept.
+    /// Call it inside a transaction, an order referenced from the audit log cannot be deleted
+    /// and the items must then stay
+    pub fn delete(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<bool> {
+        conn.execute("DELETE FROM order_items WHERE order_id = ?1", params![order_id])?;
+        conn.execute("DELETE FROM order_item_changes WHERE order_id = ?1", params![order_id])?;
+        let deleted = conn.execute("DELETE FROM orders WHERE id = ?1", params![order_id])?;
+        Ok(deleted > 0)
+    }
+
+    /// Store the current menu price on every item of an order, so repricing menus later leaves it as it was charged.
+    /// Items that already have a price keep it
+    fn store_unit_prices(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<usize> {
+        conn.execute(
+            "UPDATE order_items SET unit_price_cents = (SELECT CAST(ROUND(price * 100) AS INTEGER) FROM menus WHERE id = order_items.menu_id)
+            WHERE order_id = ?1 AND unit_price_cents IS NULL",
+            params![order_id],
+        )
+    }
+
+    /// Void an order. The order and its items are kept for history but no longer count as revenue
+    pub fn void(conn: &rusqlite::Connection, order_id: i64, reason: &str) -> rusqlite::Result<bool> {
+        OrderResponse::store_unit_prices(conn, order_id)?;
+        let updated = conn.execute(
+            "UPDATE orders SET status = 'void', void_reason = ?2 WHERE id = ?1",
+            params![order_id, reason],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Mark an order as paid and credit its customer, if any, with loyalty points for the total.
+    /// The order is kept for history and the table is free for a new order.
+    /// Call it inside a transaction so the payment and the points are stored together
+    pub fn pay(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<Payment> {
+        OrderResponse::store_unit_prices(conn, order_id)?;
+        let total_cents = OrderResponse::due_cents(conn, order_id)?;
+        conn.execute(
+            "UPDATE orders SET status = 'paid', paid_at = datetime('now') WHERE id = ?1",
+            params![order_id],
+        )?;
+        // Whole points only, partial dollars are not carried over
+        let points_earned = total_cents * Setting::loyalty_points_per_dollar(conn)? / 100;
+        let credited = conn.execute(
+            "UPDATE customers SET points = points + ?2 WHERE id = (SELECT customer_id FROM orders WHERE id = ?1)",
+            params![order_id, points_earned],
+        )?;
+        Ok(Payment {
+            total_cents,
+            points_earned: if credited > 0 { points_earned } else { 0 },
+        })
+    }
+
+    /// Take `points` from a customer and add `discount_cents` to the order's discount.
+    /// Call it inside a transaction so the points and the discount are stored together
+    pub fn redeem_points(conn: &rusqlite::Connection, order_id: i64, customer_id: i64, points: i64, discount_cents: i64) -> rusqlite::Result<()> {
+        conn.execute("UPDATE customers SET points = points - ?2 WHERE id = ?1", params![customer_id, points])?;
+        conn.execute(
+            "UPDATE orders SET discount_cents = discount_cents + ?2 WHERE id = ?1",
+            params![order_id, discount_cents],
+        )?;
+        Ok(())
+    }
+
+    /// What is left to pay on the order: its charge minus any discount, in cents. Never below 0, the discount is
+    /// checked when points are redeemed but items removed or comped later can bring the charge under it
+    pub fn due_cents(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<i64> {
+        let discount_cents: i64 = conn.query_row("SELECT discount_cents FROM orders WHERE id = ?1", params![order_id], |row| row.get(0))?;
+        Ok((OrderResponse::charge_cents(conn, order_id)? - discount_cents).max(0))
+    }
+
+    /// Sum of quantity * price over the order's items, in cents. Comped items are free
+    pub fn subtotal_cents(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<i64> {
+        let query = format!("SELECT COALESCE(SUM({}), 0) FROM order_items as oi JOIN menus as m ON m.id = oi.menu_id WHERE oi.order_id = ?1", LINE_CENTS);
+        conn.query_row(&query, params![order_id], |row| row.get(0))
+    }
+
+    /// What the order is charged before any discount, in cents: the subtotal plus the tax of its items and any
+    /// included service charge. Comped items are free
+    pub fn charge_cents(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<i64> {
+        let query = format!("
+        SELECT COALESCE(SUM({}), 0), COALESCE(SUM({}), 0)
+        FROM order_items as oi
+        JOIN menus as m ON m.id = oi.menu_id
+        {}
+        WHERE oi.order_id = ?1", LINE_CENTS, line_tax_cents(), TAX_RATE_JOIN);
+        let (subtotal_cents, tax_cents): (i64, i64) = conn.query_row(&query, params![order_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(subtotal_cents + tax_cents + Setting::service_charge_cents(conn, subtotal_cents)?)
+    }
+
+    /// Sum of quantity * price over the order's items as an amount, see `subtotal_cents`.
+    /// Tax and service charge are on the bill, see `charge_cents`
+    pub fn total(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<f64> {
+        Ok(to_amount(OrderResponse::subtotal_cents(conn, order_id)?))
+    }
+
+    /// Number of items that are charged, comped items left out
+    pub fn chargeable_item_count(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<i64> {
+        conn.query_row(
+            "SELECT COUNT(*) FROM order_items WHERE order_id = ?1 AND comped = 0",
+            params![order_id],
+            |row| row.get(0),
+        )
+    }
+
+    // Check if order has any remaining items
+    pub fn has_items(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<bool> {
+        let query = "SELECT COUNT(*) FROM order_items WHERE order_id = ?";
+        let count: i64 = conn.query_row(query, params![order_id], |row| row.get(0))?;
+        Ok(count > 0)
+    }
+}
+
+impl Receipt {
+
+    /// Build the receipt of an order, whatever its status
+    pub fn for_order(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<Option<Receipt>> {
+        let result = conn.query_row(
+            "SELECT o.order_number, t.code, o.status, o.created_at, o.paid_at, o.discount_cents
+            FROM orders as o
+            JOIN tables as t ON t.id = o.table_id
+            WHERE o.id = ?1",
+            params![order_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get::<_, i64>(5)?)),
+        );
+        let (order_number, table_code, status, created_at, paid_at, discount_cents) = match result {
+            Ok(order) => order,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        let mut stmt = conn.prepare(&format!(
+            "SELECT m.name, oi.quantity, {}, oi.comped, {}, {}
+            FROM order_items as oi
+            JOIN menus as m ON m.id = oi.menu_id
+            {}
+            WHERE oi.order_id = ?1
+            ORDER BY oi.created_at, oi.id",
+            UNIT_CENTS, TAX_RATE, line_tax_cents(), TAX_RATE_JOIN
+        ))?;
+        let mut subtotal_cents = 0;
+        let mut tax_cents = 0;
+        let mut lines = Vec::new();
+        let rows = stmt.query_map(params![order_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?, row.get::<_, bool>(3)?, row.get::<_, f64>(4)?, row.get::<_, i64>(5)?))
+        })?;
+        for row in rows {
+            let (menu_name, quantity, unit_cents, comped, tax_rate, line_tax_cents) = row?;
+            // Comped items stay on the receipt but are not charged
+            let amount_cents = if comped { 0 } else { quantity * unit_cents };
+            subtotal_cents += amount_cents;
+            tax_cents += line_tax_cents;
+            lines.push(ReceiptLine {
+                menu_name,
+                quantity,
+                unit_price: to_amount(unit_cents),
+                amount: to_amount(amount_cents),
+                tax_rate,
+                tax: to_amount(line_tax_cents),
+                comped,
+            });
+        }
+        let service_included = Setting::service_included(conn)?;
+        let service_cents = Setting::service_charge_cents(conn, subtotal_cents)?;
+        // The discount can be more than what is left on the order once items were removed, only what it covers is taken off
+        let discount_cents = discount_cents.min(subtotal_cents + tax_cents + service_cents);
+        let total_cents = subtotal_cents + tax_cents + service_cents - discount_cents;
+        let tip_suggestions = if service_included {
+            vec![]
+        } else {
+            TipSuggestions::for_total(order_id, total_cents, &Setting::tip_percentages(conn)?).suggestions
+        };
+        Ok(Some(Receipt {
+            order_id,
+            order_number,
+            table_code,
+            status,
+            created_at,
+            paid_at,
+            lines,
+            subtotal: to_amount(subtotal_cents),
+            tax: to_amount(tax_cents),
+            service_charge: service_included.then(|| to_amount(service_cents)),
+            discount: to_amount(discount_cents),
+            total: to_amount(total_cents),
+            tip_suggestions,
+            reprint_count: AuditEntry::count(conn, order_id, "reprint")?,
+        }))
+    }
+}
+
+impl Bill {
+
+    /// The bill of a table's running order, None when the table has none. Amounts are the receipt's
+    pub fn for_table(conn: &rusqlite::Connection, table_id: i64) -> rusqlite::Result<Option<Bill>> {
+        let order_id = match OrderResponse::get_existing_order_id(conn, table_id)? {
+            Some(order_id) => order_id,
+            None => return Ok(None),
+        };
+        let receipt = match Receipt::for_order(conn, order_id)? {
+            Some(receipt) => receipt,
+            None => return Ok(None),
+        };
+        let mut rates = receipt.lines.iter().filter(|line| !line.comped).map(|line| line.tax_rate);
+        let first_rate = rates.next();
+        let tax_rate = match first_rate {
+            Some(rate) if rates.all(|other| other == rate) => Some(rate),
+            Some(_) => None,
+            None => Some(0.0),
+        };
+        Ok(Some(Bill {
+            order_id,
+            table_code: receipt.table_code,
+            lines: receipt.lines.into_iter().map(|line| BillLine {
+                menu_name: line.menu_name,
+                quantity: line.quantity,
+                unit_price: line.unit_price,
+                line_total: line.amount,
+            }).collect(),
+            subtotal: receipt.subtotal,
+            tax_rate,
+            tax_amount: receipt.tax,
+            service_charge: receipt.service_charge,
+            discount: receipt.discount,
+            grand_total: receipt.total,
+        }))
+    }
+}
+
+/// Kitchen printers fit this many characters on a line
+const TICKET_WIDTH: usize = 40;
+
+/// Notes under an item on a ticket are indented by this many spaces
+const TICKET_NOTE_INDENT: usize = 4;
+
+impl OrderTicket {
+
+    /// Ticket of an order with its items in the order they were added, None when there is no such order
+    pub fn for_order(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<Option<OrderTicket>> {
+        let result = conn.query_row(
+            "SELECT o.order_number, t.code, o.created_at
+            FROM orders as o
+            JOIN tables as t ON t.id = o.table_id
+            WHERE o.id = ?1",
+            params![order_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        );
+        let (order_number, table_code, created_at) = match result {
+            Ok(order) => order,
+            Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+            Err(err) => return Err(err),
+        };
+        Ok(Some(OrderTicket {
+            order_id,
+            order_number,
+            table_code,
+            created_at,
+            items: OrderItem::list_all_order_items(conn, order_id)?,
+        }))
+    }
+
+    /// Plain text for a printer TICKET_WIDTH characters wide. Each item is "<quantity> x <menu>" with its notes
+    /// indented underneath, long lines wrap at word boundaries
+    pub fn to_text(&self) -> String {
+        let rule = "-".repeat(TICKET_WIDTH);
+        let mut lines = vec![
+            format!("Order {}", self.order_number.as_deref().map_or_else(|| self.order_id.to_string(), |number| format!("#{}", number))),
+            format!("Table {}", self.table_code),
+        ];
+        if let Some(created_at) = &self.created_at {
+            lines.push(created_at.clone());
+        }
+        lines.push(rule.clone());
+        for item in &self.items {
+            lines.extend(wrap_text(&format!("{} x {}", item.quantity, item.menu_name), TICKET_WIDTH, 0));
+            if let Some(notes) = item.notes.as_deref().filter(|notes| !notes.trim().is_empty()) {
+                lines.extend(wrap_text(notes.trim(), TICKET_WIDTH, TICKET_NOTE_INDENT));
+            }
+        }
+        lines.push(rule);
+        lines.join("\n") + "\n"
+    }
+}
+
+/// Split text into lines of at most `width` characters, each starting with `indent` spaces.
+/// Words longer than a line are cut
+fn wrap_text(text: &str, width: usize, indent: usize) -> Vec<String> {
+    let prefix = " ".repeat(indent);
+    let room = width.saturating_sub(indent).max(1);
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let mut word: Vec<char> = word.chars().collect();
+        while word.len() > room {
+            if !line.is_empty() {
+                lines.push(format!("{}{}", prefix, line));
+                line.clear();
+            }
+            lines.push(format!("{}{}", prefix, word.drain(..room).collect::<String>()));
+        }
+        if word.is_empty() {
+            continue;
+        }
+        let line_len = line.chars().count();
+        if line_len > 0 && line_len + 1 + word.len() > room {
+            lines.push(format!("{}{}", prefix, line));
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.extend(word);
+    }
+    if !line.is_empty() {
+        lines.push(format!("{}{}", prefix, line));
+    }
+    lines
+}
+
+impl AuditEntry {
+
+    /// Record an action on an order
+    pub fn record(conn: &rusqlite::Connection, order_id: i64, action: &str, actor: Option<&str>, note: Option<&str>) -> rusqlite::Result<i64> {
+        conn.execute(
+            "INSERT INTO audit_log (order_id, action, actor, note, created_at) VALUES (?1, ?2, ?3, ?4, datetime('now'))",
+            params![order_id, action, actor, note],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// All recorded actions on an order, oldest first
+    pub fn list_for_order(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<Vec<AuditEntry>> {
+        let mut stmt = conn.prepare("SELECT id, order_id, action, actor, note, created_at FROM audit_log WHERE order_id = ?1 ORDER BY id")?;
+        let rows = stmt.query_map(params![order_id], |row| {
+            Ok(AuditEntry {
+                id: row.get(0)?,
+                order_id: row.get(1)?,
+                action: row.get(2)?,
+                actor: row.get(3)?,
+                note: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?;
+        let result: Result<Vec<_>, _> = rows.collect();
+        result
+    }
+
+    /// How many times an action was recorded on an order
+    pub fn count(conn: &rusqlite::Connection, order_id: i64, action: &str) -> rusqlite::Result<i64> {
+        conn.query_row(
+            "SELECT COUNT(*) FROM audit_log WHERE order_id = ?1 AND action = ?2",
+            params![order_id, action],
+            |row| row.get(0),
+        )
+    }
+}
+
+impl TimelineEntry {
+
+    /// Everything that happened to an order, oldest first, None when there is no such order.
+    /// Steps in the same second keep their natural order: creation, then changes and audited actions, then payment
+    pub fn for_order(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<Option<Vec<TimelineEntry>>> {
+        let exists: bool = conn.query_row("SELECT EXISTS (SELECT 1 FROM orders WHERE id = ?1)", params![order_id], |row| row.get(0))?;
+        if !exists {
+            return Ok(None);
         }
+        let mut stmt = conn.prepare(
+            "SELECT action, actor, note, at FROM (
+                SELECT 'created' as action, NULL as actor, NULL as note, created_at as at, 0 as step, 0 as seq
+                FROM orders WHERE id = ?1 AND created_at IS NOT NULL
+                UNION ALL
+                SELECT 'item_' || c.change, NULL, m.name, c.created_at, 1, c.version
+                FROM order_item_changes as c LEFT JOIN menus as m ON m.id = c.menu_id WHERE c.order_id = ?1
+                UNION ALL
+                SELECT action, actor, note, created_at, 1, id FROM audit_log WHERE order_id = ?1
+                UNION ALL
+                SELECT 'paid', NULL, NULL, paid_at, 2, 0 FROM orders WHERE id = ?1 AND paid_at IS NOT NULL
+            )
+            ORDER BY at, step, seq",
+        )?;
+        let rows = stmt.query_map(params![order_id], |row| {
+            Ok(TimelineEntry {
+                action: row.get(0)?,
+                actor: row.get(1)?,
+                note: row.get(2)?,
+                at: row.get(3)?,
+            })
+        })?;
+        rows.collect::<rusqlite::Result<Vec<_>>>().map(Some)
     }
+}
 
-    /// Calculate the total cooking time dynamically from current order_items
-    pub fn calculate_total_cooking_time(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<i32> {
-        let query = "
-        SELECT SUM(oi.cooking_time)
-        FROM orders
-        JOIN order_items oi ON oi.order_id = orders.id
-        WHERE orders.id = ?1
-    ";
+impl FloorOrderResponse {
 
-        conn.query_row(query, params![order_id], |row| row.get(0))
+    /// Open orders, oldest first, labelled with the floor_amber_minutes and floor_red_minutes thresholds.
+    /// Ages are measured up to `now` (Unix time)
+    pub fn list(conn: &rusqlite::Connection, now: i64) -> rusqlite::Result<Vec<FloorOrderResponse>> {
+        let (amber_minutes, red_minutes) = Setting::floor_thresholds(conn)?;
+        let mut stmt = conn.prepare(
+            "SELECT o.id, o.order_number, o.table_id, t.code, o.created_at,
+                COALESCE((?1 - CAST(strftime('%s', o.created_at) AS INTEGER)) / 60, 0)
+            FROM orders as o
+            JOIN tables as t ON t.id = o.table_id
+            WHERE o.status NOT IN ('paid', 'void')
+            ORDER BY o.created_at, o.id",
+        )?;
+        let rows = stmt.query_map(params![now], |row| {
+            let age_minutes: i64 = row.get(5)?;
+            let urgency = if age_minutes >= red_minutes {
+                "red"
+            } else if age_minutes >= amber_minutes {
+                "amber"
+            } else {
+                "green"
+            };
+            Ok(FloorOrderResponse {
+                order_id: row.get(0)?,
+                order_number: row.get(1)?,
+                table_id: row.get(2)?,
+                table_code: row.get(3)?,
+                created_at: row.get(4)?,
+                age_minutes,
+                urgency: urgency.to_string(),
+            })
+        })?;
+        let result: Result<Vec<_>, _> = rows.collect();
+        result
     }
+}
 
-    // Check if order has any remaining items
-    pub fn has_items(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<bool> {
-        let query = "SELECT COUNT(*) FROM order_items WHERE order_id = ?";
-        let count: i64 = conn.query_row(query, params![order_id], |row| row.get(0))?;
-        Ok(count > 0)
+/// Columns selected for an OrderItemResponse, in the order `OrderItemResponse::from_row` reads them.
+/// Queries using it must join menus as `m`
+const ORDER_ITEM_COLUMNS: &str = "order_items.id, order_items.order_id, order_items.menu_id, m.name, order_items.quantity, order_items.cooking_time, order_items.item_status, order_items.fired_at, order_items.comped, order_items.comp_reason, order_items.served_at, order_items.notes";
+
+/// Order items come back in the order they were added. Items added within the same second keep their insertion order
+const ORDER_ITEM_ORDER: &str = "order_items.created_at, order_items.id";
+
+impl OrderItemResponse {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<OrderItemResponse> {
+        Ok(OrderItemResponse {
+            id: row.get(0)?,
+            order_id: row.get(1)?,
+            menu_id: row.get(2)?,
+            menu_name: row.get(3)?,
+            quantity: row.get(4)?,
+            cooking_time: row.get(5)?,
+            item_status: row.get(6)?,
+            fired_at: row.get(7)?,
+            comped: row.get(8)?,
+            comp_reason: row.get(9)?,
+            served_at: row.get(10)?,
+            notes: row.get(11)?,
+        })
     }
 }
 
@@ -225,9 +2130,16 @@ impl OrderItem {
 
     /// Create orders items
     pub fn create(conn: &rusqlite::Connection, order_id: i64, menu_id: i64, cooking_time:i64) -> rusqlite::Result<i64> {
+        OrderItem::create_with_quantity(conn, order_id, menu_id, cooking_time, 1)
+    }
+
+    /// Create an order item for several of the same menu at once, cooking_time is per unit
+    pub fn create_with_quantity(conn: &rusqlite::Connection, order_id: i64, menu_id: i64, cooking_time: i64, quantity: i64) -> rusqlite::Result<i64> {
+        // With auto_fire on the item goes straight to cooking, fired now, instead of being held as pending
         conn.execute(
-            "INSERT INTO order_items (order_id, menu_id, cooking_time, quantity) VALUES (?1, ?2, ?3, ?4)",
-            params![order_id, menu_id, cooking_time, 1],
+            "INSERT INTO order_items (order_id, menu_id, cooking_time, quantity, created_at, item_status, fired_at)
+            VALUES (?1, ?2, ?3, ?4, datetime('now'), CASE WHEN ?5 THEN 'cooking' ELSE 'pending' END, CASE WHEN ?5 THEN datetime('now') END)",
+            params![order_id, menu_id, cooking_time * quantity, quantity, Setting::auto_fire(conn)?],
         )?;
         // Get the last inserted row's ID
         let last_inserted_id = conn.last_insert_rowid();
@@ -250,63 +2162,78 @@ impl OrderItem {
         Ok(rows.map(|result| result.unwrap()).collect())
     }
     */
-    /// List all orders items for a specific order
+    /// List all orders items for a specific order, in the order they were added
     pub fn list_all_order_items(conn: &rusqlite::Connection, order_id:i64) -> rusqlite::Result<Vec<OrderItemResponse>> {
-        let mut stmt = conn.prepare("SELECT order_items.id, order_items.order_id, order_items.menu_id, m.name, order_items.quantity, order_items.cooking_time FROM order_items JOIN menus as m on order_items.menu_id=m.id WHERE order_id= ?1")?;
-        let rows = stmt.query_map(params![order_id], |row| {
-            Ok(OrderItemResponse {
-                id: row.get(0)?,
-                order_id: row.get(1)?,
-                menu_id: row.get(2)?,
-                menu_name: row.get(3)?,
-                quantity: row.get(4)?,
-                cooking_time: row.get(5)?,
-            })
-        })?;
+        let mut stmt = conn.prepare(&format!("SELECT {} FROM order_items JOIN menus as m on order_items.menu_id=m.id WHERE order_id= ?1 ORDER BY {}", ORDER_ITEM_COLUMNS, ORDER_ITEM_ORDER))?;
+        let rows = stmt.query_map(params![order_id], OrderItemResponse::from_row)?;
         let result: Result<Vec<_>, _> = rows.collect();
         result
     }
 
-    /// List all orders items for a specific table
+    /// List all orders items for a specific table, in the order they were added
     pub fn list_order_items(conn: &rusqlite::Connection, table_id:i64) -> rusqlite::Result<Vec<OrderItemResponse>> {
-        let query = "SELECT order_items.id, order_items.order_id, order_items.menu_id, m.name, order_items.quantity, order_items.cooking_time
+        let query = format!("SELECT {}
         FROM order_items
         JOIN orders ON orders.id = order_items.order_id
         JOIN menus as m on order_items.menu_id=m.id
-        WHERE orders.table_id = ?1";
-        let mut stmt = conn.prepare(query)?;
-        let rows = stmt.query_map(params![table_id], |row| {
-            Ok(OrderItemResponse {
-                id: row.get(0)?,
-                order_id: row.get(1)?,
-                menu_id: row.get(2)?,
-                menu_name: row.get(3)?,
-                quantity: row.get(4)?,
-                cooking_time: row.get(5)?,
-            })
-        })?;
+        WHERE orders.table_id = ?1 AND orders.status NOT IN ('paid', 'void')
+        ORDER BY {}", ORDER_ITEM_COLUMNS, ORDER_ITEM_ORDER);
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![table_id], OrderItemResponse::from_row)?;
+        let result: Result<Vec<_>, _> = rows.collect();
+        result
+    }
+
+    /// Items of all running orders that are not served yet, oldest first
+    pub fn list_open_items(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<OrderItemResponse>> {
+        let query = format!("SELECT {}
+        FROM order_items
+        JOIN orders ON orders.id = order_items.order_id
+        JOIN menus as m on order_items.menu_id=m.id
+        WHERE orders.status NOT IN ('paid', 'void') AND order_items.item_status != 'served'
+        ORDER BY {}", ORDER_ITEM_COLUMNS, ORDER_ITEM_ORDER);
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map([], OrderItemResponse::from_row)?;
+        rows.collect()
+    }
+
+    /// Items of an order whose status is one of `statuses`, see `Setting::locked_item_statuses`
+    pub fn locked_items(conn: &rusqlite::Connection, order_id: i64, statuses: &[&str]) -> rusqlite::Result<Vec<OrderItemResponse>> {
+        let query = format!("SELECT {}
+        FROM order_items
+        JOIN menus as m on order_items.menu_id=m.id
+        WHERE order_items.order_id = ?1
+        ORDER BY {}", ORDER_ITEM_COLUMNS, ORDER_ITEM_ORDER);
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![order_id], OrderItemResponse::from_row)?;
+        let items = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(items.into_iter().filter(|item| statuses.contains(&item.item_status.as_str())).collect())
+    }
+
+    /// Items of all running orders whose cooking time is between min and max, inclusive, quickest first
+    pub fn list_by_cooking_time(conn: &rusqlite::Connection, min: i64, max: i64) -> rusqlite::Result<Vec<OrderItemResponse>> {
+        let query = format!("SELECT {}
+        FROM order_items
+        JOIN orders ON orders.id = order_items.order_id
+        JOIN menus as m on order_items.menu_id=m.id
+        WHERE orders.status NOT IN ('paid', 'void') AND order_items.cooking_time BETWEEN ?1 AND ?2
+        ORDER BY order_items.cooking_time, order_items.id", ORDER_ITEM_COLUMNS);
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![min, max], OrderItemResponse::from_row)?;
         let result: Result<Vec<_>, _> = rows.collect();
         result
     }
 
     pub fn get_item(conn: &rusqlite::Connection, table_id:i64, menu_id: i64)->rusqlite::Result<Option<OrderItemResponse>>{
-        let query = "
-        SELECT order_items.id, order_items.order_id, order_items.menu_id, m.name, order_items.quantity, order_items.cooking_time
+        let query = format!("
+        SELECT {}
         FROM order_items
         JOIN orders ON orders.id = order_items.order_id
         JOIN menus as m on order_items.menu_id=m.id
-        WHERE orders.table_id = ?1 AND order_items.menu_id = ?2";
-        let mut stmt = conn.prepare(query)?;
-        let result = stmt.query_row(params![table_id, menu_id], |row| {
-            Ok(OrderItemResponse {
-                id: row.get(0)?,
-                order_id: row.get(1)?,
-                menu_id: row.get(2)?,
-                menu_name: row.get(3)?,
-                quantity: row.get(4)?,
-                cooking_time: row.get(5)?,
-            })
-        });
+        WHERE orders.table_id = ?1 AND orders.status NOT IN ('paid', 'void') AND order_items.menu_id = ?2
+        ORDER BY {}", ORDER_ITEM_COLUMNS, ORDER_ITEM_ORDER);
+        let mut stmt = conn.prepare(&query)?;
+        let result = stmt.query_row(params![table_id, menu_id], OrderItemResponse::from_row);
         match result {
             Ok(item) => Ok(Some(item)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
@@ -314,6 +2241,275 @@ impl OrderItem {
         }
     }
 
+    /// Move an item of the table's running order to a new status.
+    /// fired_at is stamped the first time the item starts cooking and kept on later transitions,
+    /// only sending the item back to pending clears it. ready_at and served_at work the same way
+    /// for their statuses and are cleared when the item moves back before them
+    pub fn set_status(conn: &rusqlite::Connection, table_id: i64, menu_id: i64, status: &str) -> rusqlite::Result<bool> {
+        let query = "
+        UPDATE order_items
+        SET fired_at = CASE
+                WHEN ?3 = 'cooking' THEN COALESCE(fired_at, datetime('now'))
+                WHEN ?3 = 'pending' THEN NULL
+                ELSE fired_at
+            END,
+            ready_at = CASE
+                WHEN ?3 = 'ready' THEN COALESCE(ready_at, datetime('now'))
+                WHEN ?3 = 'served' THEN ready_at
+            END,
+            served_at = CASE WHEN ?3 = 'served' THEN COALESCE(served_at, datetime('now')) END,
+            item_status = ?3
+        WHERE menu_id = ?2 AND order_id IN (
+            SELECT id FROM orders WHERE table_id = ?1 AND status NOT IN ('paid', 'void')
+        )";
+        let updated = conn.execute(query, params![table_id, menu_id, status])?;
+        Ok(updated > 0)
+    }
+
+    /// Mark every ready item of a table's running order served, when the food goes out together.
+    /// Returns how many items were served
+    pub fn serve_ready(conn: &rusqlite::Connection, table_id: i64) -> rusqlite::Result<usize> {
+        conn.execute(
+            "UPDATE order_items
+            SET item_status = 'served', served_at = COALESCE(served_at, datetime('now'))
+            WHERE item_status = 'ready' AND order_id IN (
+                SELECT id FROM orders WHERE table_id = ?1 AND status NOT IN ('paid', 'void')
+            )",
+            params![table_id],
+        )
+    }
+
+    /// Comp an item of a table's running order: it stays on the order for the kitchen but is no longer charged
+    pub fn comp(conn: &rusqlite::Connection, table_id: i64, menu_id: i64, reason: &str) -> rusqlite::Result<bool> {
+        let query = "
+        UPDATE order_items
+        SET comped = 1, comp_reason = ?3
+        WHERE menu_id = ?2 AND order_id IN (
+            SELECT id FROM orders WHERE table_id = ?1 AND status NOT IN ('paid', 'void')
+        )";
+        let updated = conn.execute(query, params![table_id, menu_id, reason])?;
+        Ok(updated > 0)
+    }
+
+    /// List every item currently cooking on a running order, oldest first, with the minutes since it was fired
+    pub fn list_cooking(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<PassItemResponse>> {
+        let query = "
+        SELECT oi.order_id, o.table_id, t.code, oi.menu_id, m.name, oi.quantity, oi.fired_at,
+            CAST((julianday('now') - julianday(oi.fired_at)) * 1440 AS INTEGER), o.order_number
+        FROM order_items as oi
+        JOIN orders as o ON o.id = oi.order_id
+        JOIN tables as t ON t.id = o.table_id
+        JOIN menus as m ON m.id = oi.menu_id
+        WHERE oi.item_status = 'cooking' AND oi.fired_at IS NOT NULL AND o.status != 'void'
+        ORDER BY oi.fired_at, oi.id";
+        let mut stmt = conn.prepare(query)?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok(PassItemResponse {
+                order_id: row.get(0)?,
+                order_number: row.get(8)?,
+                table_id: row.get(1)?,
+                table_code: row.get(2)?,
+                menu_id: row.get(3)?,
+                menu_name: row.get(4)?,
+                quantity: row.get(5)?,
+                fired_at: row.get(6)?,
+                elapsed_minutes: row.get(7)?,
+            })
+        })?;
+        let result: Result<Vec<_>, _> = rows.collect();
+        result
+    }
+
+    /// Every ready item not yet served, grouped by table, with the minutes from it was marked ready until `now` (Unix time)
+    pub fn list_ready_by_table(conn: &rusqlite::Connection, now: i64) -> rusqlite::Result<Vec<ReadyTableResponse>> {
+        let query = "
+        SELECT o.table_id, t.code, oi.order_id, o.order_number, oi.menu_id, m.name, oi.quantity, oi.ready_at,
+            (?1 - CAST(strftime('%s', oi.ready_at) AS INTEGER)) / 60
+        FROM order_items as oi
+        JOIN orders as o ON o.id = oi.order_id
+        JOIN tables as t ON t.id = o.table_id
+        JOIN menus as m ON m.id = oi.menu_id
+        WHERE oi.item_status = 'ready' AND oi.ready_at IS NOT NULL AND o.status != 'void'
+        ORDER BY o.table_id, oi.ready_at, oi.id";
+        let mut stmt = conn.prepare(query)?;
+        let mut rows = stmt.query(params![now])?;
+        let mut tables: Vec<ReadyTableResponse> = Vec::new();
+        while let Some(row) = rows.next()? {
+            let table_id: i64 = row.get(0)?;
+            let item = ReadyItemResponse {
+                order_id: row.get(2)?,
+                order_number: row.get(3)?,
+                menu_id: row.get(4)?,
+                menu_name: row.get(5)?,
+                quantity: row.get(6)?,
+                ready_at: row.get(7)?,
+                waiting_minutes: row.get(8)?,
+            };
+            match tables.last_mut() {
+                Some(table) if table.table_id == table_id => table.items.push(item),
+                _ => tables.push(ReadyTableResponse { table_id, table_code: row.get(1)?, items: vec![item] }),
+            }
+        }
+        Ok(tables)
+    }
+
+    /// Pending and cooking items for one kitchen station, oldest order first
+    pub fn list_station_queue(conn: &rusqlite::Connection, station: &str) -> rusqlite::Result<Vec<StationQueueItemResponse>> {
+        let query = "
+        SELECT oi.order_id, o.order_number, o.table_id, t.code, oi.menu_id, m.name, oi.quantity, oi.item_status
+        FROM order_items as oi
+        JOIN orders as o ON o.id = oi.order_id
+        JOIN tables as t ON t.id = o.table_id
+        JOIN menus as m ON m.id = oi.menu_id
+        WHERE oi.item_status IN ('pending', 'cooking') AND o.status != 'void' AND COALESCE(m.station, ?2) = ?1
+        ORDER BY o.created_at, oi.order_id, oi.created_at, oi.id";
+        let mut stmt = conn.prepare(query)?;
+        let rows = stmt.query_map(params![station, DEFAULT_STATION], |row| {
+            Ok(StationQueueItemResponse {
+                order_id: row.get(0)?,
+                order_number: row.get(1)?,
+                table_id: row.get(2)?,
+                table_code: row.get(3)?,
+                menu_id: row.get(4)?,
+                menu_name: row.get(5)?,
+                quantity: row.get(6)?,
+                item_status: row.get(7)?,
+            })
+        })?;
+        let result: Result<Vec<_>, _> = rows.collect();
+        result
+    }
+
+    /// Names of the given menus that were already added to the table within the last `window_seconds`
+    pub fn recent_duplicate_names(conn: &rusqlite::Connection, table_id: i64, menu_ids: &[i64], window_seconds: i64) -> rusqlite::Result<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT m.name FROM recent_adds as ra
+            JOIN menus as m ON m.id = ra.menu_id
+            WHERE ra.table_id = ?1 AND ra.menu_id = ?2 AND (julianday('now') - julianday(ra.added_at)) * 86400 < ?3",
+        )?;
+        let mut names = Vec::new();
+        for menu_id in menu_ids {
+            match stmt.query_row(params![table_id, menu_id, window_seconds], |row| row.get::<_, String>(0)) {
+                Ok(name) => {
+                    if !names.contains(&name) {
+                        names.push(name);
+                    }
+                }
+                Err(rusqlite::Error::QueryReturnedNoRows) => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(names)
+    }
+
+    /// Remember when the menus were last added to the table, to catch accidental double taps
+    pub fn record_recent_adds(conn: &rusqlite::Connection, table_id: i64, menu_ids: &[i64]) -> rusqlite::Result<()> {
+        let mut stmt = conn.prepare(
+            "INSERT INTO recent_adds (table_id, menu_id, added_at) VALUES (?1, ?2, strftime('%Y-%m-%d %H:%M:%f', 'now'))
+            ON CONFLICT(table_id, menu_id) DO UPDATE SET added_at = excluded.added_at",
+        )?;
+        for menu_id in menu_ids {
+            stmt.execute(params![table_id, menu_id])?;
+        }
+        Ok(())
+    }
+
+    /// Minutes until the running order of a table is cooked. Items cook in parallel, so this is the longest
+    /// cooking_time among the items not ready yet, 0 when everything is ready
+    pub fn remaining_time(conn: &rusqlite::Connection, table_id: i64) -> rusqlite::Result<i64> {
+        conn.query_row(
+            "SELECT COALESCE(MAX(oi.cooking_time), 0)
+            FROM order_items as oi
+            JOIN orders as o ON o.id = oi.order_id
+            WHERE o.table_id = ?1 AND o.status NOT IN ('paid', 'void') AND oi.item_status IN ('pending', 'cooking')",
+            params![table_id],
+            |row| row.get(0),
+        )
+    }
+
+    /// Minutes until the running order of a table is cooked, measured from `now` (Unix time). Stations work
+    /// in parallel but prepare their items one after another, so this is the busiest station's remaining time.
+    /// Cooking items have their elapsed time taken off. The result is not scaled by the rush_multiplier
+    pub fn remaining_station_time(conn: &rusqlite::Connection, table_id: i64, now: i64) -> rusqlite::Result<i64> {
+        conn.query_row(
+            "SELECT COALESCE(MAX(minutes), 0) FROM (
+                SELECT COALESCE(m.station, ?3) as station, SUM(CASE oi.item_status
+                    WHEN 'pending' THEN oi.cooking_time
+                    WHEN 'cooking' THEN MAX(oi.cooking_time - MAX((?2 - COALESCE(CAST(strftime('%s', oi.fired_at) AS INTEGER), ?2)) / 60, 0), 0)
+                    ELSE 0
+                END) as minutes
+                FROM order_items as oi
+                JOIN menus as m ON m.id = oi.menu_id
+                JOIN orders as o ON o.id = oi.order_id
+                WHERE o.table_id = ?1 AND o.status NOT IN ('paid', 'void')
+                GROUP BY station
+            )",
+            params![table_id, now, DEFAULT_STATION],
+            |row| row.get(0),
+        )
+    }
+
+    /// Portions served in the `window_minutes` up to `now` (Unix time), by served_at, whichever order they were on
+    pub fn served_in_window(conn: &rusqlite::Connection, now: i64, window_minutes: i64) -> rusqlite::Result<i64> {
+        conn.query_row(
+            "SELECT COALESCE(SUM(quantity), 0) FROM order_items
+            WHERE item_status = 'served' AND CAST(strftime('%s', served_at) AS INTEGER) > ?1 - ?2 * 60
+                AND CAST(strftime('%s', served_at) AS INTEGER) <= ?1",
+            params![now, window_minutes],
+            |row| row.get(0),
+        )
+    }
+
+    /// Preparation of every item on the running order of a table, measured up to `now` (Unix time).
+    /// Pending items have not started, ready and served ones stopped at ready_at and have nothing left
+    pub fn prep_breakdown(conn: &rusqlite::Connection, table_id: i64, now: i64) -> rusqlite::Result<Vec<PrepBreakdownItem>> {
+        let mut stmt = conn.prepare(
+            "SELECT menu_id, name, quantity, item_status, cooking_time, elapsed,
+                CASE item_status
+                    WHEN 'pending' THEN cooking_time
+                    WHEN 'cooking' THEN MAX(cooking_time - elapsed, 0)
+                    ELSE 0
+                END
+            FROM (
+                SELECT oi.id, oi.created_at, oi.menu_id, m.name, oi.quantity, oi.item_status, oi.cooking_time,
+                    CASE WHEN oi.item_status = 'pending' OR oi.fired_at IS NULL THEN 0
+                        ELSE MAX((COALESCE(CAST(strftime('%s', oi.ready_at) AS INTEGER), ?2) - CAST(strftime('%s', oi.fired_at) AS INTEGER)) / 60, 0)
+                    END as elapsed
+                FROM order_items as oi
+                JOIN menus as m ON m.id = oi.menu_id
+                JOIN orders as o ON o.id = oi.order_id
+                WHERE o.table_id = ?1 AND o.status NOT IN ('paid', 'void')
+            )
+            ORDER BY created_at, id",
+        )?;
+        let rows = stmt.query_map(params![table_id, now], |row| {
+            Ok(PrepBreakdownItem {
+                menu_id: row.get(0)?,
+                menu_name: row.get(1)?,
+                quantity: row.get(2)?,
+                item_status: row.get(3)?,
+                cooking_time: row.get(4)?,
+                elapsed_minutes: row.get(5)?,
+                remaining_minutes: row.get(6)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Take every item off an order, the order itself stays
+    pub fn clear_order(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<usize> {
+        conn.execute("DELETE FROM order_items WHERE order_id = ?1", params![order_id])
+    }
+
+    /// Set the notes of a menu on an order for the kitchen
+    pub fn set_notes(conn: &rusqlite::Connection, order_id: i64, menu_id: i64, notes: &str) -> rusqlite::Result<bool> {
+        let updated = conn.execute(
+            "UPDATE order_items SET notes = ?3 WHERE order_id = ?1 AND menu_id = ?2",
+            params![order_id, menu_id, notes],
+        )?;
+        Ok(updated > 0)
+    }
+
     /* Utility Functions for OrderItem Model. This block will contain some utility function to call on OrderItem Model */
 
     /// Get the exisiting order item for a order and a menu
@@ -328,12 +2524,49 @@ impl OrderItem {
         }
     }
 
+    pub fn get_quantity(conn: &Connection, order_item_id: i64) -> rusqlite::Result<i64> {
+        conn.query_row("SELECT quantity FROM order_items WHERE id = ?1", params![order_item_id], |row| row.get(0))
+    }
+
+    /// Set the quantity of an item, scaling its cooking time with it
+    pub fn set_quantity(conn: &Connection, order_item_id: i64, quantity: i64) -> rusqlite::Result<bool> {
+        let updated = conn.execute(
+            "UPDATE order_items SET cooking_time = (cooking_time / quantity) * ?2, quantity = ?2 WHERE id = ?1",
+            params![order_item_id, quantity],
+        )?;
+        Ok(updated > 0)
+    }
+
+    /// Take one off the quantity of an item, deleting it when it was the last one
+    pub fn remove_one(conn: &Connection, order_item_id: i64) -> rusqlite::Result<bool> {
+        let updated = conn.execute(
+            "UPDATE order_items SET cooking_time = cooking_time - (cooking_time / quantity), quantity = quantity - 1
+            WHERE id = ?1 AND quantity > 1",
+            params![order_item_id],
+        )?;
+        if updated > 0 {
+            return Ok(true);
+        }
+        let deleted = conn.execute("DELETE FROM order_items WHERE id = ?1", params![order_item_id])?;
+        Ok(deleted > 0)
+    }
+
+    pub fn delete(conn: &Connection, order_item_id: i64) -> rusqlite::Result<bool> {
+        let deleted = conn.execute("DELETE FROM order_items WHERE id = ?1", params![order_item_id])?;
+        Ok(deleted > 0)
+    }
+
     pub fn add_quantity_of_existing_order_item(conn: &Connection, order_item_id: i64) -> Result<bool, rusqlite::Error> {
+        OrderItem::add_to_quantity(conn, order_item_id, 1)
+    }
+
+    /// Add `count` to the quantity of an existing order item in a single UPDATE
+    pub fn add_to_quantity(conn: &Connection, order_item_id: i64, count: i64) -> Result<bool, rusqlite::Error> {
         let query = "UPDATE order_items
-        SET cooking_time = (cooking_time / quantity) * (quantity + 1),
-        quantity = quantity + 1
+        SET cooking_time = (cooking_time / quantity) * (quantity + ?2),
+        quantity = quantity + ?2
         WHERE id = ?1";
-        let result = conn.execute(query, params![order_item_id])?;
+        let result = conn.execute(query, params![order_item_id, count])?;
         if result > 0 {
             Ok(true)
         } else {
@@ -341,3 +2574,512 @@ impl OrderItem {
         }
         }
 }
+
+/// Functions for bulk import
+impl ImportRequest {
+
+    /// Reject entries that can never be stored, before anything is written
+    pub fn validate(&self) -> Result<(), String> {
+        self.tables.iter().try_for_each(Table::validate)?;
+        if self.menus.iter().any(|menu| menu.name.trim().is_empty()) {
+            return Err("Menu name cannot be empty".to_string());
+        }
+        Ok(())
+    }
+
+    /// Upsert all tables and menus. Existing tables are left alone, existing menus (by name) get the new price and category.
+    /// Run it inside a transaction so a failure leaves nothing behind
+    pub fn import(&self, conn: &rusqlite::Connection) -> rusqlite::Result<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        for table in &self.tables {
+            match Table::get_existing_table_id(conn, table)? {
+                Some(_) => summary.tables_existing += 1,
+                None => {
+                    Table::create(conn, table)?;
+                    summary.tables_created += 1;
+                }
+            }
+        }
+        for menu in &self.menus {
+            match Menu::get_existing_menu_id(conn, menu)? {
+                Some(menu_id) => {
+                    Menu::update_price_and_category(conn, menu_id, menu)?;
+                    summary.menus_updated += 1;
+                }
+                None => {
+                    Menu::create(conn, menu)?;
+                    summary.menus_created += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+}
+
+impl TipSuggestions {
+
+    /// Tip for each percentage, rounded half up to the cent
+    pub fn for_total(order_id: i64, total_cents: i64, percentages: &[i64]) -> TipSuggestions {
+        let suggestions = percentages.iter().map(|&percent| {
+            let tip_cents = (total_cents * percent + 50) / 100;
+            TipSuggestion {
+                percent,
+                tip: to_amount(tip_cents),
+                total_with_tip: to_amount(total_cents + tip_cents),
+            }
+        }).collect();
+        TipSuggestions {
+            order_id,
+            total: to_amount(total_cents),
+            suggestions,
+        }
+    }
+}
+
+/// Functions for Setting Model
+impl Setting {
+
+    /// Get the raw value of a setting, None when it was never set
+    pub fn get(conn: &rusqlite::Connection, key: &str) -> rusqlite::Result<Option<String>> {
+        let result = conn.query_row("SELECT value FROM settings WHERE key = ?1", params![key], |row| row.get(0));
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Insert or overwrite a setting
+    pub fn set(conn: &rusqlite::Connection, key: &str, value: &str) -> rusqlite::Result<()> {
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(())
+    }
+
+    /// List all settings that were changed from their defaults
+    pub fn list(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<Setting>> {
+        let mut stmt = conn.prepare("SELECT key, value FROM settings ORDER BY key")?;
+        let rows = stmt.query_map(params![], |row| {
+            Ok(Setting {
+                key: row.get(0)?,
+                value: row.get(1)?,
+            })
+        })?;
+        let result: Result<Vec<_>, _> = rows.collect();
+        result
+    }
+
+    /// Check that a setting is known and its value is acceptable before it gets stored
+    pub fn validate(key: &str, value: &str) -> Result<(), String> {
+        match key {
+            "default_menu_sort" => {
+                if MENU_SORT_FIELDS.contains(&value) {
+                    Ok(())
+                } else {
+                    Err(format!("default_menu_sort must be one of {}", MENU_SORT_FIELDS.join(", ")))
+                }
+            }
+            "tip_percentages" => {
+                let valid = !value.is_empty() && value.split(',').all(|percent| matches!(percent.trim().parse::<i64>(), Ok(0..=100)));
+                if valid {
+                    Ok(())
+                } else {
+                    Err("tip_percentages must be a comma separated list of whole percentages, e.g. 15,18,20".to_string())
+                }
+            }
+            "order_number_prefix" => {
+                if value.chars().count() <= 5 {
+                    Ok(())
+                } else {
+                    Err("order_number_prefix can be at most 5 characters".to_string())
+                }
+            }
+            "order_number_digits" => {
+                match value.parse::<i64>() {
+                    Ok(1..=9) => Ok(()),
+                    _ => Err("order_number_digits must be a number between 1 and 9".to_string()),
+                }
+            }
+            "table_code_prefix" => {
+                if value.chars().count() <= 5 {
+                    Ok(())
+                } else {
+                    Err("table_code_prefix can be at most 5 characters".to_string())
+                }
+            }
+            "table_code_digits" => {
+                match value.parse::<i64>() {
+                    Ok(1..=9) => Ok(()),
+                    _ => Err("table_code_digits must be a number between 1 and 9".to_string()),
+                }
+            }
+            "auto_fire" => {
+                if value == "true" || value == "false" {
+                    Ok(())
+                } else {
+                    Err("auto_fire must be true or false".to_string())
+                }
+            }
+            "service_included" => {
+                if value == "true" || value == "false" {
+                    Ok(())
+                } else {
+                    Err("service_included must be true or false".to_string())
+                }
+            }
+            "service_charge_percent" => {
+                match value.parse::<f64>() {
+                    Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(()),
+                    _ => Err("service_charge_percent must be a percentage between 0 and 100".to_string()),
+                }
+            }
+            "closing_time" => {
+                match parse_time_of_day(value) {
+                    Some(_) => Ok(()),
+                    None => Err("closing_time must be a time of day formatted as HH:MM".to_string()),
+                }
+            }
+            "last_order_minutes" => {
+                match value.parse::<i64>() {
+                    Ok(0..=720) => Ok(()),
+                    _ => Err("last_order_minutes must be a whole number of minutes between 0 and 720".to_string()),
+                }
+            }
+            "floor_amber_minutes" | "floor_red_minutes" => {
+                match value.parse::<i64>() {
+                    Ok(1..=1440) => Ok(()),
+                    _ => Err(format!("{} must be a whole number of minutes between 1 and 1440", key)),
+                }
+            }
+            "duplicate_add_window_seconds" => {
+                match value.parse::<i64>() {
+                    Ok(0..=300) => Ok(()),
+                    _ => Err("duplicate_add_window_seconds must be a whole number of seconds between 0 and 300".to_string()),
+                }
+            }
+            "price_decimal_places" => {
+                match value.parse::<u32>() {
+                    Ok(0..=2) => Ok(()),
+                    _ => Err("price_decimal_places must be 0, 1 or 2".to_string()),
+                }
+            }
+            "max_line_quantity" => {
+                match value.parse::<i64>() {
+                    Ok(1..=100000) => Ok(()),
+                    _ => Err("max_line_quantity must be a whole number between 1 and 100000".to_string()),
+                }
+            }
+            "large_list_items" => {
+                match value.parse::<i64>() {
+                    Ok(1..=10000) => Ok(()),
+                    _ => Err("large_list_items must be a whole number between 1 and 10000".to_string()),
+                }
+            }
+            "rush_multiplier" => {
+                match value.parse::<f64>() {
+                    Ok(multiplier) if (1.0..=5.0).contains(&multiplier) => Ok(()),
+                    _ => Err("rush_multiplier must be a number between 1 and 5".to_string()),
+                }
+            }
+            "points_redemption_cents" => {
+                match value.parse::<i64>() {
+                    Ok(1..=100) => Ok(()),
+                    _ => Err("points_redemption_cents must be a whole number of cents between 1 and 100".to_string()),
+                }
+            }
+            "lock_items_from" => {
+                if ["ready", "served", "off"].contains(&value) {
+                    Ok(())
+                } else {
+                    Err("lock_items_from must be one of ready, served, off".to_string())
+                }
+            }
+            key if key.starts_with("tax_rate_") && key.len() > "tax_rate_".len() => {
+                match value.parse::<f64>() {
+                    Ok(rate) if (0.0..=100.0).contains(&rate) => Ok(()),
+                    _ => Err(format!("{} must be a percentage between 0 and 100", key)),
+                }
+            }
+            "loyalty_points_per_dollar" => {
+                match value.parse::<i64>() {
+                    Ok(0..=100) => Ok(()),
+                    _ => Err("loyalty_points_per_dollar must be a whole number between 0 and 100".to_string()),
+                }
+            }
+            _ => Err(format!("Unknown setting {}", key)),
+        }
+    }
+
+    /// Sort used by the menu list when the request does not ask for one
+    pub fn default_menu_sort(conn: &rusqlite::Connection) -> rusqlite::Result<String> {
+        Ok(Setting::get(conn, "default_menu_sort")?.unwrap_or_else(|| "id".to_string()))
+    }
+
+    /// Percentages offered as tip suggestions
+    pub fn tip_percentages(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<i64>> {
+        let value = Setting::get(conn, "tip_percentages")?.unwrap_or_else(|| "15,18,20".to_string());
+        Ok(value.split(',').filter_map(|percent| percent.trim().parse().ok()).collect())
+    }
+
+    /// Whether new order items start cooking right away. Off by default, they are held as pending until fired
+    pub fn auto_fire(conn: &rusqlite::Connection) -> rusqlite::Result<bool> {
+        Ok(Setting::get(conn, "auto_fire")?.as_deref() == Some("true"))
+    }
+
+    /// Whether a service charge is added to every bill, in which case no tips are suggested
+    pub fn service_included(conn: &rusqlite::Connection) -> rusqlite::Result<bool> {
+        Ok(Setting::get(conn, "service_included")?.as_deref() == Some("true"))
+    }
+
+    /// Service charge on a subtotal, rounded to the cent. 0 unless service_included is on,
+    /// then service_charge_percent of the subtotal (10% by default)
+    pub fn service_charge_cents(conn: &rusqlite::Connection, subtotal_cents: i64) -> rusqlite::Result<i64> {
+        if !Setting::service_included(conn)? {
+            return Ok(0);
+        }
+        let percent: f64 = Setting::get(conn, "service_charge_percent")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(10.0);
+        Ok((subtotal_cents as f64 * percent / 100.0).round() as i64)
+    }
+
+    /// Loyalty points credited for every whole dollar paid
+    pub fn loyalty_points_per_dollar(conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+        Ok(Setting::get(conn, "loyalty_points_per_dollar")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1))
+    }
+
+    /// Minutes after which an open order turns amber and red on the floor dashboard. Defaults to 15 and 30
+    pub fn floor_thresholds(conn: &rusqlite::Connection) -> rusqlite::Result<(i64, i64)> {
+        let amber = Setting::get(conn, "floor_amber_minutes")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(15);
+        let red = Setting::get(conn, "floor_red_minutes")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(30);
+        Ok((amber, red))
+    }
+
+    /// Seconds during which adding the same menu to the same table again is rejected as a double tap. 0 turns the check off
+    pub fn duplicate_add_window_seconds(conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+        Ok(Setting::get(conn, "duplicate_add_window_seconds")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0))
+    }
+
+    /// Whether orders are refused at `minute` (minutes since midnight): from last_order_minutes before
+    /// closing_time until closing time. Without a closing_time orders are always taken
+    pub fn past_last_orders(conn: &rusqlite::Connection, minute: i64) -> rusqlite::Result<bool> {
+        let closing = match Setting::get(conn, "closing_time")?.as_deref().and_then(parse_time_of_day) {
+            Some(closing) => closing,
+            None => return Ok(false),
+        };
+        let last_order_minutes: i64 = Setting::get(conn, "last_order_minutes")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(0);
+        // Minutes left until closing, wrapping past midnight
+        let until_closing = (closing - minute).rem_euclid(MINUTES_PER_DAY);
+        Ok(until_closing > 0 && until_closing <= last_order_minutes)
+    }
+
+    /// Item statuses from which quantity edits are blocked. Defaults to ready (ready and served items)
+    pub fn locked_item_statuses(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<&'static str>> {
+        let statuses = match Setting::get(conn, "lock_items_from")?.as_deref() {
+            Some("off") => vec![],
+            Some("served") => vec!["served"],
+            _ => vec!["ready", "served"],
+        };
+        Ok(statuses)
+    }
+
+    /// How many decimal places a menu price may have. 2 (whole cents) by default, 0 for currencies without minor units
+    pub fn price_decimal_places(conn: &rusqlite::Connection) -> rusqlite::Result<u32> {
+        Ok(Setting::get(conn, "price_decimal_places")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(2))
+    }
+
+    /// List replies with more items than this carry the X-Result-Truncated-Hint header, 100 by default
+    pub fn large_list_items(conn: &rusqlite::Connection) -> rusqlite::Result<usize> {
+        Ok(Setting::get(conn, "large_list_items")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(100))
+    }
+
+    /// Most of one menu a single order item may hold, 1000 by default
+    pub fn max_line_quantity(conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+        Ok(Setting::get(conn, "max_line_quantity")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1000))
+    }
+
+    /// Factor applied to wait and kitchen load estimates during a rush. 1 means no rush
+    pub fn rush_multiplier(conn: &rusqlite::Connection) -> rusqlite::Result<f64> {
+        Ok(Setting::get(conn, "rush_multiplier")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1.0))
+    }
+
+    /// Discount in cents a single loyalty point is worth
+    pub fn points_redemption_cents(conn: &rusqlite::Connection) -> rusqlite::Result<i64> {
+        Ok(Setting::get(conn, "points_redemption_cents")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(1))
+    }
+
+    /// Prefix and zero padding of the daily order number. Defaults to #001 style numbers
+    pub fn order_number_format(conn: &rusqlite::Connection) -> rusqlite::Result<(String, i64)> {
+        let prefix = Setting::get(conn, "order_number_prefix")?.unwrap_or_else(|| "#".to_string());
+        let digits = Setting::get(conn, "order_number_digits")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(3);
+        Ok((prefix, digits))
+    }
+
+    /// Prefix and zero padding of auto-numbered table codes. Defaults to T-01 style codes
+    pub fn table_code_format(conn: &rusqlite::Connection) -> rusqlite::Result<(String, i64)> {
+        let prefix = Setting::get(conn, "table_code_prefix")?.unwrap_or_else(|| "T-".to_string());
+        let digits = Setting::get(conn, "table_code_digits")?
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(2);
+        Ok((prefix, digits))
+    }
+}
+
+impl OrderDiff {
+
+    /// Current version of an order
+    pub fn current_version(conn: &rusqlite::Connection, order_id: i64) -> rusqlite::Result<i64> {
+        conn.query_row("SELECT version FROM orders WHERE id = ?1", params![order_id], |row| row.get(0))
+    }
+
+    /// Changes recorded for the order after version `since`, sorted into added, removed and changed items
+    pub fn since(conn: &rusqlite::Connection, order_id: i64, version: i64, since: i64) -> rusqlite::Result<OrderDiff> {
+        let query = "
+        SELECT c.change, c.version, c.menu_id, m.name, c.quantity_before, c.quantity_after, c.item_status
+        FROM order_item_changes as c
+        LEFT JOIN menus as m ON m.id = c.menu_id
+        WHERE c.order_id = ?1 AND c.version > ?2
+        ORDER BY c.version, c.id";
+        let mut stmt = conn.prepare(query)?;
+        let mut rows = stmt.query(params![order_id, since])?;
+        let mut diff = OrderDiff { order_id, since, version, added: Vec::new(), removed: Vec::new(), changed: Vec::new() };
+        while let Some(row) = rows.next()? {
+            let change: String = row.get(0)?;
+            let item = OrderItemChange {
+                version: row.get(1)?,
+                menu_id: row.get(2)?,
+                menu_name: row.get(3)?,
+                quantity_before: row.get(4)?,
+                quantity_after: row.get(5)?,
+                item_status: row.get(6)?,
+            };
+            match change.as_str() {
+                "added" => diff.added.push(item),
+                "removed" => diff.removed.push(item),
+                _ => diff.changed.push(item),
+            }
+        }
+        Ok(diff)
+    }
+}
+
+/// Functions for the report models
+impl PrepAccuracy {
+
+    /// Average estimated (cooking_time) and actual (served_at - fired_at) minutes per menu, rounded to a tenth of a minute
+    pub fn list(conn: &rusqlite::Connection) -> rusqlite::Result<Vec<PrepAccuracy>> {
+        let query = "
+        SELECT m.id, m.name, COUNT(*), AVG(oi.cooking_time),
+            AVG((julianday(oi.served_at) - julianday(oi.fired_at)) * 1440)
+        FROM order_items as oi
+        JOIN orders as o ON o.id = oi.order_id
+        JOIN menus as m ON m.id = oi.menu_id
+        WHERE oi.fired_at IS NOT NULL AND oi.served_at IS NOT NULL AND o.status != 'void'
+        GROUP BY m.id, m.name
+        ORDER BY m.id";
+        let mut stmt = conn.prepare(query)?;
+        let rows = stmt.query_map(params![], |row| {
+            let estimated: f64 = row.get(3)?;
+            let actual: f64 = row.get(4)?;
+            Ok(PrepAccuracy {
+                menu_id: row.get(0)?,
+                menu_name: row.get(1)?,
+                items: row.get(2)?,
+                avg_estimated_minutes: round_tenth(estimated),
+                avg_actual_minutes: round_tenth(actual),
+                delta_minutes: round_tenth(actual - estimated),
+            })
+        })?;
+        let result: Result<Vec<_>, _> = rows.collect();
+        result
+    }
+}
+
+fn round_tenth(minutes: f64) -> f64 {
+    (minutes * 10.0).round() / 10.0
+}
+
+impl TablePerformance {
+
+    /// Orders and revenue per table for orders created between `from` and `to` (inclusive).
+    /// Voided orders are left out and discounts are taken off. Tables without orders in the window are listed with zeros
+    pub fn list(conn: &rusqlite::Connection, from: &str, to: &str) -> rusqlite::Result<Vec<TablePerformance>> {
+        let query = format!("
+        SELECT t.id, t.code, COUNT(o.id), COALESCE(SUM((
+            SELECT COALESCE(SUM({}), 0)
+            FROM order_items as oi
+            JOIN menus as m ON m.id = oi.menu_id
+            WHERE oi.order_id = o.id
+        ) - o.discount_cents), 0)
+        FROM tables as t
+        LEFT JOIN orders as o ON o.table_id = t.id AND o.status != 'void' AND date(o.created_at) BETWEEN date(?1) AND date(?2)
+        GROUP BY t.id, t.code
+        ORDER BY t.id", LINE_CENTS);
+        let mut stmt = conn.prepare(&query)?;
+        let rows = stmt.query_map(params![from, to], |row| {
+            Ok(TablePerformance {
+                table_id: row.get(0)?,
+                table_code: row.get(1)?,
+                orders_served: row.get(2)?,
+                revenue: to_amount(row.get(3)?),
+            })
+        })?;
+        let result: Result<Vec<_>, _> = rows.collect();
+        result
+    }
+}
+
+/// Convert cents to a currency amount for responses. Money math is always done in cents
+pub fn to_amount(cents: i64) -> f64 {
+    cents as f64 / 100.0
+}
+
+/// Whether a price has no more than `places` decimal places, e.g. 9.99 has 2 and 9.999 has 3.
+/// Allows for the binary representation, 9.99 * 100 is not exactly 999
+pub fn has_decimal_places_within(price: f64, places: u32) -> bool {
+    let scaled = price * 10f64.powi(places as i32);
+    (scaled - scaled.round()).abs() < 1e-6
+}
+
+/// Check that a value is a real calendar date in YYYY-MM-DD format
+pub fn is_valid_date(conn: &rusqlite::Connection, value: &str) -> rusqlite::Result<bool> {
+    conn.query_row("SELECT date(?1) IS NOT NULL AND date(?1) = ?1", params![value], |row| row.get(0))
+}
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/// Minutes since midnight of an HH:MM time of day
+fn parse_time_of_day(value: &str) -> Option<i64> {
+    let (hours, minutes) = value.split_once(':')?;
+    if hours.len() != 2 || minutes.len() != 2 {
+        return None;
+    }
+    match (hours.parse::<i64>(), minutes.parse::<i64>()) {
+        (Ok(hours @ 0..=23), Ok(minutes @ 0..=59)) => Some(hours * 60 + minutes),
+        _ => None,
+    }
+}
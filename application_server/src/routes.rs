@@ -1,152 +1,1164 @@
 // src/routes.rs
 use crate::handlers::{
     create_order_handler,
+    batch_create_orders_handler,
+    modify_order_handler,
+    order_diff_handler,
+    get_order_total_handler,
     list_table_handler,
     create_table_handler,
+    create_tables_bulk_handler,
+    create_next_table_handler,
     list_menu_handler,
     create_menu_handler,
+    menu_of_the_day_handler,
+    schedule_menu_of_the_day_handler,
+    create_menu_snapshot_handler,
+    restore_menu_snapshot_handler,
+    merge_menus_handler,
+    adjust_prices_handler,
+    set_category_availability_handler,
+    eighty_six_handler,
+    archive_menu_handler,
     list_order_handler,
     delete_order_item_handler,
+    delete_order_handler,
     list_order_items_for_table_handler,
-    get_order_item_for_table_handler
+    get_order_item_for_table_handler,
+    set_order_item_quantity_handler,
+    move_order_handler,
+    table_performance_handler,
+    eod_report_handler,
+    covers_handler,
+    turnover_handler,
+    prep_accuracy_handler,
+    enums_handler,
+    slow_queries_handler,
+    health_handler,
+    live_handler,
+    list_settings_handler,
+    update_setting_handler,
+    void_order_handler,
+    reprint_receipt_handler,
+    generate_bill_handler,
+    order_audit_handler,
+    order_timeline_handler,
+    on_the_pass_handler,
+    events_handler,
+    kitchen_ws_handler,
+    ready_to_serve_handler,
+    throughput_handler,
+    station_queue_handler,
+    items_by_time_handler,
+    update_item_status_handler,
+    serve_table_handler,
+    update_order_status_handler,
+    order_ticket_handler,
+    search_menu_handler,
+    list_menus_by_category_handler,
+    list_menu_categories_handler,
+    menu_margins_handler,
+    get_menu_handler,
+    update_menu_handler,
+    unused_tables_handler,
+    list_tables_with_status_handler,
+    get_table_eta_handler,
+    order_eta_handler,
+    order_prep_breakdown_handler,
+    backup_handler,
+    restore_handler,
+    comp_item_handler,
+    import_handler,
+    get_order_by_number_handler,
+    floor_status_handler,
+    tip_suggestions_handler,
+    estimated_wait_handler,
+    create_customer_handler,
+    attach_customer_handler,
+    pay_order_handler,
+    customer_history_handler,
+    customer_points_handler,
+    redeem_points_handler
 };
+use crate::models::{AvailabilityUpdate, CookingTimeQuery, DateRangeQuery, MenuCategoryQuery, MenuListQuery, MenuOfTheDayQuery, MenuSearchQuery, MoveOrderRequest, OrderDiffQuery, OrderNumberQuery, PageQuery, PayOrderQuery, RemoveItemQuery, RequestContext, ThroughputQuery, THROUGHPUT_WINDOW_MINUTES};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
 use warp::{Filter, Rejection, Reply};
-use rusqlite::Connection;
-use crate::db::get_db_conn;
+use crate::clock::{system_clock, Clock};
+use crate::cooking_time::{random_cooking_time, CookingTimeSource};
+use crate::error::ApiError;
+use crate::db::{conn_for_request, get_db_conn, CancelHandle, PooledConnection};
 use std::convert::Infallible;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Time a handler may take before the client gets a 504. REQUEST_TIMEOUT_MS overrides it
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Reports aggregate many rows and get longer. REPORT_TIMEOUT_MS overrides it
+const REPORT_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Middleware to handle errors and convert them into a JSON response
-/// For now it handles Route Not Found and Deserialization Error.
+/// It handles the ApiError returned by handlers, Route Not Found and Deserialization Error.
 /// We can add custom action to handle different type of error
-async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
-
-    // If route not found
-    if err.is_not_found() {
-        Ok(warp::reply::with_status(
-            warp::reply::json(&format!("Mahadi Error: {:?}", err)),
-            warp::http::StatusCode::NOT_FOUND,
-        ))
-    } else if let Some(_) = err.find::<warp::filters::body::BodyDeserializeError>() {
-        // If fail to deserialize request body
-        Ok(warp::reply::with_status(
-            warp::reply::json(&format!("Error: Failed to deserialize request body")),
-            warp::http::StatusCode::BAD_REQUEST,
-        ))
+pub async fn handle_rejection(err: Rejection) -> Result<impl Reply, Rejection> {
+
+    let fallback;
+    let api_err = if let Some(api_err) = err.find::<ApiError>() {
+        api_err
     } else {
-        // Default to Internal Server Error for other errors
-        Ok(warp::reply::with_status(
-            warp::reply::json(&format!("Error: {:?}", err)),
-            warp::http::StatusCode::INTERNAL_SERVER_ERROR,
-        ))
+        fallback = if err.is_not_found() {
+            // If route not found
+            ApiError::NotFound("Route not found".to_string())
+        } else if err.find::<warp::filters::body::BodyDeserializeError>().is_some() {
+            // If fail to deserialize request body
+            ApiError::Validation("Failed to deserialize request body".to_string())
+        } else if err.find::<warp::reject::InvalidQuery>().is_some() {
+            // If the query string has missing or badly typed parameters
+            ApiError::Validation("Invalid query string".to_string())
+        } else {
+            // Default to Internal Server Error for other errors
+            ApiError::Internal(format!("{:?}", err))
+        };
+        &fallback
+    };
+    if let Some(detail) = api_err.log_detail() {
+        tracing::error!("{}", detail);
     }
+    Ok(warp::reply::with_status(
+        warp::reply::json(&api_err.to_json()),
+        api_err.status(),
+    ))
 }
 
 /// Helper function to provide a database connection to route handlers
-/// Returns a New Db connection Per Route
-fn with_db() -> impl Filter<Extract = (Connection,), Error = Infallible> + Clone {
-    warp::any().map(|| get_db_conn())
+/// Returns a pooled Db connection Per Route, given back to the pool once the handler is done.
+/// With debug endpoints on, `?__fail=db` swaps it for one whose queries all fail
+fn with_db() -> impl Filter<Extract = (PooledConnection,), Error = Infallible> + Clone {
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .map(|query: String| conn_for_request(debug_endpoints(), &query, get_db_conn))
+}
+
+/// Helper function to provide the server clock to handlers with time dependent rules
+fn with_clock() -> impl Filter<Extract = (Arc<dyn Clock>,), Error = Infallible> + Clone {
+    warp::any().map(system_clock)
+}
+
+/// Helper function to provide the cooking times of new order items to handlers
+fn with_cooking_times() -> impl Filter<Extract = (Arc<dyn CookingTimeSource>,), Error = Infallible> + Clone {
+    warp::any().map(random_cooking_time)
+}
+
+/// Helper function to tell handlers who is making the request, from the x-actor and x-role headers
+fn with_context() -> impl Filter<Extract = (RequestContext,), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-actor")
+        .and(warp::header::optional::<String>("x-role"))
+        .and(warp::header::optional::<String>("x-request-id"))
+        .map(|actor, role, request_id: Option<String>| RequestContext {
+            actor,
+            role,
+            request_id: Some(request_id.unwrap_or_else(|| format!("{:016x}", rand::random::<u64>()))),
+        })
+}
+
+/// Debug only behaviour, off unless DEBUG_ENDPOINTS is set to 1 or true
+fn debug_endpoints() -> bool {
+    matches!(std::env::var("DEBUG_ENDPOINTS").as_deref(), Ok("1") | Ok("true"))
+}
+
+/// Reject request bodies with fields the target type does not know, off unless STRICT_JSON_BODIES is set to 1 or true
+fn strict_json_bodies() -> bool {
+    matches!(std::env::var("STRICT_JSON_BODIES").as_deref(), Ok("1") | Ok("true"))
+}
+
+/// Helper function to read a JSON request body, rejecting unknown fields as a 400 in strict mode
+fn json_body<T>() -> impl Filter<Extract = (T,), Error = Rejection> + Clone
+where
+    T: DeserializeOwned + Serialize + Send,
+{
+    warp::body::json::<Value>()
+        .and_then(|body: Value| async move { parse_body(body, strict_json_bodies()).map_err(warp::reject::custom) })
+}
+
+/// Deserialize a request body. In strict mode the body is compared with the parsed value written back out,
+/// anything only in the body is a field serde ignored
+pub(crate) fn parse_body<T: DeserializeOwned + Serialize>(body: Value, strict: bool) -> Result<T, ApiError> {
+    let parsed: T = serde_json::from_value(body.clone())
+        .map_err(|err| ApiError::Validation(format!("invalid request body: {}", err)))?;
+    if strict {
+        let known = serde_json::to_value(&parsed)
+            .map_err(|err| ApiError::Validation(format!("invalid request body: {}", err)))?;
+        let mut unknown = Vec::new();
+        unknown_fields(&body, &known, "", &mut unknown);
+        if !unknown.is_empty() {
+            return Err(ApiError::Validation(format!("unknown fields: {}", unknown.join(", "))));
+        }
+    }
+    Ok(parsed)
+}
+
+fn unknown_fields(body: &Value, known: &Value, path: &str, unknown: &mut Vec<String>) {
+    match (body, known) {
+        (Value::Object(fields), Value::Object(known_fields)) => {
+            for (name, value) in fields {
+                let field = if path.is_empty() { name.clone() } else { format!("{}.{}", path, name) };
+                match known_fields.get(name) {
+                    Some(known_value) => unknown_fields(value, known_value, &field, unknown),
+                    None => unknown.push(field),
+                }
+            }
+        }
+        (Value::Array(items), Value::Array(known_items)) => {
+            for (index, (item, known_item)) in items.iter().zip(known_items).enumerate() {
+                unknown_fields(item, known_item, &format!("{}[{}]", path, index), unknown);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Rejects as NOT_FOUND unless debug endpoints are on, so the route looks like it does not exist
+fn debug_only() -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::any()
+        .and_then(|| async {
+            if debug_endpoints() {
+                Ok(())
+            } else {
+                Err(warp::reject::not_found())
+            }
+        })
+        .untuple_one()
+}
+
+fn timeout_from_env(var: &str, default: Duration) -> Duration {
+    std::env::var(var)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .map(Duration::from_millis)
+        .unwrap_or(default)
 }
 
-/// This Route lists all orders. GET request
+fn request_timeout() -> Duration {
+    timeout_from_env("REQUEST_TIMEOUT_MS", REQUEST_TIMEOUT)
+}
+
+fn report_timeout() -> Duration {
+    timeout_from_env("REPORT_TIMEOUT_MS", REPORT_TIMEOUT)
+}
+
+/// Run a handler with a time limit and reject with a 504 ApiError when it is exceeded.
+/// The handler runs as its own task so the limit holds even while it is blocked on the database. Its queries run
+/// synchronously and cannot be stopped at an await, so on timeout `cancel` interrupts the statement running on
+/// its connection and makes every later commit roll back. A client answered 504 can retry without its first
+/// attempt having written anything
+pub async fn with_timeout<F, R>(limit: Duration, cancel: CancelHandle, handler: F) -> Result<warp::reply::Response, Rejection>
+where
+    F: Future<Output = Result<R, Rejection>> + Send + 'static,
+    R: Reply + Send + 'static,
+{
+    let mut task = tokio::spawn(handler);
+    match tokio::time::timeout(limit, &mut task).await {
+        Ok(Ok(result)) => result.map(Reply::into_response),
+        // The handler panicked or was cancelled
+        Ok(Err(err)) => Err(ApiError::Internal(err.to_string()).into()),
+        Err(_) => {
+            cancel.cancel();
+            task.abort();
+            Err(ApiError::Timeout("request timed out".to_string()).into())
+        }
+    }
+}
+
+/// This Route lists all orders a page at a time. /orders?limit=50&offset=0
+/// limit defaults to 50 and is capped at 200, a negative offset returns BAD REQUEST
 pub fn list_all_orders_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("orders")
+    warp::path!("orders")
         .and(warp::get())
         .and(with_db())
-        .and_then(|conn| list_order_handler(conn))
+        .and(warp::query::<PageQuery>())
+        .and_then(|conn: PooledConnection, page| with_timeout(request_timeout(), conn.cancel_handle(), list_order_handler(conn, page)))
 }
 
 
+/// This Route lists open orders for the floor dashboard, oldest first. /floor
+/// Each order has an urgency (green, amber, red) from the floor_amber_minutes and floor_red_minutes settings
+pub fn floor_status_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("floor")
+        .and(warp::get())
+        .and(with_db())
+        .and(with_clock())
+        .and_then(|conn: PooledConnection, clock| with_timeout(request_timeout(), conn.cancel_handle(), floor_status_handler(conn, clock)))
+}
+
+/// This Route fetches an order by its daily number. /orders/number/{order_number}?date=YYYY-MM-DD
+/// The date defaults to today. The "#" prefix can be left out of the path
+pub fn get_order_by_number_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/"number"/String)
+        .and(warp::get())
+        .and(with_db())
+        .and(warp::query::<OrderNumberQuery>())
+        .and(with_clock())
+        .and_then(|order_number, conn: PooledConnection, query: OrderNumberQuery, clock| with_timeout(request_timeout(), conn.cancel_handle(), get_order_by_number_handler(conn, order_number, query.date, clock)))
+}
+
 /// This Route creates a new order
-/// Its a POST request and expects table_id: i64 and menu_ids: vec![i64]
-/// If menu_ids is empty, return BAD REQUEST
+/// Its a POST request and expects table_id: i64 and menu_ids: vec![i64], or items: [{menu_id, quantity, notes}] instead of menu_ids
+/// An optional customer_id attaches that customer to the order
+/// If menu_ids and items are empty or a quantity is 0, return BAD REQUEST
+/// If any of the menus is unavailable, return CONFLICT with their names
+/// If the same menus were added to the table within duplicate_add_window_seconds, return CONFLICT unless force is true
+/// From last_order_minutes before closing_time until closing, return SERVICE UNAVAILABLE
 /// If there is already existing order (status=0) for this table_id, try to add new items t the existing order. Return success or error message
+/// on_existing: "reject" returns CONFLICT instead, "replace" takes the existing items off first
 /// If no exisiting order or order with (status=1), creates a new order and return id
 pub fn create_order_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("orders"/"create")
+    warp::path!("orders"/"create")
         .and(warp::post())
         .and(with_db())
-        .and(warp::body::json())
-        .and_then(|conn, req_body| create_order_handler(conn, req_body))
-        
+        .and(json_body())
+        .and(with_context())
+        .and(with_clock())
+        .and(with_cooking_times())
+        .and_then(|conn: PooledConnection, req_body, context, clock, cooking_times| with_timeout(request_timeout(), conn.cancel_handle(), create_order_handler(conn, req_body, context, clock, cooking_times)))
+}
+
+/// This Route creates many orders in one call, for load tests and demos. /orders/batch
+/// It expects an array of create order bodies, at most MAX_BATCH_ORDERS. Each is created on its own and
+/// the results list the status and body every order got, a failing order does not stop the others
+pub fn batch_create_orders_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/"batch")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and(with_context())
+        .and(with_clock())
+        .and(with_cooking_times())
+        .and_then(|conn: PooledConnection, req_body, context, clock, cooking_times| with_timeout(request_timeout(), conn.cancel_handle(), batch_create_orders_handler(conn, req_body, context, clock, cooking_times)))
+}
+
+/// This Route applies several operations to the running order of a table in one go. /orders/{table_id}
+/// Its a patch request with {"operations": [{"op": "add"|"remove", "menu_id": ..}, {"op": "set_quantity", "menu_id": .., "quantity": ..}]}
+/// Either every operation is applied and the resulting order returned, or none is and the failing operation is reported
+pub fn modify_order_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64)
+        .and(warp::patch())
+        .and(with_db())
+        .and(json_body())
+        .and(with_context())
+        .and(with_cooking_times())
+        .and_then(|table_id, conn: PooledConnection, data, context, cooking_times| with_timeout(request_timeout(), conn.cancel_handle(), modify_order_handler(conn, table_id, data, context, cooking_times)))
+}
+
+/// This Route lists what changed in the items of a table's running order since a version. /orders/{table_id}/diff?since=3
+/// The current version is part of every order response
+pub fn order_diff_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64/"diff")
+        .and(warp::get())
+        .and(warp::query::<OrderDiffQuery>())
+        .and(with_db())
+        .and_then(|table_id, query: OrderDiffQuery, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), order_diff_handler(conn, table_id, query.since)))
 }
 
 /// This Route to delete specific menu from table.
 /// Its a delete request. /orders/{table_id}/items/{item_id}
 /// If item found for this table, deleted the item and return success/error message 
 /// If this is the las item in this table, update order status=1 marking it as complete
+/// Ready or served items return 409 unless a manager sends ?force=true
 pub fn delete_item_from_order_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("orders"/i64/"items"/i64)
+    warp::path!("orders"/i64/"items"/i64)
         .and(warp::delete())
         .and(with_db())
-        .and_then(|table_id, menu_id, conn| delete_order_item_handler(conn, table_id, menu_id))
+        .and(warp::query::<RemoveItemQuery>())
+        .and(with_context())
+        .and_then(|table_id, menu_id, conn: PooledConnection, query, context| with_timeout(request_timeout(), conn.cancel_handle(), delete_order_item_handler(conn, table_id, menu_id, query, context)))
         
 }
 
-/// This Route lists all tables
+/// This Route cancels the running order of a table, deleting it with its items. /orders/{table_id}
+/// Its a delete request. Returns NOT FOUND without a running order and CONFLICT when the order has audit history
+pub fn delete_order_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64)
+        .and(warp::delete())
+        .and(with_db())
+        .and_then(|table_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), delete_order_handler(conn, table_id)))
+}
+
+/// This Route voids the running order of a table. /orders/{table_id}/void
+/// It expects a reason in the POST body. The order is kept for history with status=void
+pub fn void_order_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64/"void")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|table_id, conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), void_order_handler(conn, table_id, req_body)))
+}
+
+/// This Route returns the kitchen ticket of an order as plain text for a 40 column printer. /tickets/{order_id}
+pub fn order_ticket_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tickets"/i64)
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|order_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), order_ticket_handler(conn, order_id)))
+}
+
+/// This Route moves the running order of a table to another status. /orders/{table_id}/status
+/// It expects a status (preparing, served, paid) in the PUT body, an order never moves back
+pub fn update_order_status_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64/"status")
+        .and(warp::put())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|table_id, conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), update_order_status_handler(conn, table_id, req_body)))
+}
+
+/// This Route pays the running order of a table. /orders/{table_id}/pay
+/// The order is kept for history with status=paid and the table can take a new order
+/// The customer attached to the order, if any, earns loyalty points
+/// An order with nothing to charge needs ?comp_reason=..., otherwise it returns 400
+pub fn pay_order_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64/"pay")
+        .and(warp::post())
+        .and(with_db())
+        .and(warp::query::<PayOrderQuery>())
+        .and(with_context())
+        .and_then(|table_id, conn: PooledConnection, query, context| with_timeout(request_timeout(), conn.cancel_handle(), pay_order_handler(conn, table_id, query, context)))
+}
+
+/// This Route returns the itemized bill of the running order of a table. /tables/{table_id}/bill
+/// Returns NOT FOUND when the table has no running order
+pub fn generate_bill_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"bill")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|table_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), generate_bill_handler(conn, table_id)))
+}
+
+/// This Route reprints the receipt of an order. /receipts/{order_id}/reprint
+/// The reprint is recorded in the audit log with the actor from the x-actor header
+pub fn reprint_receipt_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("receipts"/i64/"reprint")
+        .and(warp::post())
+        .and(with_db())
+        .and(with_context())
+        .and_then(|order_id, conn: PooledConnection, context| with_timeout(request_timeout(), conn.cancel_handle(), reprint_receipt_handler(conn, order_id, context)))
+}
+
+/// This Route lists everything that happened to an order, oldest first. /audit/orders/{order_id}/timeline
+/// Returns NOT FOUND for an unknown order
+pub fn order_timeline_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("audit"/"orders"/i64/"timeline")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|order_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), order_timeline_handler(conn, order_id)))
+}
+
+/// This Route lists the audit log of an order. /audit/orders/{order_id}
+pub fn order_audit_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("audit"/"orders"/i64)
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|order_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), order_audit_handler(conn, order_id)))
+}
+
+/// This Route redeems loyalty points as a discount on the running order of a table. /orders/{table_id}/redeem
+/// It expects the number of points in the POST body, taken from the customer attached to the order
+pub fn redeem_points_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64/"redeem")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|table_id, conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), redeem_points_handler(conn, table_id, req_body)))
+}
+
+/// This Route attaches a customer to the running order of a table. /orders/{table_id}/customer
+/// It expects a customer_id in the PATCH body
+pub fn attach_customer_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64/"customer")
+        .and(warp::patch())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|table_id, conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), attach_customer_handler(conn, table_id, req_body)))
+}
+
+/// This Route creates a customer.
+/// It expects a name and an optional phone in the POST body. Returns id on successfull creation
+pub fn create_customer_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("customers"/"create")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), create_customer_handler(conn, req_body)))
+}
+
+/// This Route lists the paid orders of a customer, newest first. /customers/{customer_id}/history
+pub fn customer_history_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("customers"/i64/"history")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|customer_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), customer_history_handler(conn, customer_id)))
+}
+
+/// This Route returns the loyalty points balance of a customer. /customers/{customer_id}/points
+pub fn customer_points_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("customers"/i64/"points")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|customer_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), customer_points_handler(conn, customer_id)))
+}
+
+/// This Route lists all tables a page at a time. /tables?limit=50&offset=0
+/// Paging works as for /orders
 pub fn list_tables_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("tables")
+    warp::path!("tables")
         .and(warp::get())
         .and(with_db())
-        .and_then(|conn| list_table_handler(conn))
+        .and(warp::query::<PageQuery>())
+        .and_then(|conn: PooledConnection, page| with_timeout(request_timeout(), conn.cancel_handle(), list_table_handler(conn, page)))
+}
+
+/// This Route lists the tables that never had an order. /tables/unused
+pub fn unused_tables_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/"unused")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), unused_tables_handler(conn)))
+}
+
+/// This Route lists every table by code with whether it is occupied. /tables/status
+/// Occupied tables also carry the id of their running order and its item count
+pub fn list_tables_with_status_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/"status")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), list_tables_with_status_handler(conn)))
 }
 
 /// This Route creates a table.
-/// It expects a code in the request POST body. Returns id on successfull creation
+/// It expects a code in the request POST body, without one the next auto-numbered code is used. Returns id on successfull creation
 pub fn create_table_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("tables"/"create")
+    warp::path!("tables"/"create")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), create_table_handler(conn, req_body)))
+}
+
+/// This Route creates many tables in one request. /tables/bulk
+/// It expects a list of {"code": String} in the POST body. Returns the created ids and the codes skipped because they exist
+pub fn create_tables_bulk_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/"bulk")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), create_tables_bulk_handler(conn, req_body)))
+}
+
+/// This Route creates a table with the next auto-numbered code. /tables/next
+/// Codes use the table_code_prefix and table_code_digits settings, T-01 style by default
+pub fn create_next_table_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/"next")
         .and(warp::post())
         .and(with_db())
-        .and(warp::body::json())
-        .and_then(|conn, req_body| create_table_handler(conn, req_body))
+        .and_then(|conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), create_next_table_handler(conn)))
+}
+
+/// This Route returns the total of a table's running order, quantity * price before tax and service charge.
+/// /tables/{table_id}/total. Returns NOT FOUND when the table has no running order
+pub fn get_order_total_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"total")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|table_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), get_order_total_handler(conn, table_id)))
 }
 
 /// This Route lists all menus for a table. /tables/{table_id}/items
 pub fn list_order_items_for_table_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("tables"/i64/"items")
+    warp::path!("tables"/i64/"items")
         .and(warp::get())
         .and(with_db())
-        .and_then(|table_id, conn| list_order_items_for_table_handler(conn, table_id))
+        .and_then(|table_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), list_order_items_for_table_handler(conn, table_id)))
 }
 
 /// This Route retrieves a specific menu for table. /tables/{table_id}/items/{item_id}
 pub fn get_item_from_order_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("tables"/i64/"items"/i64)
+    warp::path!("tables"/i64/"items"/i64)
         .and(warp::get())
         .and(with_db())
-        .and_then(|table_id, menu_id, conn| get_order_item_for_table_handler(conn, table_id, menu_id))
+        .and_then(|table_id, menu_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), get_order_item_for_table_handler(conn, table_id, menu_id)))
         
 }
 
-/// This Route lists all menus
+/// This Route reseats the running order of a table at another table. /orders/move
+/// It expects {"from_table_id": .., "to_table_id": ..} in the POST body and returns the moved order.
+/// NOT FOUND without a running order or destination table, CONFLICT when the destination is occupied
+pub fn move_order_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/"move")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|conn: PooledConnection, req_body: MoveOrderRequest| with_timeout(request_timeout(), conn.cancel_handle(), move_order_handler(conn, req_body.from_table_id, req_body.to_table_id)))
+}
+
+/// This Route sets the quantity of a menu on a table's running order. /tables/{table_id}/items/{menu_id}
+/// It expects {"quantity": n} in the PATCH body, 0 takes the item off. BAD REQUEST for a negative quantity,
+/// NOT FOUND when the menu is not on the order and CONFLICT for ready or served items
+pub fn set_order_item_quantity_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"items"/i64)
+        .and(warp::patch())
+        .and(with_db())
+        .and(json_body())
+        .and(with_context())
+        .and_then(|table_id, menu_id, conn: PooledConnection, req_body, context| with_timeout(request_timeout(), conn.cancel_handle(), set_order_item_quantity_handler(conn, table_id, menu_id, req_body, context)))
+}
+
+/// This Route suggests tip amounts for the running order of a table. /tables/{table_id}/tips
+pub fn tip_suggestions_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"tips")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|table_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), tip_suggestions_handler(conn, table_id)))
+}
+
+/// This Route breaks the running order of a table down per item. /tables/{table_id}/prep
+/// Each item has its status, cooking_time, elapsed_minutes and remaining_minutes. Returns NOT FOUND without a running order
+pub fn order_prep_breakdown_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"prep")
+        .and(warp::get())
+        .and(with_db())
+        .and(with_clock())
+        .and_then(|table_id, conn: PooledConnection, clock| with_timeout(request_timeout(), conn.cancel_handle(), order_prep_breakdown_handler(conn, table_id, clock)))
+}
+
+/// This Route returns the minutes until the running order of a table is cooked. /tables/{table_id}/eta
+/// Returns NOT FOUND when the table has no running order
+pub fn table_eta_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"eta")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|table_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), get_table_eta_handler(conn, table_id)))
+}
+
+/// This Route returns when the running order of a table should be cooked. /orders/{table_id}/eta
+/// estimated_ready_at is an ISO timestamp in UTC. Returns NOT FOUND when the table has no running order
+pub fn order_eta_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("orders"/i64/"eta")
+        .and(warp::get())
+        .and(with_db())
+        .and(with_clock())
+        .and_then(|table_id, conn: PooledConnection, clock| with_timeout(request_timeout(), conn.cancel_handle(), order_eta_handler(conn, table_id, clock)))
+}
+
+/// This Route estimates the wait for the running order of a table and the kitchen load. /tables/{table_id}/estimate
+/// Both are scaled by the rush_multiplier setting, changed with PUT /settings/rush_multiplier
+pub fn estimated_wait_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"estimate")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|table_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), estimated_wait_handler(conn, table_id)))
+}
+
+/// This Route changes the kitchen status of a menu on a table. /tables/{table_id}/items/{item_id}/status
+/// It expects a status (pending, cooking, ready, served) in the PUT body
+pub fn update_item_status_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"items"/i64/"status")
+        .and(warp::put())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|table_id, menu_id, conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), update_item_status_handler(conn, table_id, menu_id, req_body)))
+}
+
+/// This Route serves every ready item of a table's running order at once. /tables/{table_id}/serve
+/// Returns how many items were served, NOT FOUND when the table has no running order
+pub fn serve_table_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"serve")
+        .and(warp::post())
+        .and(with_db())
+        .and_then(|table_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), serve_table_handler(conn, table_id)))
+}
+
+/// This Route comps a menu on a table, it stays on the order for the kitchen but is not charged. /tables/{table_id}/items/{item_id}/comp
+/// It expects a reason in the PATCH body and the x-role: manager header
+pub fn comp_item_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("tables"/i64/"items"/i64/"comp")
+        .and(warp::patch())
+        .and(with_db())
+        .and(json_body())
+        .and(with_context())
+        .and_then(|table_id, menu_id, conn: PooledConnection, req_body, context| with_timeout(request_timeout(), conn.cancel_handle(), comp_item_handler(conn, table_id, menu_id, req_body, context)))
+}
+
+/// This Route lists the menus of one category by name. /menus?category=drinks
+/// Without a category param the request falls through to the paged list below
+pub fn list_menus_by_category_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus")
+        .and(warp::get())
+        .and(warp::query::<MenuCategoryQuery>())
+        .and(with_db())
+        .and_then(|query: MenuCategoryQuery, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), list_menus_by_category_handler(conn, query.category)))
+}
+
+/// This Route lists the distinct categories of all menus. /menus/categories
+pub fn list_menu_categories_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/"categories")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), list_menu_categories_handler(conn)))
+}
+
+/// This Route lists all menus a page at a time. /menus?sort=name&limit=50&offset=0&include_inactive=true
+/// Without a sort param the default_menu_sort setting is used. Paging works as for /orders.
+/// Archived menus are only listed with include_inactive=true
 pub fn list_menus_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("menus")
+    warp::path!("menus")
         .and(warp::get())
         .and(with_db())
-        .and_then(|conn| list_menu_handler(conn))
+        .and(warp::query::<MenuListQuery>())
+        .and(warp::query::<PageQuery>())
+        .and_then(|conn: PooledConnection, query: MenuListQuery, page| with_timeout(request_timeout(), conn.cancel_handle(), list_menu_handler(conn, query.sort, query.include_inactive, page)))
         
 }
 
+/// This Route returns a single menu. /menus/{menu_id}
+/// Returns NOT FOUND when there is no menu with this id
+pub fn get_menu_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/i64)
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|menu_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), get_menu_handler(conn, menu_id)))
+}
+
+/// This Route renames and reprices a menu. /menus/{menu_id}
+/// It expects a name and price in the PUT body. Returns NOT FOUND for an unknown menu and CONFLICT when the name is taken
+pub fn update_menu_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/i64)
+        .and(warp::put())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|menu_id, conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), update_menu_handler(conn, menu_id, req_body)))
+}
+
+/// This Route finds menus by a part of their name. /menus/search?q=soup
+/// Returns an empty list when nothing matches and BAD REQUEST when q is empty
+pub fn search_menus_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/"search")
+        .and(warp::get())
+        .and(with_db())
+        .and(warp::query::<MenuSearchQuery>())
+        .and_then(|conn: PooledConnection, query: MenuSearchQuery| with_timeout(request_timeout(), conn.cancel_handle(), search_menu_handler(conn, query.q)))
+}
+
+/// This Route lists the price, cost and margin of menus with a cost, highest margin first. /menus/margins
+/// Managers only, FORBIDDEN for everyone else
+pub fn menu_margins_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/"margins")
+        .and(warp::get())
+        .and(with_db())
+        .and(with_context())
+        .and_then(|conn: PooledConnection, context| with_timeout(request_timeout(), conn.cancel_handle(), menu_margins_handler(conn, context)))
+}
+
 ///  This Route creates a menu
-/// It expects a name in request POST body
+/// It expects a name in request POST body, optionally a price, category, kitchen station and cost
 pub fn create_menu_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
-    return warp::path!("menus"/"create")
+    warp::path!("menus"/"create")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), create_menu_handler(conn, req_body)))
+}
+
+/// This Route returns the menu of the day with its price. /menu-of-the-day?date=YYYY-MM-DD
+/// The date defaults to today. A scheduled menu wins, otherwise available menus rotate by day
+pub fn menu_of_the_day_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menu-of-the-day")
+        .and(warp::get())
+        .and(with_db())
+        .and(warp::query::<MenuOfTheDayQuery>())
+        .and(with_clock())
+        .and_then(|conn: PooledConnection, query: MenuOfTheDayQuery, clock| with_timeout(request_timeout(), conn.cancel_handle(), menu_of_the_day_handler(conn, query.date, clock)))
+}
+
+/// This Route schedules the menu of the day for a date. /menu-of-the-day/{date}
+/// It expects a menu_id in the PUT body
+pub fn schedule_menu_of_the_day_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menu-of-the-day"/String)
+        .and(warp::put())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|date, conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), schedule_menu_of_the_day_handler(conn, date, req_body)))
+}
+
+/// This Route saves the current menus and prices under a name. /menu-snapshots
+/// It expects {"name": ..} in the POST body, an existing name returns CONFLICT
+pub fn create_menu_snapshot_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menu-snapshots")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), create_menu_snapshot_handler(conn, req_body)))
+}
+
+/// This Route puts the menus back as they were in a snapshot. /menu-snapshots/{name}/restore
+/// Menus created after the snapshot are made unavailable
+pub fn restore_menu_snapshot_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menu-snapshots"/String/"restore")
+        .and(warp::post())
+        .and(with_db())
+        .and_then(|name, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), restore_menu_snapshot_handler(conn, name)))
+}
+
+/// This Route 86es a menu, it becomes unavailable at once. /menus/{menu_id}/86
+/// Returns the tables still waiting for it and pushes an item_86ed event for each of their orders
+pub fn eighty_six_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/i64/"86")
+        .and(warp::post())
+        .and(with_db())
+        .and(with_context())
+        .and_then(|menu_id, conn: PooledConnection, context| with_timeout(request_timeout(), conn.cancel_handle(), eighty_six_handler(conn, menu_id, context)))
+}
+
+/// This Route archives a menu, it leaves the menu listings and can no longer be ordered. /menus/{menu_id}/archive
+/// Past orders keep its name. Returns NOT FOUND for an unknown menu
+pub fn archive_menu_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/i64/"archive")
+        .and(warp::post())
+        .and(with_db())
+        .and_then(|menu_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), archive_menu_handler(conn, menu_id)))
+}
+
+/// This Route merges a duplicate menu into another. /menus/{keep_id}/merge/{remove_id}
+/// Order items of the removed menu move to the kept one, then the removed menu is deleted
+pub fn merge_menus_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/i64/"merge"/i64)
+        .and(warp::post())
+        .and(with_db())
+        .and_then(|keep_id, remove_id, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), merge_menus_handler(conn, keep_id, remove_id)))
+}
+
+/// This Route raises or lowers menu prices by a percentage. /menus/prices/adjust
+/// It expects {"percent": f64, "category": optional} in the POST body and the manager role
+pub fn adjust_prices_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/"prices"/"adjust")
         .and(warp::post())
         .and(with_db())
-        .and(warp::body::json())
-        .and_then(|conn, req_body| create_menu_handler(conn, req_body))
+        .and(json_body())
+        .and(with_context())
+        .and_then(|conn: PooledConnection, req_body, context| with_timeout(request_timeout(), conn.cancel_handle(), adjust_prices_handler(conn, req_body, context)))
+}
+
+/// This Route marks all menus of a category as available or not. /menus/category/{category}/availability
+/// It expects {"available": bool} in the PUT body. Unavailable menus cannot be ordered
+pub fn set_category_availability_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("menus"/"category"/String/"availability")
+        .and(warp::put())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|category, conn: PooledConnection, req_body: AvailabilityUpdate| with_timeout(request_timeout(), conn.cancel_handle(), set_category_availability_handler(conn, category, req_body.available)))
+}
+
+/// This Route returns orders served and revenue per table. /reports/tables?from=YYYY-MM-DD&to=YYYY-MM-DD
+pub fn table_performance_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reports"/"tables")
+        .and(warp::get())
+        .and(with_db())
+        .and(warp::query::<DateRangeQuery>())
+        .and_then(|conn: PooledConnection, range: DateRangeQuery| with_timeout(report_timeout(), conn.cancel_handle(), table_performance_handler(conn, range.from, range.to)))
+}
+
+/// This Route returns the end of day report. /reports/eod/{date}, date formatted as YYYY-MM-DD
+/// Revenue is net sales, tax and service charge are not included
+pub fn eod_report_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reports"/"eod"/String)
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|date, conn: PooledConnection| with_timeout(report_timeout(), conn.cancel_handle(), eod_report_handler(conn, date)))
+}
+
+/// This Route returns the covers and spend per cover of a day. /reports/covers/{date}, date formatted as YYYY-MM-DD
+pub fn covers_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reports"/"covers"/String)
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|date, conn: PooledConnection| with_timeout(report_timeout(), conn.cancel_handle(), covers_handler(conn, date)))
+}
+
+/// This Route returns the average and median table turnover of a day. /reports/turnover/{date}, date formatted as YYYY-MM-DD
+pub fn turnover_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reports"/"turnover"/String)
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|date, conn: PooledConnection| with_timeout(report_timeout(), conn.cancel_handle(), turnover_handler(conn, date)))
+}
+
+/// This Route compares estimated and actual preparation minutes per menu. /reports/prep-accuracy
+pub fn prep_accuracy_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("reports"/"prep-accuracy")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|conn: PooledConnection| with_timeout(report_timeout(), conn.cancel_handle(), prep_accuracy_handler(conn)))
+}
+
+/// This Route lists the items of running orders within a cooking time range. /kitchen/items?min=5&max=15
+/// Both ends are in minutes and inclusive, min greater than max is a BAD_REQUEST
+pub fn items_by_time_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("kitchen"/"items")
+        .and(warp::get())
+        .and(warp::query::<CookingTimeQuery>())
+        .and(with_db())
+        .and_then(|query: CookingTimeQuery, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), items_by_time_handler(conn, query.min, query.max)))
+}
+
+/// This Route lists the pending and cooking items of one kitchen station. /kitchen/stations/{station}/queue
+/// Menus without a station are prepared at the "default" station
+pub fn station_queue_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("kitchen"/"stations"/String/"queue")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|station, conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), station_queue_handler(conn, station)))
+}
+
+/// This Route streams order events as server sent events. /events
+/// The stream stays open, so it is not wrapped in a request timeout
+pub fn events_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("events")
+        .and(warp::get())
+        .and_then(events_handler)
+}
+
+/// This Route streams the open items and then order events over a websocket. /ws/kitchen
+/// The socket stays open, so it is not wrapped in a request timeout
+pub fn kitchen_ws_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("ws"/"kitchen")
+        .and(warp::ws())
+        .and(with_db())
+        .and_then(|ws, conn| kitchen_ws_handler(conn, ws))
+}
+
+/// This Route lists the items currently cooking with their elapsed time. /kitchen/pass
+pub fn on_the_pass_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("kitchen"/"pass")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), on_the_pass_handler(conn)))
+}
+
+/// This Route lists the ready items waiting to be served, grouped by table. /kitchen/ready
+pub fn ready_to_serve_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("kitchen"/"ready")
+        .and(warp::get())
+        .and(with_db())
+        .and(with_clock())
+        .and_then(|conn: PooledConnection, clock| with_timeout(request_timeout(), conn.cancel_handle(), ready_to_serve_handler(conn, clock)))
+}
+
+/// This Route returns how many portions the kitchen served per minute lately. /kitchen/throughput?window_minutes=15
+/// The window defaults to THROUGHPUT_WINDOW_MINUTES, BAD REQUEST outside 1 to 1440 minutes
+pub fn throughput_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("kitchen"/"throughput")
+        .and(warp::get())
+        .and(with_db())
+        .and(warp::query::<ThroughputQuery>())
+        .and(with_clock())
+        .and_then(|conn: PooledConnection, query: ThroughputQuery, clock| with_timeout(request_timeout(), conn.cancel_handle(), throughput_handler(conn, query.window_minutes.unwrap_or(THROUGHPUT_WINDOW_MINUTES), clock)))
+}
+
+/// This Route imports tables and menus in one go. /import
+/// It expects {"tables": [{"code": ..}], "menus": [{"name": .., "price": .., "category": ..}]} in the POST body
+pub fn import_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("import")
+        .and(warp::post())
+        .and(with_db())
+        .and(json_body())
+        .and_then(|conn: PooledConnection, req_body| with_timeout(request_timeout(), conn.cancel_handle(), import_handler(conn, req_body)))
+}
+
+/// Token /backup and /restore expect in the x-backup-token header, from BACKUP_TOKEN. Both routes are off without it
+fn backup_token() -> Option<String> {
+    std::env::var("BACKUP_TOKEN").ok().filter(|token| !token.is_empty())
+}
+
+/// Rejects as NOT_FOUND when no token is configured, so the route looks like it does not exist, and as
+/// FORBIDDEN unless the x-backup-token header matches it
+pub(crate) fn require_backup_token(expected: Option<String>) -> impl Filter<Extract = (), Error = Rejection> + Clone {
+    warp::header::optional::<String>("x-backup-token")
+        .and_then(move |sent: Option<String>| {
+            let expected = expected.clone();
+            async move {
+                match (expected, sent) {
+                    (None, _) => Err(warp::reject::not_found()),
+                    (Some(expected), Some(sent)) if tokens_match(&expected, &sent) => Ok(()),
+                    _ => Err(warp::reject::custom(ApiError::Forbidden("a valid x-backup-token is required".to_string()))),
+                }
+            }
+        })
+        .untuple_one()
+}
+
+/// Compares every byte, so the time taken does not tell how much of a guessed token was right
+fn tokens_match(expected: &str, sent: &str) -> bool {
+    expected.len() == sent.len() && expected.bytes().zip(sent.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+/// This Route returns every table's rows as one JSON document, customer details included. /backup
+/// It expects the x-role: manager header and the x-backup-token header matching BACKUP_TOKEN, NOT FOUND when
+/// BACKUP_TOKEN is not set
+pub fn backup_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("backup")
+        .and(warp::get())
+        .and(require_backup_token(backup_token()))
+        .and(with_db())
+        .and(with_context())
+        .and_then(|conn: PooledConnection, context| with_timeout(request_timeout(), conn.cancel_handle(), backup_handler(conn, context)))
+}
+
+/// This Route wipes all data and loads a document from /backup in one transaction. /restore
+/// It expects the backup in the POST body and the same headers as /backup. There is no timeout: the restore
+/// cannot be stopped halfway, answering 504 while it goes on to commit would tell the client it failed
+pub fn restore_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("restore")
+        .and(warp::post())
+        .and(require_backup_token(backup_token()))
+        .and(with_db())
+        .and(json_body())
+        .and(with_context())
+        .and_then(restore_handler)
+}
+
+/// This Route lists the order and item statuses the API accepts. /enums
+pub fn enums_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("enums")
+        .and(warp::get())
+        .and_then(enums_handler)
+}
+
+/// This Route tells whether the server can serve requests, its database included. /health
+/// Returns SERVICE UNAVAILABLE when the database does not answer
+pub fn health_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("health")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), health_handler(conn)))
+}
+
+/// This Route tells whether the server is running. /live
+pub fn live_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("live")
+        .and(warp::get())
+        .and_then(live_handler)
+}
+
+/// This Route lists the slowest SQL statements seen since startup. /debug/slow-queries
+/// Only served with DEBUG_ENDPOINTS on, NOT_FOUND otherwise
+pub fn slow_queries_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("debug"/"slow-queries")
+        .and(warp::get())
+        .and(debug_only())
+        .and_then(slow_queries_handler)
+}
+
+/// This Route lists all settings
+pub fn list_settings_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("settings")
+        .and(warp::get())
+        .and(with_db())
+        .and_then(|conn: PooledConnection| with_timeout(request_timeout(), conn.cancel_handle(), list_settings_handler(conn)))
+}
+
+/// This Route changes a setting. /settings/{key}
+/// It expects a value in the PUT body and the x-role: manager header
+pub fn update_setting_route() -> impl Filter<Extract = impl Reply, Error = Rejection> + Clone {
+    warp::path!("settings"/String)
+        .and(warp::put())
+        .and(with_db())
+        .and(json_body())
+        .and(with_context())
+        .and_then(|key, conn: PooledConnection, req_body, context| with_timeout(request_timeout(), conn.cancel_handle(), update_setting_handler(conn, key, req_body, context)))
 }
 
 /// Combine all routes
 pub fn restaurent_routes()->impl Filter<Extract = impl Reply, Error = Rejection> + Clone{
     let routes = create_order_route()
+    .or(batch_create_orders_route())
+    .or(move_order_route())
+    .or(modify_order_route())
     .or(create_table_route())
+    .or(create_next_table_route())
+    .or(create_tables_bulk_route())
     .or(create_menu_route())
     .or(list_tables_route())
+    .or(unused_tables_route())
+    .or(list_tables_with_status_route())
+    .or(list_menus_by_category_route())
     .or(list_menus_route())
+    .or(list_menu_categories_route())
+    .or(search_menus_route())
+    .or(menu_margins_route())
+    .or(get_menu_route())
+    .or(update_menu_route())
+    .or(set_category_availability_route())
+    .or(menu_of_the_day_route())
+    .or(create_menu_snapshot_route())
+    .or(restore_menu_snapshot_route())
+    .or(merge_menus_route())
+    .or(eighty_six_route())
+    .or(archive_menu_route())
+    .or(adjust_prices_route())
+    .or(schedule_menu_of_the_day_route())
     .or(list_all_orders_route())
+    .or(get_order_by_number_route())
+    .or(floor_status_route())
+    .or(order_diff_route())
+    .or(get_order_total_route())
     .or(delete_item_from_order_route())
+    .or(delete_order_route())
+    .or(void_order_route())
+    .or(update_order_status_route())
+    .or(order_ticket_route())
+    .or(pay_order_route())
+    .or(redeem_points_route())
+    .or(reprint_receipt_route())
+    .or(generate_bill_route())
+    .or(order_audit_route())
+    .or(order_timeline_route())
+    .or(attach_customer_route())
+    .or(create_customer_route())
+    .or(customer_history_route())
+    .or(customer_points_route())
     .or(list_order_items_for_table_route())
-    .or(get_item_from_order_route());
+    .or(get_item_from_order_route())
+    .or(set_order_item_quantity_route())
+    .or(update_item_status_route())
+    .or(serve_table_route())
+    .or(comp_item_route())
+    .or(tip_suggestions_route())
+    .or(estimated_wait_route())
+    .or(table_eta_route())
+    .or(order_eta_route())
+    .or(order_prep_breakdown_route())
+    .or(table_performance_route())
+    .or(eod_report_route())
+    .or(covers_route())
+    .or(turnover_route())
+    .or(prep_accuracy_route())
+    .or(on_the_pass_route())
+    .or(events_route())
+    .or(kitchen_ws_route())
+    .or(ready_to_serve_route())
+    .or(throughput_route())
+    .or(station_queue_route())
+    .or(items_by_time_route())
+    .or(import_route())
+    .or(backup_route())
+    .or(restore_route())
+    .or(enums_route())
+    .or(slow_queries_route())
+    .or(health_route())
+    .or(live_route())
+    .or(list_settings_route())
+    .or(update_setting_route());
 
     routes.recover(handle_rejection)
 }
\ No newline at end of file
@@ -0,0 +1,46 @@
+// src/server.rs
+use crate::{logging, routes};
+use std::future::Future;
+use std::net::SocketAddr;
+use warp::Filter;
+
+/// Bind all routes to `addr` and return the address actually bound, port 0 picks a free one, with the server
+/// future. Once `shutdown` completes the server stops accepting connections and the future ends when the requests
+/// in flight are done, so a running transaction gets to commit
+pub fn bind(
+    addr: impl Into<SocketAddr> + 'static,
+    shutdown: impl Future<Output = ()> + Send + 'static,
+) -> (SocketAddr, impl Future<Output = ()>) {
+    let routes = routes::restaurent_routes().with(logging::requests());
+    warp::serve(routes).bind_with_graceful_shutdown(addr, shutdown)
+}
+
+/// Completes on Ctrl-C, or on SIGTERM on unix
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(err) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", err);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("shutting down");
+}
@@ -0,0 +1,44 @@
+// src/slow_queries.rs
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+
+/// How many of the slowest statements are kept
+const SLOW_QUERY_CAPACITY: usize = 20;
+
+/// A statement and how long SQLite took to run it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowQuery {
+    pub sql: String,
+    pub duration_ms: f64,
+}
+
+fn slowest() -> &'static Mutex<Vec<SlowQuery>> {
+    static SLOWEST: OnceLock<Mutex<Vec<SlowQuery>>> = OnceLock::new();
+    SLOWEST.get_or_init(|| Mutex::new(Vec::with_capacity(SLOW_QUERY_CAPACITY)))
+}
+
+/// Profile callback for the connections. Keeps the statement when it is among the slowest seen so far,
+/// pushing out the quickest one. SQLite times statements to the millisecond, quicker ones report zero and are skipped
+pub fn record(sql: &str, duration: Duration) {
+    if duration.is_zero() {
+        return;
+    }
+    let duration_ms = duration.as_secs_f64() * 1000.0;
+    let mut queries = slowest().lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if queries.len() == SLOW_QUERY_CAPACITY {
+        match queries.last() {
+            Some(quickest) if quickest.duration_ms >= duration_ms => return,
+            _ => {
+                queries.pop();
+            }
+        }
+    }
+    let position = queries.partition_point(|query| query.duration_ms >= duration_ms);
+    queries.insert(position, SlowQuery { sql: sql.to_string(), duration_ms });
+}
+
+/// The slowest statements since startup, slowest first
+pub fn list() -> Vec<SlowQuery> {
+    slowest().lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
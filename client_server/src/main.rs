@@ -6,14 +6,14 @@ use tokio::time::{Duration, timeout};
 
 async fn create_tables()->Vec<i64>{
     let client = Client::new();
-    let table_codes = vec!["T-01", "T-02", "T-03", "T-04", "T-05"];
+    let table_codes = ["T-01", "T-02", "T-03", "T-04", "T-05"];
     let mut table_ids = Vec::new();
 
-    for index in 0..5 {
+    for code in table_codes {
         // Simulate creating a table
         let response: Value = client
             .post("http://localhost:3030/tables/create")
-            .json(&serde_json::json!({"code": table_codes[index]}))
+            .json(&serde_json::json!({"code": code}))
             .send()
             .await
             .expect("Failed to create table")
@@ -25,7 +25,7 @@ async fn create_tables()->Vec<i64>{
         table_ids.push(response["id"].as_i64().expect("Missing or invalid id"));
     }
 
-    return table_ids;
+    table_ids
 }
 
 async fn create_menus()->Vec<i64>{
@@ -33,11 +33,11 @@ async fn create_menus()->Vec<i64>{
     let menu_names = ["Menu-01", "Menu-02", "Menu-03", "Menu-04", "Menu-05"];
     let mut menu_ids = Vec::new();
 
-    for index in 0..5 {
+    for name in menu_names {
         // Simulate creating a menu
         let response: Value = client
             .post("http://localhost:3030/menus/create")
-            .json(&serde_json::json!({"name": menu_names[index]}))
+            .json(&serde_json::json!({"name": name}))
             .send()
             .await
             .expect("Failed to create table")
@@ -49,7 +49,7 @@ async fn create_menus()->Vec<i64>{
         menu_ids.push(response["id"].as_i64().expect("Missing or invalid id"));
     }
 
-    return menu_ids;
+    menu_ids
 }
 
 
@@ -82,7 +82,7 @@ async fn order_simulation(client: &Client, table_ids: &[i64], menu_ids: &[i64])
 
                 // 2. Get All Items from Order by Table ID
                 let response = client
-                    .get(&format!("http://localhost:3030/tables/{}/items", table_id))
+                    .get(format!("http://localhost:3030/tables/{}/items", table_id))
                     .send()
                     .await
                     .expect("Failed to get all items")
@@ -112,7 +112,7 @@ async fn order_simulation(client: &Client, table_ids: &[i64], menu_ids: &[i64])
                 // 3. Get Specific Item from Table
                 if let Some(menu_id) = menu_subarray.first() {
                     let response = client
-                        .get(&format!("http://localhost:3030/tables/{}/items/{}", table_id, *menu_id))
+                        .get(format!("http://localhost:3030/tables/{}/items/{}", table_id, *menu_id))
                         .send()
                         .await
                         .expect("Failed to get specific item")
@@ -127,7 +127,7 @@ async fn order_simulation(client: &Client, table_ids: &[i64], menu_ids: &[i64])
                 // 4. Remove One Item from Table
                 if let Some(menu_id) = menu_subarray.first() {
                     let response = client
-                        .delete(&format!("http://localhost:3030/orders/{}/items/{}", table_id, *menu_id))
+                        .delete(format!("http://localhost:3030/orders/{}/items/{}", table_id, *menu_id))
                         .send()
                         .await
                         .expect("Failed to remove item")